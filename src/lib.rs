@@ -1,240 +1,10451 @@
 #![no_std]
 pub extern crate paste;
 
+// The crates of the integrations the generated code refers to, so the crate using the macros
+// doesn't have to depend on them itself.
+#[doc(hidden)]
+pub mod __private {
+    #[cfg(feature = "arbitrary")]
+    pub use arbitrary;
+
+    #[cfg(feature = "bytemuck")]
+    pub use bytemuck;
+
+    #[cfg(feature = "defmt")]
+    pub use defmt;
+
+    #[cfg(feature = "rkyv")]
+    pub use rkyv;
+
+    #[cfg(feature = "serde")]
+    pub use serde;
+
+    #[cfg(feature = "ufmt")]
+    pub use ufmt;
+
+    #[cfg(feature = "zerocopy")]
+    pub use zerocopy;
+}
+
+use core::sync::atomic::Ordering;
+
+/// Describes a field of a struct generated by these macros, see the `FIELDS` constant.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FieldInfo {
+    /// The name of the field.
+    pub name: &'static str,
+    /// The offset of the field in bytes.
+    pub offset: usize,
+    /// The number of bytes the field spans.
+    pub size: usize,
+}
+
+/// Describes a struct generated by these macros as a value, see the `LAYOUT` constant, so
+/// dumpers and validators can handle any of them the same way.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Layout {
+    /// The name of the struct.
+    pub name: &'static str,
+    /// The size of the struct in bytes.
+    pub size: usize,
+    /// The fields of the struct in declaration order, without reserved regions.
+    pub fields: &'static [FieldDescriptor],
+}
+
+impl Layout {
+    /// Looks up a field by its name.
+    pub fn field(&self, name: &str) -> Option<&'static FieldDescriptor> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    /// Finds the field covering the byte at `offset`, the first one declared if several do.
+    pub fn field_at(&self, offset: usize) -> Option<&'static FieldDescriptor> {
+        self.fields.iter().find(|field| field.offset <= offset && offset < field.offset + field.size)
+    }
+}
+
+/// Describes a field of a struct in its [`Layout`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FieldDescriptor {
+    /// The name of the field.
+    pub name: &'static str,
+    /// The offset of the field in bytes.
+    pub offset: usize,
+    /// The number of bytes the field spans.
+    pub size: usize,
+    /// The type of the field as it's written in the declaration, e.g. `*mut LIST_ENTRY`.
+    pub type_name: &'static str,
+}
+
+impl FieldDescriptor {
+    /// The name, offset and size of the field, as they're listed in `FIELDS`.
+    pub const fn info(&self) -> FieldInfo {
+        FieldInfo { name: self.name, offset: self.offset, size: self.size }
+    }
+}
+
+/// The invariant a struct failed to satisfy in its generated `validate` method.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct LayoutError {
+    /// The name of the struct.
+    pub struct_name: &'static str,
+    /// The name of the field the invariant was declared on.
+    pub field: &'static str,
+    /// The invariant as it was written, e.g. `magic == 0x5A4D`.
+    pub invariant: &'static str,
+}
+
+impl core::fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "`{}::{}` doesn't satisfy `{}`", self.struct_name, self.field, self.invariant)
+    }
+}
+
+impl core::error::Error for LayoutError {}
+
+/// Why the offsets of a struct declared with `offset_dynamic!` couldn't be read from a layout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OffsetsError {
+    /// The layout doesn't describe the struct.
+    MissingStruct(&'static str),
+    /// The layout doesn't have an offset for a field.
+    MissingField {
+        /// The name of the struct.
+        struct_name: &'static str,
+        /// The name of the field.
+        field: &'static str,
+    },
+    /// A field doesn't fit within the size the layout gives the struct.
+    OutOfBounds {
+        /// The name of the struct.
+        struct_name: &'static str,
+        /// The name of the field.
+        field: &'static str,
+        /// The offset of the field.
+        offset: usize,
+        /// The size of the struct.
+        size: usize,
+    },
+}
+
+impl core::fmt::Display for OffsetsError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::MissingStruct(struct_name) => write!(f, "`{}` isn't in the layout", struct_name),
+            Self::MissingField { struct_name, field } => write!(f, "`{}::{}` isn't in the layout", struct_name, field),
+            Self::OutOfBounds { struct_name, field, offset, size } => {
+                write!(f, "`{}::{}` at {:#x} doesn't fit in the {:#x} bytes of `{}`", struct_name, field, offset, size, struct_name)
+            }
+        }
+    }
+}
+
+impl core::error::Error for OffsetsError {}
+
+/// A difference between the fields of two structs, see `layout_diff!`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FieldChange {
+    /// A field only the new struct has.
+    Added(FieldInfo),
+    /// A field only the old struct has.
+    Removed(FieldInfo),
+    /// A field both structs have, but at different offsets or with different sizes.
+    Moved {
+        /// The field in the old struct.
+        old: FieldInfo,
+        /// The field in the new struct.
+        new: FieldInfo,
+    },
+}
+
+impl core::fmt::Display for FieldChange {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Added(new) => write!(f, "`{}` added at {:#x}..{:#x}", new.name, new.offset, new.offset + new.size),
+            Self::Removed(old) => write!(f, "`{}` removed from {:#x}..{:#x}", old.name, old.offset, old.offset + old.size),
+            Self::Moved { old, new } => write!(
+                f,
+                "`{}` moved from {:#x}..{:#x} to {:#x}..{:#x}",
+                old.name,
+                old.offset,
+                old.offset + old.size,
+                new.offset,
+                new.offset + new.size
+            ),
+        }
+    }
+}
+
+/// Iterates over the differences between the fields of two structs, see `layout_diff!`.
+///
+/// Fields are matched by name. The removed and moved fields come first in the order of the old
+/// struct, followed by the added fields in the order of the new one.
+#[derive(Clone, Debug)]
+pub struct LayoutDiff<'a> {
+    old: &'a [FieldInfo],
+    new: &'a [FieldInfo],
+    index: usize,
+}
+
+impl<'a> LayoutDiff<'a> {
+    /// Compares two lists of fields, e.g. the `FIELDS` constants of two structs.
+    pub const fn new(old: &'a [FieldInfo], new: &'a [FieldInfo]) -> Self {
+        Self { old, new, index: 0 }
+    }
+}
+
+impl Iterator for LayoutDiff<'_> {
+    type Item = FieldChange;
+
+    fn next(&mut self) -> Option<FieldChange> {
+        let find = |fields: &[FieldInfo], name: &str| fields.iter().copied().find(|field| field.name == name);
+
+        while self.index < self.old.len() + self.new.len() {
+            let index = self.index;
+            self.index += 1;
+
+            match index.checked_sub(self.old.len()) {
+                None => {
+                    let old = self.old[index];
+                    match find(self.new, old.name) {
+                        None => return Some(FieldChange::Removed(old)),
+                        Some(new) if new.offset != old.offset || new.size != old.size => {
+                            return Some(FieldChange::Moved { old, new });
+                        }
+                        Some(_) => {}
+                    }
+                }
+                Some(index) => {
+                    let new = self.new[index];
+                    if find(self.old, new.name).is_none() {
+                        return Some(FieldChange::Added(new));
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Formats the bytes of a field as hex in the alternate Debug output of the generated structs.
+///
+/// Fields of 1, 2, 4, 8 or 16 bytes are shown as a number in the byte order of the target, e.g.
+/// `0xdeadbeef`, all others as their bytes in memory order, e.g. `[4d 5a 90]`.
+#[doc(hidden)]
+pub struct HexBytes<'a>(pub &'a [u8]);
+
+impl core::fmt::Debug for HexBytes<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let length = self.0.len();
+        match length {
+            1 | 2 | 4 | 8 | 16 => {
+                let mut bytes = [0; 16];
+                let value = if cfg!(target_endian = "little") {
+                    bytes[..length].copy_from_slice(self.0);
+                    u128::from_le_bytes(bytes)
+                } else {
+                    bytes[16 - length..].copy_from_slice(self.0);
+                    u128::from_be_bytes(bytes)
+                };
+                write!(f, "{:#x}", value)
+            }
+            _ => HexDump(self.0).fmt(f),
+        }
+    }
+}
+
+/// Lists the bytes of a struct that aren't covered by any of its fields in its Debug output, see
+/// the `debug_padding` option.
+#[doc(hidden)]
+pub struct DebugPadding<'a>(pub &'a [u8], pub &'a [FieldInfo]);
+
+impl core::fmt::Debug for DebugPadding<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let covered = |offset: usize| {
+            self.1.iter().any(|field| field.offset <= offset && offset < field.offset + field.size)
+        };
+
+        let mut debug = f.debug_map();
+        let mut offset = 0;
+        while offset < self.0.len() {
+            if covered(offset) {
+                offset += 1;
+                continue;
+            }
+
+            let start = offset;
+            while offset < self.0.len() && !covered(offset) {
+                offset += 1;
+            }
+            debug.entry(&format_args!("{:#x}..{:#x}", start, offset), &HexDump(&self.0[start..offset]));
+        }
+        debug.finish()
+    }
+}
+
+/// Formats the bytes of a large field as its first bytes and its length in the Debug output of the
+/// generated structs, see the `debug_truncate` option. Smaller fields are shown like `HexBytes`.
+#[doc(hidden)]
+pub struct DebugTruncated<'a>(pub &'a [u8]);
+
+impl DebugTruncated<'_> {
+    /// The number of bytes shown of a truncated field.
+    pub const LIMIT: usize = 16;
+}
+
+impl core::fmt::Debug for DebugTruncated<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if self.0.len() <= Self::LIMIT {
+            return HexBytes(self.0).fmt(f);
+        }
+        HexDump(&self.0[..Self::LIMIT]).fmt(f)?;
+        write!(f, " ... ({:#x} bytes)", self.0.len())
+    }
+}
+
+/// Writes the table of the generated `describe` methods, with the fields and reserved regions
+/// ordered by offset and the bytes between them listed as padding.
+#[doc(hidden)]
+pub fn describe_layout(
+    writer: &mut impl core::fmt::Write,
+    name: &str,
+    size: usize,
+    fields: &[FieldInfo],
+    reserved: &[FieldInfo],
+) -> core::fmt::Result {
+    let count = fields.len() + reserved.len();
+    let entry = |index: usize| match index.checked_sub(fields.len()) {
+        Some(index) => (&reserved[index], " (reserved)"),
+        None => (&fields[index], ""),
+    };
+
+    writeln!(writer, "{} ({:#x} bytes)", name, size)?;
+    writeln!(writer, "{:<8} {:<8} field", "offset", "size")?;
+
+    // Overlapping fields and bitfields can be declared out of order, so the entries are visited
+    // by offset, and by declaration order for equal offsets.
+    let mut previous = None;
+    let mut end = 0;
+    for _ in 0..count {
+        let next = (0..count)
+            .map(|index| (entry(index).0.offset, index))
+            .filter(|&key| previous.is_none_or(|previous| key > previous))
+            .min();
+        let Some((offset, index)) = next else {
+            break;
+        };
+        previous = next;
+
+        if offset > end {
+            writeln!(writer, "{:<#8x} {:<#8x} (padding)", end, offset - end)?;
+        }
+
+        let (field, suffix) = entry(index);
+        writeln!(writer, "{:<#8x} {:<#8x} {}{}", field.offset, field.size, field.name, suffix)?;
+        end = end.max(field.offset + field.size);
+    }
+
+    if size > end {
+        writeln!(writer, "{:<#8x} {:<#8x} (padding)", end, size - end)?;
+    }
+    Ok(())
+}
+
+/// Writes the JSON of the generated `layout_json` methods, in the format `verify_layout!` and
+/// `from_json` read. Names are identifiers, so they don't need escaping.
+#[cfg(feature = "json")]
+#[doc(hidden)]
+pub fn write_layout_json(writer: &mut impl core::fmt::Write, name: &str, size: usize, fields: &[FieldInfo]) -> core::fmt::Result {
+    writeln!(writer, "{{")?;
+    writeln!(writer, "    \"{}\": {{", name)?;
+    writeln!(writer, "        \"size\": {},", size)?;
+    writeln!(writer, "        \"fields\": {{")?;
+    for (index, field) in fields.iter().enumerate() {
+        let separator = if index + 1 < fields.len() { "," } else { "" };
+        writeln!(writer, "            \"{}\": {{ \"offset\": {}, \"size\": {} }}{}", field.name, field.offset, field.size, separator)?;
+    }
+    writeln!(writer, "        }}")?;
+    writeln!(writer, "    }}")?;
+    writeln!(writer, "}}")
+}
+
+/// Writes the C declaration of the generated `c_declaration` methods. Every field is given with
+/// its kind, e.g. `"field"` or `"bits"`, and the name of its Rust type, which is translated where
+/// C has an equivalent and declared as a byte array otherwise.
+#[doc(hidden)]
+pub fn write_c_declaration(
+    writer: &mut impl core::fmt::Write,
+    name: &str,
+    size: usize,
+    fields: &[(&str, FieldInfo, &str)],
+) -> core::fmt::Result {
+    writeln!(writer, "#pragma pack(push, 1)")?;
+    writeln!(writer, "struct {} {{", name)?;
+
+    // Like in `describe_layout`, the fields are visited by offset, and by declaration order for
+    // equal offsets.
+    let mut previous = None;
+    let mut end = 0;
+    let mut bits = 0;
+    let mut flexible = None;
+    for _ in 0..fields.len() {
+        let next = (0..fields.len())
+            .map(|index| (fields[index].1.offset, index))
+            .filter(|&key| previous.is_none_or(|previous| key > previous))
+            .min();
+        let Some((offset, index)) = next else {
+            break;
+        };
+        previous = next;
+
+        let (kind, field, ty) = fields[index];
+        let field_name = field.name.trim_start_matches("r#");
+        if offset > end {
+            writeln!(writer, "    char _padding_{:#x}[{:#x}];", end, offset - end)?;
+        }
+
+        // Bitfields share their storage, which is declared once under a name of its own, since C
+        // doesn't guarantee the order of bits in its own bitfields.
+        if kind == "bits" {
+            if offset >= end {
+                bits = offset;
+                match c_type(ty) {
+                    Some((c_name, c_size)) if c_size == field.size => writeln!(writer, "    {} _bits_{:#x}; /* {:#x} */", c_name, offset, offset)?,
+                    _ => writeln!(writer, "    char _bits_{:#x}[{:#x}]; /* {:#x} */", offset, field.size, offset)?,
+                }
+                end = offset + field.size;
+            }
+            writeln!(writer, "    /* {}: bits of _bits_{:#x} */", field_name, bits)?;
+            continue;
+        }
+
+        if offset < end {
+            writeln!(writer, "    /* {:#x} {}: {}, overlapping the fields before */", offset, field_name, ty)?;
+            end = end.max(offset + field.size);
+            continue;
+        }
+
+        // C only allows a flexible array as the last member, after the padding at the end.
+        if kind == "flexible" {
+            flexible = Some((field_name, offset, ty));
+            continue;
+        }
+
+        let comment = if kind == "reserved" { ", reserved" } else { "" };
+        let pointee = ty.strip_prefix("*mut").or_else(|| ty.strip_prefix("*const")).map(str::trim);
+        let array = ty.strip_prefix('[').and_then(|ty| ty.strip_suffix(']')).and_then(|ty| ty.split_once(';'));
+        match (c_type(ty), pointee, array) {
+            (Some((c_name, c_size)), _, _) if c_size == field.size => {
+                writeln!(writer, "    {} {}; /* {:#x}{} */", c_name, field_name, offset, comment)?
+            }
+            (_, Some(pointee), _) if field.size == core::mem::size_of::<usize>() => {
+                let c_name = c_type(pointee).map_or("void", |(c_name, _)| c_name);
+                writeln!(writer, "    {} *{}; /* {:#x}{} */", c_name, field_name, offset, comment)?
+            }
+            (_, _, Some((element, _))) if kind != "stride" && c_type(element.trim()).is_some_and(|(_, c_size)| field.size % c_size == 0) => {
+                let (c_name, c_size) = c_type(element.trim()).unwrap_or(("char", 1));
+                writeln!(writer, "    {} {}[{}]; /* {:#x}{} */", c_name, field_name, field.size / c_size, offset, comment)?
+            }
+            _ => writeln!(writer, "    char {}[{:#x}]; /* {:#x}, {}{} */", field_name, field.size, offset, ty, comment)?,
+        }
+        end = offset + field.size;
+    }
+
+    if size > end {
+        writeln!(writer, "    char _padding_{:#x}[{:#x}];", end, size - end)?;
+    }
+    if let Some((field_name, offset, ty)) = flexible {
+        let element = ty.trim_start_matches('[').split(';').next().unwrap_or("").trim();
+        let c_name = c_type(element).map_or("char", |(c_name, _)| c_name);
+        writeln!(writer, "    {} {}[]; /* {:#x} */", c_name, field_name, offset)?;
+    }
+    writeln!(writer, "}};")?;
+    writeln!(writer, "#pragma pack(pop)")
+}
+
+// The C types with the size and layout of Rust's primitive types.
+fn c_type(ty: &str) -> Option<(&'static str, usize)> {
+    Some(match ty {
+        "u8" => ("uint8_t", 1),
+        "u16" => ("uint16_t", 2),
+        "u32" => ("uint32_t", 4),
+        "u64" => ("uint64_t", 8),
+        "i8" => ("int8_t", 1),
+        "i16" => ("int16_t", 2),
+        "i32" => ("int32_t", 4),
+        "i64" => ("int64_t", 8),
+        "usize" => ("uintptr_t", core::mem::size_of::<usize>()),
+        "isize" => ("intptr_t", core::mem::size_of::<isize>()),
+        "f32" => ("float", 4),
+        "f64" => ("double", 8),
+        "bool" => ("bool", 1),
+        _ => return None,
+    })
+}
+
+struct HexDump<'a>(&'a [u8]);
+
+impl core::fmt::Debug for HexDump<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("[")?;
+        for (index, byte) in self.0.iter().enumerate() {
+            if index > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{:02x}", byte)?;
+        }
+        f.write_str("]")
+    }
+}
+
+/// Builds the message of a failed layout check at compile time, since a constant can only panic
+/// with a string and not format numbers into it.
+#[doc(hidden)]
+pub struct CheckMessage {
+    bytes: [u8; 256],
+    length: usize,
+}
+
+impl CheckMessage {
+    pub const EMPTY: Self = Self { bytes: [0; 256], length: 0 };
+
+    pub const fn str(mut self, text: &str) -> Self {
+        let text = text.as_bytes();
+        let mut index = 0;
+        while index < text.len() && self.length < self.bytes.len() {
+            self.bytes[self.length] = text[index];
+            self.length += 1;
+            index += 1;
+        }
+        self
+    }
+
+    pub const fn hex(mut self, value: usize) -> Self {
+        self = self.str("0x");
+        let mut shift = usize::BITS;
+        loop {
+            shift -= 4;
+            if value >> shift != 0 || shift == 0 {
+                break;
+            }
+        }
+        loop {
+            let digit = (value >> shift & 0xf) as u8;
+            let digit = [if digit < 10 { b'0' + digit } else { b'a' + digit - 10 }];
+            self = self.str(match core::str::from_utf8(&digit) {
+                Ok(digit) => digit,
+                Err(_) => unreachable!(),
+            });
+            if shift == 0 {
+                return self;
+            }
+            shift -= 4;
+        }
+    }
+
+    pub const fn same(first: &str, second: &str) -> bool {
+        let (first, second) = (first.as_bytes(), second.as_bytes());
+        if first.len() != second.len() {
+            return false;
+        }
+
+        let mut index = 0;
+        while index < first.len() {
+            if first[index] != second[index] {
+                return false;
+            }
+            index += 1;
+        }
+        true
+    }
+
+    pub const fn as_str(&self) -> &str {
+        let (bytes, _) = self.bytes.split_at(self.length);
+        match core::str::from_utf8(bytes) {
+            Ok(text) => text,
+            Err(_) => "",
+        }
+    }
+}
+
+/// Whether a field of a dual struct holds pointers of its layout, which is the only kind of
+/// field whose size may differ between the two layouts.
+const fn dual_pointer(type_name: &str) -> bool {
+    let bytes = type_name.as_bytes();
+    CheckMessage::same(type_name, "Ptr") || (bytes.len() > 5 && bytes[0] == b'[' && bytes[1] == b'P' && bytes[2] == b't' && bytes[3] == b'r' && bytes[4] == b';')
+}
+
+/// Returns the name of the first field of a dual struct whose size differs between its layouts
+/// even though it isn't a `Ptr` or an array of them, see `#[offset(dual)]`.
+#[doc(hidden)]
+pub const fn dual_mismatch(narrow: &Layout, wide: &Layout) -> Option<&'static str> {
+    let mut index = 0;
+    while index < narrow.fields.len() && index < wide.fields.len() {
+        let (field, other) = (&narrow.fields[index], &wide.fields[index]);
+        if field.size != other.size && !(dual_pointer(field.type_name) && field.size * 2 == other.size) {
+            return Some(field.name);
+        }
+        index += 1;
+    }
+    None
+}
+
+/// Converts a dual struct to its other layout, zero-extending or truncating the pointers in its
+/// fields and zeroing the padding, see `#[offset(dual)]`.
+///
+/// # Safety
+///
+/// `from` and `to` have to be the `LAYOUT` of `F` and `T`, and `dual_mismatch` has to accept them.
+#[doc(hidden)]
+pub unsafe fn convert_dual<F, T>(value: &F, from: &Layout, to: &Layout) -> Result<T, core::num::TryFromIntError> {
+    let mut converted = core::mem::MaybeUninit::<T>::zeroed();
+    let source = unsafe { core::slice::from_raw_parts((value as *const F).cast::<u8>(), core::mem::size_of::<F>()) };
+    let target = unsafe { core::slice::from_raw_parts_mut(converted.as_mut_ptr().cast::<u8>(), core::mem::size_of::<T>()) };
+    for (field, other) in from.fields.iter().zip(to.fields) {
+        let source = &source[field.offset..][..field.size];
+        let target = &mut target[other.offset..][..other.size];
+        if field.size == other.size {
+            target.copy_from_slice(source);
+            continue;
+        }
+
+        // The sizes only differ for pointers, which are 4 bytes on one side and 8 on the other.
+        let count = field.size.min(other.size) / 4;
+        for (source, target) in source.chunks_exact(field.size / count).zip(target.chunks_exact_mut(other.size / count)) {
+            let pointer = match *source {
+                [a, b, c, d] => u64::from(u32::from_ne_bytes([a, b, c, d])),
+                _ => u64::from_ne_bytes(source.try_into().unwrap_or_default()),
+            };
+            match target.len() {
+                4 => target.copy_from_slice(&u32::try_from(pointer)?.to_ne_bytes()),
+                _ => target.copy_from_slice(&pointer.to_ne_bytes()),
+            }
+        }
+    }
+    Ok(unsafe { converted.assume_init() })
+}
+
+/// Parses the value of the environment variable `name` giving an offset, in decimal or in hex
+/// with a `0x` prefix, with `_` allowed between digits.
+#[doc(hidden)]
+pub const fn env_offset(name: &str, value: &str) -> usize {
+    let bytes = value.as_bytes();
+    let (radix, mut index) = match bytes {
+        [b'0', b'x' | b'X', ..] => (16, 2),
+        _ => (10, 0),
+    };
+
+    let mut offset: usize = 0;
+    let mut valid = index < bytes.len();
+    while valid && index < bytes.len() {
+        let digit = match bytes[index] {
+            digit @ b'0'..=b'9' => Some((digit - b'0') as usize),
+            digit @ b'a'..=b'f' if radix == 16 => Some((digit - b'a' + 10) as usize),
+            digit @ b'A'..=b'F' if radix == 16 => Some((digit - b'A' + 10) as usize),
+            b'_' => None,
+            _ => {
+                valid = false;
+                None
+            }
+        };
+
+        if let Some(digit) = digit {
+            match offset.checked_mul(radix) {
+                Some(shifted) if shifted.checked_add(digit).is_some() => offset = shifted + digit,
+                _ => valid = false,
+            }
+        }
+        index += 1;
+    }
+
+    if !valid {
+        let message = CheckMessage::EMPTY.str("the environment variable `").str(name).str("` is `").str(value).str("`, which isn't an offset");
+        panic!("{}", message.as_str());
+    }
+    offset
+}
+
+/// Checks the fields of a struct against a layout exported as JSON, see `verify_layout!`.
+#[doc(hidden)]
+pub const fn verify_layout(name: &str, key: &str, size: usize, fields: &[FieldInfo], layout: &str) {
+    let layout = layout.as_bytes();
+    let root = json::skip_whitespace(layout, 0);
+    let object = match json::member(layout, root, key) {
+        Some(object) => object,
+        None => match json::member(layout, root, "user_types") {
+            Some(types) => match json::member(layout, types, key) {
+                Some(object) => object,
+                None => panic_missing(name, None),
+            },
+            None => panic_missing(name, None),
+        },
+    };
+
+    let Some(members) = json::member(layout, object, "fields") else {
+        panic_missing(name, None);
+    };
+
+    let mut index = 0;
+    while index < fields.len() {
+        let field = fields[index];
+        let Some(member) = json::member(layout, members, field.name) else {
+            panic_missing(name, Some(field.name));
+        };
+
+        if let Some(expected) = json::member_number(layout, member, "offset") {
+            if expected != field.offset {
+                let message = CheckMessage::EMPTY
+                    .str("`")
+                    .str(name)
+                    .str("::")
+                    .str(field.name)
+                    .str("` is at ")
+                    .hex(field.offset)
+                    .str(", but the layout puts it at ")
+                    .hex(expected);
+                panic!("{}", message.as_str());
+            }
+        }
+
+        if let Some(expected) = json::member_number(layout, member, "size") {
+            if expected != field.size {
+                let message = CheckMessage::EMPTY
+                    .str("`")
+                    .str(name)
+                    .str("::")
+                    .str(field.name)
+                    .str("` is ")
+                    .hex(field.size)
+                    .str(" bytes large, but the layout says ")
+                    .hex(expected);
+                panic!("{}", message.as_str());
+            }
+        }
+        index += 1;
+    }
+
+    if let Some(expected) = json::member_number(layout, object, "size") {
+        if expected != size {
+            let message = CheckMessage::EMPTY
+                .str("`")
+                .str(name)
+                .str("` is ")
+                .hex(size)
+                .str(" bytes large, but the layout says ")
+                .hex(expected);
+            panic!("{}", message.as_str());
+        }
+    }
+}
+
+/// Reads the offsets of the fields of a struct declared with `offset_dynamic!` from a layout in
+/// the format of `verify_layout!`, where `fields` holds the name and size of every field. Unless
+/// every field is `required`, the fields the layout leaves out keep the offsets they had.
+#[doc(hidden)]
+pub fn read_offsets(
+    layout: &str,
+    name: &'static str,
+    fields: &[(&'static str, usize)],
+    offsets: &mut [usize],
+    required: bool,
+) -> Result<(), OffsetsError> {
+    let layout = layout.as_bytes();
+    let root = json::skip_whitespace(layout, 0);
+    let object = json::member(layout, root, name)
+        .or_else(|| json::member(layout, root, "user_types").and_then(|types| json::member(layout, types, name)))
+        .ok_or(OffsetsError::MissingStruct(name))?;
+    let members = json::member(layout, object, "fields").ok_or(OffsetsError::MissingStruct(name))?;
+    let size = json::member_number(layout, object, "size");
+
+    for (&(field, field_size), offset) in fields.iter().zip(offsets) {
+        match json::member(layout, members, field).and_then(|member| json::member_number(layout, member, "offset")) {
+            Some(value) => *offset = value,
+            None if required => return Err(OffsetsError::MissingField { struct_name: name, field }),
+            None => {}
+        }
+
+        if let Some(size) = size {
+            if offset.checked_add(field_size).is_none_or(|end| end > size) {
+                return Err(OffsetsError::OutOfBounds { struct_name: name, field, offset: *offset, size });
+            }
+        }
+    }
+    Ok(())
+}
+
+const fn panic_missing(name: &str, field: Option<&str>) -> ! {
+    let mut message = CheckMessage::EMPTY.str("`").str(name);
+    if let Some(field) = field {
+        message = message.str("::").str(field);
+    }
+    message = message.str("` isn't in the layout");
+    panic!("{}", message.as_str());
+}
+
+/// Just enough of a JSON reader to look up members of objects in constants. Positions are byte
+/// indices of the first character of a value, and malformed input reads as missing members.
+mod json {
+    pub const fn skip_whitespace(json: &[u8], mut index: usize) -> usize {
+        while index < json.len() && json[index].is_ascii_whitespace() {
+            index += 1;
+        }
+        index
+    }
+
+    const fn skip_string(json: &[u8], mut index: usize) -> usize {
+        index += 1;
+        while index < json.len() && json[index] != b'"' {
+            index += if json[index] == b'\\' { 2 } else { 1 };
+        }
+        index + 1
+    }
+
+    const fn skip_value(json: &[u8], mut index: usize) -> usize {
+        let mut depth = 0;
+        while index < json.len() {
+            match json[index] {
+                b'"' => {
+                    index = skip_string(json, index);
+                    if depth == 0 {
+                        return index;
+                    }
+                    continue;
+                }
+                b'{' | b'[' => depth += 1,
+                b'}' | b']' if depth == 0 => return index,
+                b'}' | b']' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return index + 1;
+                    }
+                }
+                b',' if depth == 0 => return index,
+                _ => {}
+            }
+            index += 1;
+        }
+        index
+    }
+
+    /// Compares names ignoring ASCII case and underscores, so `_EPROCESS` and `UniqueProcessId`
+    /// in the layout match `EPROCESS` and `unique_process_id` in Rust.
+    const fn same_name(json: &[u8], mut index: usize, end: usize, name: &str) -> bool {
+        let name = name.as_bytes();
+        let mut position = 0;
+        loop {
+            while index < end && json[index] == b'_' {
+                index += 1;
+            }
+            while position < name.len() && name[position] == b'_' {
+                position += 1;
+            }
+            if index == end || position == name.len() {
+                return index == end && position == name.len();
+            }
+            if !json[index].eq_ignore_ascii_case(&name[position]) {
+                return false;
+            }
+            index += 1;
+            position += 1;
+        }
+    }
+
+    /// Returns the position of the value of the member `name` of the object at `object`.
+    pub const fn member(json: &[u8], object: usize, name: &str) -> Option<usize> {
+        if object >= json.len() || json[object] != b'{' {
+            return None;
+        }
+
+        let mut index = skip_whitespace(json, object + 1);
+        while index < json.len() && json[index] == b'"' {
+            let key = index + 1;
+            index = skip_string(json, index);
+            let matches = same_name(json, key, index - 1, name);
+
+            index = skip_whitespace(json, index);
+            if index >= json.len() || json[index] != b':' {
+                return None;
+            }
+            index = skip_whitespace(json, index + 1);
+            if matches {
+                return Some(index);
+            }
+
+            index = skip_whitespace(json, skip_value(json, index));
+            if index >= json.len() || json[index] != b',' {
+                return None;
+            }
+            index = skip_whitespace(json, index + 1);
+        }
+        None
+    }
+
+    /// Returns the member `name` of the object at `object` if it's a non-negative integer.
+    pub const fn member_number(json: &[u8], object: usize, name: &str) -> Option<usize> {
+        let Some(mut index) = member(json, object, name) else {
+            return None;
+        };
+        if index >= json.len() || !json[index].is_ascii_digit() {
+            return None;
+        }
+
+        let mut value = 0;
+        while index < json.len() && json[index].is_ascii_digit() {
+            value = value * 10 + (json[index] - b'0') as usize;
+            index += 1;
+        }
+        Some(value)
+    }
+}
+
+// Naming of the items written by the code generators of the `pdb` and `layout` modules.
+#[cfg(feature = "std")]
+mod names {
+    extern crate std;
+
+    use std::string::String;
+    use std::vec::Vec;
+
+    // `_EPROCESS` becomes `EPROCESS`, and `UniqueProcessId` becomes `UNIQUE_PROCESS_ID`.
+    pub fn constant_name(name: &str) -> String {
+        let characters: Vec<char> = name.trim_start_matches('_').chars().collect();
+        let mut constant = String::new();
+        for (index, &character) in characters.iter().enumerate() {
+            if !character.is_ascii_alphanumeric() {
+                if !constant.ends_with('_') {
+                    constant.push('_');
+                }
+                continue;
+            }
+
+            if character.is_ascii_uppercase() && index > 0 && !constant.ends_with('_') {
+                let previous = characters[index - 1];
+                let next_is_lower = characters.get(index + 1).is_some_and(|next| next.is_ascii_lowercase());
+                if previous.is_ascii_lowercase() || previous.is_ascii_digit() || (previous.is_ascii_uppercase() && next_is_lower) {
+                    constant.push('_');
+                }
+            }
+            constant.push(character.to_ascii_uppercase());
+        }
+        constant
+    }
+}
+
+/// Reads the layouts of structs from Microsoft PDB files, e.g. in a build script generating the
+/// offsets used by `offset!` from the symbols of the binary they describe.
+///
+/// `Pdb::open` reads a PDB file, and `Pdb::write_offsets` writes a Rust module with a constant
+/// for the offset of every member of the given structs, e.g. `EPROCESS_UNIQUE_PROCESS_ID`, and
+/// their sizes, e.g. `SIZE_OF_EPROCESS`. Leading underscores are dropped from the names of the
+/// structs, and the names of the members are converted to upper snake case.
+///
+/// ```no_run
+/// // build.rs
+/// use offsetter::pdb::Pdb;
+///
+/// fn main() -> std::io::Result<()> {
+///     let pdb = Pdb::open("symbols/ntkrnlmp.pdb")?;
+///     let path = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("offsets.rs");
+///     pdb.write_offsets(&["_EPROCESS", "_KPROCESS"], std::fs::File::create(path)?)
+/// }
+/// ```
+///
+/// The generated module is then included with
+/// `mod offsets { include!(concat!(env!("OUT_DIR"), "/offsets.rs")); }` and its constants used as
+/// offsets, e.g. `offsets::EPROCESS_PEB pub peb: *mut PEB`.
+///
+/// Instead of checking PDBs in, `Pdb::fetch` finds the PDB a PE image was built with in a local
+/// cache, downloading it from a symbol server the first time, e.g. for the `ntoskrnl.exe` of the
+/// Windows build being supported:
+///
+/// ```no_run
+/// // build.rs
+/// use offsetter::pdb::{Pdb, MICROSOFT_SYMBOL_SERVER};
+///
+/// fn main() -> std::io::Result<()> {
+///     let pdb = Pdb::fetch("images/ntoskrnl.exe", MICROSOFT_SYMBOL_SERVER, "target/symbols")?;
+///     let path = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("offsets.rs");
+///     pdb.write_offsets(&["_EPROCESS", "_KPROCESS"], std::fs::File::create(path)?)
+/// }
+/// ```
+///
+/// Downloads from a URL run the `curl` command line tool, which then has to be installed wherever
+/// the PDB isn't cached yet, e.g. on build machines. `Pdb::fetch_with` takes a function doing the
+/// download instead, e.g. with an HTTP client the build already depends on.
+#[cfg(feature = "pdb")]
+pub mod pdb {
+    extern crate std;
+
+    use std::format;
+    use std::io::{Error, ErrorKind, Result, Write};
+    use std::path::Path;
+    use std::string::String;
+    use std::vec::Vec;
+
+    use crate::names::constant_name;
+
+    /// The symbol server of Microsoft, with the PDBs of Windows.
+    pub const MICROSOFT_SYMBOL_SERVER: &str = "https://msdl.microsoft.com/download/symbols";
+
+    const MAGIC: &[u8] = b"Microsoft C/C++ MSF 7.00\r\n\x1aDS\0\0\0";
+    const TPI_STREAM: usize = 2;
+
+    const LF_CLASS: u16 = 0x1504;
+    const LF_STRUCTURE: u16 = 0x1505;
+    const LF_UNION: u16 = 0x1506;
+    const LF_FIELDLIST: u16 = 0x1203;
+    const LF_BCLASS: u16 = 0x1400;
+    const LF_VBCLASS: u16 = 0x1401;
+    const LF_IVBCLASS: u16 = 0x1402;
+    const LF_INDEX: u16 = 0x1404;
+    const LF_VFUNCTAB: u16 = 0x1409;
+    const LF_ENUMERATE: u16 = 0x1502;
+    const LF_MEMBER: u16 = 0x150d;
+    const LF_STMEMBER: u16 = 0x150e;
+    const LF_METHOD: u16 = 0x150f;
+    const LF_NESTTYPE: u16 = 0x1510;
+    const LF_ONEMETHOD: u16 = 0x1511;
+
+    const FORWARD_REFERENCE: u16 = 0x80;
+
+    /// The layout of a struct, class or union read from a PDB.
+    #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+    pub struct Layout {
+        /// The name of the struct, e.g. `_EPROCESS`.
+        pub name: String,
+        /// The size of the struct in bytes.
+        pub size: usize,
+        /// The name and offset of every member and base class, in declaration order. Base classes
+        /// are named after their types.
+        pub members: Vec<(String, usize)>,
+    }
+
+    /// Identifies the PDB a PE image was built with, from the CodeView record in its debug
+    /// directory.
+    #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+    pub struct PdbId {
+        /// The file name of the PDB, e.g. `ntkrnlmp.pdb`.
+        pub name: String,
+        /// The GUID of the PDB, in the byte order it's stored in.
+        pub guid: [u8; 16],
+        /// The age of the PDB.
+        pub age: u32,
+    }
+
+    impl PdbId {
+        /// Reads the identity of the PDB of the PE image at `path`, e.g. a DLL or a driver.
+        pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+            Self::from_image(&std::fs::read(path)?)
+        }
+
+        /// Reads the identity of the PDB of a PE image from its bytes, as they're stored in the
+        /// file.
+        pub fn from_image(image: &[u8]) -> Result<Self> {
+            let invalid_image = || invalid("not a PE image with a PDB");
+            if image.get(..2) != Some(b"MZ") {
+                return Err(invalid_image());
+            }
+            let header = u32_at(image, 0x3c)? as usize;
+            if image.get(header..header + 4) != Some(b"PE\0\0") {
+                return Err(invalid_image());
+            }
+
+            let sections = u16_at(image, header + 6)? as usize;
+            let optional_size = u16_at(image, header + 20)? as usize;
+            let optional = header + 24;
+            let directories = match u16_at(image, optional)? {
+                0x10b => optional + 96,
+                0x20b => optional + 112,
+                _ => return Err(invalid_image()),
+            };
+            const DEBUG_DIRECTORY: usize = 6;
+            let debug_rva = u32_at(image, directories + DEBUG_DIRECTORY * 8)? as usize;
+            let debug_size = u32_at(image, directories + DEBUG_DIRECTORY * 8 + 4)? as usize;
+
+            // Addresses in the image are mapped to the file through the section headers.
+            let section_table = optional + optional_size;
+            let file_offset = |rva: usize| -> Result<usize> {
+                for section in 0..sections {
+                    let header = section_table + section * 40;
+                    let size = u32_at(image, header + 8)?.max(u32_at(image, header + 16)?) as usize;
+                    let address = u32_at(image, header + 12)? as usize;
+                    if (address..address + size).contains(&rva) {
+                        return Ok(rva - address + u32_at(image, header + 20)? as usize);
+                    }
+                }
+                Err(invalid_image())
+            };
+
+            const CODEVIEW: u32 = 2;
+            let debug = file_offset(debug_rva)?;
+            for entry in (debug..debug + debug_size).step_by(28) {
+                if u32_at(image, entry + 12)? != CODEVIEW {
+                    continue;
+                }
+                let record = u32_at(image, entry + 24)? as usize;
+                if image.get(record..record + 4) != Some(b"RSDS") {
+                    continue;
+                }
+                let guid = image.get(record + 4..record + 20).ok_or_else(|| invalid("truncated PE image"))?;
+                let (path, _) = name_at(image, record + 24)?;
+                return Ok(PdbId {
+                    name: path.rsplit(['\\', '/']).next().unwrap_or(path).into(),
+                    guid: guid.try_into().map_err(|_| invalid("truncated PE image"))?,
+                    age: u32_at(image, record + 20)?,
+                });
+            }
+            Err(invalid_image())
+        }
+
+        /// The path of the PDB in a symbol store, made of the GUID with its first three parts
+        /// read as little endian numbers and the age in hex.
+        ///
+        /// ```
+        /// use offsetter::pdb::PdbId;
+        ///
+        /// let id = PdbId {
+        ///     name: "ntkrnlmp.pdb".into(),
+        ///     guid: [0xb9, 0xdb, 0x44, 0x38, 0x17, 0x20, 0x67, 0x49, 0xbe, 0x7a, 0xa4, 0xa2, 0xc2, 0x04, 0x30, 0xfa],
+        ///     age: 2,
+        /// };
+        /// assert_eq!(id.store_path(), "ntkrnlmp.pdb/3844DBB920174967BE7AA4A2C20430FA2/ntkrnlmp.pdb");
+        /// ```
+        pub fn store_path(&self) -> String {
+            let guid = &self.guid;
+            let mut path = format!(
+                "{}/{:08X}{:04X}{:04X}",
+                self.name,
+                u32::from_le_bytes([guid[0], guid[1], guid[2], guid[3]]),
+                u16::from_le_bytes([guid[4], guid[5]]),
+                u16::from_le_bytes([guid[6], guid[7]]),
+            );
+            for byte in &guid[8..] {
+                path.push_str(&format!("{:02X}", byte));
+            }
+            path.push_str(&format!("{:X}/{}", self.age, self.name));
+            path
+        }
+    }
+
+    /// The type information of a PDB file.
+    pub struct Pdb {
+        types: Vec<u8>,
+        // The position of every type record in `types`, by type index.
+        records: Vec<usize>,
+        first_index: u32,
+    }
+
+    fn invalid(message: &str) -> Error {
+        Error::new(ErrorKind::InvalidData, message)
+    }
+
+    fn download(id: &PdbId, server: &str, path: &Path) -> Result<()> {
+        if !server.contains("://") {
+            std::fs::copy(Path::new(server).join(id.store_path()), path)?;
+            return Ok(());
+        }
+
+        let url = format!("{}/{}", server.trim_end_matches('/'), id.store_path());
+        let status = std::process::Command::new("curl")
+            .args(["--fail", "--silent", "--show-error", "--location", "--output"])
+            .arg(path)
+            .arg(&url)
+            .status()
+            .map_err(|error| Error::new(error.kind(), format!("couldn't run curl to download `{}`: {}", url, error)))?;
+        if !status.success() {
+            return Err(Error::new(ErrorKind::NotFound, format!("couldn't download `{}`, curl failed with {}", url, status)));
+        }
+        Ok(())
+    }
+
+    fn u16_at(bytes: &[u8], position: usize) -> Result<u16> {
+        bytes
+            .get(position..position + 2)
+            .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+            .ok_or_else(|| invalid("truncated PDB"))
+    }
+
+    fn u32_at(bytes: &[u8], position: usize) -> Result<u32> {
+        bytes
+            .get(position..position + 4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .ok_or_else(|| invalid("truncated PDB"))
+    }
+
+    // Numbers in type records are stored inline if they're small, and otherwise after a kind.
+    fn numeric_at(bytes: &[u8], position: usize) -> Result<(u64, usize)> {
+        let kind = u16_at(bytes, position)?;
+        let value = position + 2;
+        Ok(match kind {
+            0..0x8000 => (kind as u64, value),
+            0x8000 => (bytes.get(value).copied().ok_or_else(|| invalid("truncated PDB"))? as i8 as u64, value + 1),
+            0x8001 => (u16_at(bytes, value)? as i16 as u64, value + 2),
+            0x8002 => (u16_at(bytes, value)? as u64, value + 2),
+            0x8003 => (u32_at(bytes, value)? as i32 as u64, value + 4),
+            0x8004 => (u32_at(bytes, value)? as u64, value + 4),
+            0x8009 | 0x800a => (u32_at(bytes, value)? as u64 | (u32_at(bytes, value + 4)? as u64) << 32, value + 8),
+            _ => return Err(invalid("unsupported numeric leaf in PDB")),
+        })
+    }
+
+    fn name_at(bytes: &[u8], position: usize) -> Result<(&str, usize)> {
+        let rest = bytes.get(position..).ok_or_else(|| invalid("truncated PDB"))?;
+        let length = rest.iter().position(|&byte| byte == 0).ok_or_else(|| invalid("unterminated name in PDB"))?;
+        let name = core::str::from_utf8(&rest[..length]).map_err(|_| invalid("name in PDB isn't UTF-8"))?;
+        Ok((name, position + length + 1))
+    }
+
+    impl Pdb {
+        /// Reads the PDB file at `path`.
+        pub fn open(path: impl AsRef<std::path::Path>) -> Result<Self> {
+            Self::parse(&std::fs::read(path)?)
+        }
+
+        /// Reads the PDB the PE image at `image` was built with from `cache`, a directory laid
+        /// out like a symbol store. If it isn't there yet, it's downloaded from `server` into
+        /// `cache` first.
+        ///
+        /// The server is either a URL, e.g. [`MICROSOFT_SYMBOL_SERVER`], which is downloaded from
+        /// with `curl` to not depend on an HTTP client, or the path of another symbol store,
+        /// e.g. a network share.
+        pub fn fetch(image: impl AsRef<Path>, server: &str, cache: impl AsRef<Path>) -> Result<Self> {
+            Self::fetch_with(image, cache, |id, path| download(id, server, path))
+        }
+
+        /// Reads the PDB the PE image at `image` was built with from `cache` like
+        /// [`Pdb::fetch`], but leaves getting a PDB that isn't there yet to `download`, e.g. to use
+        /// an HTTP client instead of `curl`. It's given the identity of the PDB and the path of
+        /// the file to write it to.
+        pub fn fetch_with(
+            image: impl AsRef<Path>,
+            cache: impl AsRef<Path>,
+            download: impl FnOnce(&PdbId, &Path) -> Result<()>,
+        ) -> Result<Self> {
+            let id = PdbId::read(image)?;
+            let path = cache.as_ref().join(id.store_path());
+            if !path.is_file() {
+                if let Some(directory) = path.parent() {
+                    std::fs::create_dir_all(directory)?;
+                }
+                // The PDB is downloaded into a temporary file first, so an interrupted download
+                // isn't taken for the PDB.
+                let partial = path.with_extension("partial");
+                if let Err(error) = download(&id, &partial) {
+                    let _ = std::fs::remove_file(&partial);
+                    return Err(error);
+                }
+                std::fs::rename(partial, &path)?;
+            }
+            Self::open(path)
+        }
+
+        /// Reads a PDB from its bytes.
+        pub fn parse(bytes: &[u8]) -> Result<Self> {
+            if !bytes.starts_with(MAGIC) {
+                return Err(invalid("not a PDB file"));
+            }
+
+            let block_size = u32_at(bytes, 32)? as usize;
+            let directory_size = u32_at(bytes, 44)? as usize;
+            let block_map = u32_at(bytes, 52)? as usize;
+            if block_size == 0 {
+                return Err(invalid("PDB has a block size of 0"));
+            }
+
+            let read_blocks = |blocks: &mut dyn Iterator<Item = Result<u32>>, size: usize| -> Result<Vec<u8>> {
+                let mut data = Vec::with_capacity(size);
+                for block in blocks {
+                    let start = block? as usize * block_size;
+                    data.extend_from_slice(bytes.get(start..start + block_size).ok_or_else(|| invalid("truncated PDB"))?);
+                }
+                data.truncate(size);
+                Ok(data)
+            };
+
+            let block_count = |size: usize| size.div_ceil(block_size);
+            let directory = read_blocks(
+                &mut (0..block_count(directory_size)).map(|index| u32_at(bytes, block_map * block_size + index * 4)),
+                directory_size,
+            )?;
+
+            // The directory lists the size of every stream, followed by the blocks of every stream.
+            let stream_count = u32_at(&directory, 0)? as usize;
+            let size_of = |stream: usize| -> Result<usize> {
+                let size = u32_at(&directory, 4 + stream * 4)?;
+                Ok(if size == u32::MAX { 0 } else { size as usize })
+            };
+
+            if stream_count <= TPI_STREAM {
+                return Err(invalid("PDB has no type information"));
+            }
+            let mut position = 4 + stream_count * 4;
+            for stream in 0..TPI_STREAM {
+                position += block_count(size_of(stream)?) * 4;
+            }
+            let size = size_of(TPI_STREAM)?;
+            let types = read_blocks(&mut (0..block_count(size)).map(|index| u32_at(&directory, position + index * 4)), size)?;
+
+            let header_size = u32_at(&types, 4)? as usize;
+            let first_index = u32_at(&types, 8)?;
+            let record_bytes = u32_at(&types, 16)? as usize;
+
+            let mut records = Vec::new();
+            let mut position = header_size;
+            while position + 4 <= (header_size + record_bytes).min(types.len()) {
+                records.push(position);
+                position += 2 + u16_at(&types, position)? as usize;
+            }
+            Ok(Self { types, records, first_index })
+        }
+
+        fn record(&self, index: u32) -> Result<(u16, &[u8])> {
+            let position = index
+                .checked_sub(self.first_index)
+                .and_then(|index| self.records.get(index as usize))
+                .copied()
+                .ok_or_else(|| invalid("type index out of range in PDB"))?;
+            let length = u16_at(&self.types, position)? as usize;
+            let record = self.types.get(position + 2..position + 2 + length).ok_or_else(|| invalid("truncated PDB"))?;
+            Ok((u16_at(record, 0)?, &record[2..]))
+        }
+
+        /// Returns the layout of the struct, class or union named `name`, or `None` if the PDB
+        /// doesn't define it.
+        pub fn layout(&self, name: &str) -> Result<Option<Layout>> {
+            for index in 0..self.records.len() as u32 {
+                let (kind, data) = self.record(self.first_index + index)?;
+                let (properties, fields, size_position) = match kind {
+                    LF_CLASS | LF_STRUCTURE => (u16_at(data, 2)?, u32_at(data, 4)?, 16),
+                    LF_UNION => (u16_at(data, 2)?, u32_at(data, 4)?, 8),
+                    _ => continue,
+                };
+                let (size, name_position) = numeric_at(data, size_position)?;
+                if properties & FORWARD_REFERENCE != 0 || name_at(data, name_position)?.0 != name {
+                    continue;
+                }
+
+                let mut members = Vec::new();
+                self.members(fields, &mut members)?;
+                return Ok(Some(Layout { name: name.into(), size: size as usize, members }));
+            }
+            Ok(None)
+        }
+
+        fn members(&self, fields: u32, members: &mut Vec<(String, usize)>) -> Result<()> {
+            let (kind, data) = self.record(fields)?;
+            if kind != LF_FIELDLIST {
+                return Err(invalid("expected a field list in PDB"));
+            }
+
+            let mut position = 0;
+            while position + 2 <= data.len() {
+                // Entries are padded to 4 bytes with bytes of 0xf0 and above.
+                if data[position] >= 0xf0 {
+                    position += 1;
+                    continue;
+                }
+
+                let kind = u16_at(data, position)?;
+                let entry = position + 2;
+                position = match kind {
+                    LF_MEMBER => {
+                        let (offset, name) = numeric_at(data, entry + 6)?;
+                        let (name, end) = name_at(data, name)?;
+                        members.push((name.into(), offset as usize));
+                        end
+                    }
+                    LF_BCLASS => {
+                        let (offset, end) = numeric_at(data, entry + 6)?;
+                        members.push((self.type_name(u32_at(data, entry + 2)?)?, offset as usize));
+                        end
+                    }
+                    LF_VBCLASS | LF_IVBCLASS => {
+                        let (_, end) = numeric_at(data, entry + 10)?;
+                        numeric_at(data, end)?.1
+                    }
+                    LF_ENUMERATE => name_at(data, numeric_at(data, entry + 2)?.1)?.1,
+                    LF_STMEMBER | LF_NESTTYPE => name_at(data, entry + 6)?.1,
+                    LF_METHOD => name_at(data, entry + 6)?.1,
+                    LF_ONEMETHOD => {
+                        // Introducing virtual methods store the offset of their entry in the vtable.
+                        let introducing = matches!((u16_at(data, entry)? >> 2) & 7, 4 | 6);
+                        name_at(data, entry + 6 + if introducing { 4 } else { 0 })?.1
+                    }
+                    LF_VFUNCTAB => entry + 6,
+                    LF_INDEX => {
+                        self.members(u32_at(data, entry + 2)?, members)?;
+                        entry + 6
+                    }
+                    _ => return Err(invalid("unsupported field list entry in PDB")),
+                };
+            }
+            Ok(())
+        }
+
+        fn type_name(&self, index: u32) -> Result<String> {
+            let (kind, data) = self.record(index)?;
+            let size_position = match kind {
+                LF_CLASS | LF_STRUCTURE => 16,
+                LF_UNION => 8,
+                _ => return Err(invalid("expected a struct in PDB")),
+            };
+            let (_, name) = numeric_at(data, size_position)?;
+            Ok(name_at(data, name)?.0.into())
+        }
+
+        /// Writes a Rust module with the offsets of the members of the structs named in `names`
+        /// and their sizes as constants, e.g. `EPROCESS_PEB` and `SIZE_OF_EPROCESS`.
+        pub fn write_offsets(&self, names: &[&str], mut writer: impl Write) -> Result<()> {
+            writeln!(writer, "// Generated by offsetter from a PDB, don't edit by hand.")?;
+            for &name in names {
+                let layout = self.layout(name)?.ok_or_else(|| Error::new(ErrorKind::NotFound, std::format!("`{}` isn't in the PDB", name)))?;
+                let prefix = constant_name(name);
+                writeln!(writer)?;
+                writeln!(writer, "pub const SIZE_OF_{}: usize = {:#x};", prefix, layout.size)?;
+                for (member, offset) in &layout.members {
+                    writeln!(writer, "pub const {}_{}: usize = {:#x};", prefix, constant_name(member), offset)?;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::string::ToString;
+        use std::vec;
+
+        const BLOCK_SIZE: usize = 0x40;
+
+        fn record(kind: u16, data: &[u8]) -> Vec<u8> {
+            let mut record = Vec::new();
+            record.extend_from_slice(&(data.len() as u16 + 2).to_le_bytes());
+            record.extend_from_slice(&kind.to_le_bytes());
+            record.extend_from_slice(data);
+            record
+        }
+
+        fn name(name: &str) -> Vec<u8> {
+            let mut bytes = name.as_bytes().to_vec();
+            bytes.push(0);
+            bytes
+        }
+
+        // Field list entries are padded to 4 bytes like the compiler does.
+        fn padded(mut entry: Vec<u8>) -> Vec<u8> {
+            while !entry.len().is_multiple_of(4) {
+                entry.push(0xf0 + (4 - entry.len() % 4) as u8);
+            }
+            entry
+        }
+
+        fn member(offset: &[u8], member_name: &str) -> Vec<u8> {
+            let mut entry = [&LF_MEMBER.to_le_bytes()[..], &[3, 0], &0x74u32.to_le_bytes(), offset].concat();
+            entry.extend(name(member_name));
+            padded(entry)
+        }
+
+        fn structure(properties: u16, fields: u32, size: &[u8], struct_name: &str) -> Vec<u8> {
+            let mut data = [&2u16.to_le_bytes()[..], &properties.to_le_bytes(), &fields.to_le_bytes(), &[0; 8], size].concat();
+            data.extend(name(struct_name));
+            record(LF_STRUCTURE, &data)
+        }
+
+        // Type 0x1000 is the field list of `_KPROCESS`, 0x1001 a forward reference to it and 0x1002
+        // its definition. 0x1003 is the field list of `_EPROCESS`, with `_KPROCESS` as base class
+        // and a size stored as a numeric leaf, and 0x1004 its definition.
+        fn types() -> Vec<u8> {
+            let mut records = Vec::new();
+            records.extend(record(LF_FIELDLIST, &[member(&[0, 0], "Header"), member(&[0x28, 0], "DirectoryTableBase")].concat()));
+            records.extend(structure(FORWARD_REFERENCE, 0, &[0, 0], "_KPROCESS"));
+            records.extend(structure(0, 0x1000, &[0x38, 0], "_KPROCESS"));
+            let base = padded([&LF_BCLASS.to_le_bytes()[..], &[3, 0], &0x1002u32.to_le_bytes(), &[0, 0]].concat());
+            records.extend(record(LF_FIELDLIST, &[base, member(&[0x40, 0x04], "UniqueProcessId"), member(&[0x50, 0x05], "Peb")].concat()));
+            records.extend(structure(0, 0x1003, &[0x02, 0x80, 0x80, 0x0a], "_EPROCESS"));
+
+            let mut stream = Vec::new();
+            for field in [0x20040918u32, 56, 0x1000, 0x1005, records.len() as u32] {
+                stream.extend_from_slice(&field.to_le_bytes());
+            }
+            stream.resize(56, 0);
+            stream.extend(records);
+            stream
+        }
+
+        // Block 0 holds the superblock, block 1 the block of the directory, and block 2 the
+        // directory itself, with two empty streams in front of the type stream.
+        fn pdb_with(types: &[u8]) -> Vec<u8> {
+            let type_blocks = types.len().div_ceil(BLOCK_SIZE);
+            let mut directory = Vec::new();
+            for field in [3, 0, 0, types.len() as u32] {
+                directory.extend_from_slice(&field.to_le_bytes());
+            }
+            for block in 0..type_blocks as u32 {
+                directory.extend_from_slice(&(3 + block).to_le_bytes());
+            }
+            assert!(directory.len() <= BLOCK_SIZE);
+
+            let mut bytes = MAGIC.to_vec();
+            for field in [BLOCK_SIZE as u32, 1, 3 + type_blocks as u32, directory.len() as u32, 0, 1] {
+                bytes.extend_from_slice(&field.to_le_bytes());
+            }
+            bytes.resize(BLOCK_SIZE, 0);
+            bytes.extend_from_slice(&2u32.to_le_bytes());
+            bytes.resize(2 * BLOCK_SIZE, 0);
+            bytes.extend(directory);
+            bytes.resize(3 * BLOCK_SIZE, 0);
+            bytes.extend_from_slice(types);
+            bytes.resize((3 + type_blocks) * BLOCK_SIZE, 0);
+            bytes
+        }
+
+        fn pdb() -> Vec<u8> {
+            pdb_with(&types())
+        }
+
+        const GUID: [u8; 16] = [0xb9, 0xdb, 0x44, 0x38, 0x17, 0x20, 0x67, 0x49, 0xbe, 0x7a, 0xa4, 0xa2, 0xc2, 0x04, 0x30, 0xfa];
+
+        // A PE32+ image with a single section at 0x1000, mapped to 0x200 in the file, holding the
+        // debug directory and the CodeView record after it.
+        fn image() -> Vec<u8> {
+            let mut image = vec![0; 0x400];
+            let mut put = |position: usize, bytes: &[u8]| image[position..position + bytes.len()].copy_from_slice(bytes);
+            put(0, b"MZ");
+            put(0x3c, &0x40u32.to_le_bytes());
+            put(0x40, b"PE\0\0");
+            put(0x46, &1u16.to_le_bytes());
+            put(0x54, &240u16.to_le_bytes());
+            put(0x58, &0x20bu16.to_le_bytes());
+            put(0xf8, &0x1000u32.to_le_bytes());
+            put(0xfc, &28u32.to_le_bytes());
+            put(0x148 + 8, &0x200u32.to_le_bytes());
+            put(0x148 + 12, &0x1000u32.to_le_bytes());
+            put(0x148 + 16, &0x200u32.to_le_bytes());
+            put(0x148 + 20, &0x200u32.to_le_bytes());
+            put(0x200 + 12, &2u32.to_le_bytes());
+            put(0x200 + 24, &0x220u32.to_le_bytes());
+            put(0x220, b"RSDS");
+            put(0x224, &GUID);
+            put(0x234, &2u32.to_le_bytes());
+            put(0x238, b"C:\\symbols\\ntkrnlmp.pdb\0");
+            image
+        }
+
+        fn message(error: Error) -> String {
+            assert_eq!(error.kind(), ErrorKind::InvalidData);
+            error.to_string()
+        }
+
+        #[test]
+        fn layout() {
+            let pdb = Pdb::parse(&pdb()).unwrap();
+            let process = pdb.layout("_KPROCESS").unwrap().unwrap();
+            assert_eq!(process.size, 0x38);
+            assert_eq!(process.members, [("Header".into(), 0), ("DirectoryTableBase".into(), 0x28)]);
+
+            let process = pdb.layout("_EPROCESS").unwrap().unwrap();
+            assert_eq!(process.size, 0xa80);
+            assert_eq!(process.members, [("_KPROCESS".into(), 0), ("UniqueProcessId".into(), 0x440), ("Peb".into(), 0x550)]);
+
+            assert_eq!(pdb.layout("_ETHREAD").unwrap(), None);
+        }
+
+        #[test]
+        fn write_offsets() {
+            let pdb = Pdb::parse(&pdb()).unwrap();
+            let mut offsets = Vec::new();
+            pdb.write_offsets(&["_KPROCESS", "_EPROCESS"], &mut offsets).unwrap();
+            assert_eq!(
+                String::from_utf8(offsets).unwrap(),
+                "// Generated by offsetter from a PDB, don't edit by hand.\n\
+                 \n\
+                 pub const SIZE_OF_KPROCESS: usize = 0x38;\n\
+                 pub const KPROCESS_HEADER: usize = 0x0;\n\
+                 pub const KPROCESS_DIRECTORY_TABLE_BASE: usize = 0x28;\n\
+                 \n\
+                 pub const SIZE_OF_EPROCESS: usize = 0xa80;\n\
+                 pub const EPROCESS_KPROCESS: usize = 0x0;\n\
+                 pub const EPROCESS_UNIQUE_PROCESS_ID: usize = 0x440;\n\
+                 pub const EPROCESS_PEB: usize = 0x550;\n"
+            );
+
+            let error = pdb.write_offsets(&["_ETHREAD"], Vec::new()).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::NotFound);
+            assert_eq!(error.to_string(), "`_ETHREAD` isn't in the PDB");
+        }
+
+        #[test]
+        fn parse_errors() {
+            assert_eq!(message(Pdb::parse(b"Microsoft C/C++ program database 2.00\r\n").err().unwrap()), "not a PDB file");
+
+            let bytes = pdb();
+            assert_eq!(message(Pdb::parse(&bytes[..bytes.len() - 1]).err().unwrap()), "truncated PDB");
+            assert_eq!(message(Pdb::parse(&bytes[..MAGIC.len() + 8]).err().unwrap()), "truncated PDB");
+
+            let mut empty = bytes.clone();
+            empty[32..36].copy_from_slice(&0u32.to_le_bytes());
+            assert_eq!(message(Pdb::parse(&empty).err().unwrap()), "PDB has a block size of 0");
+
+            let mut streams = bytes.clone();
+            streams[2 * BLOCK_SIZE..2 * BLOCK_SIZE + 4].copy_from_slice(&2u32.to_le_bytes());
+            assert_eq!(message(Pdb::parse(&streams).err().unwrap()), "PDB has no type information");
+        }
+
+        #[test]
+        fn malformed_types() {
+            let mut types = types();
+            // The field list of `_KPROCESS` now starts with an entry of an unknown kind.
+            types[56 + 4..56 + 6].copy_from_slice(&0x1234u16.to_le_bytes());
+            let pdb = Pdb::parse(&pdb_with(&types)).unwrap();
+            assert_eq!(message(pdb.layout("_KPROCESS").unwrap_err()), "unsupported field list entry in PDB");
+
+            let mut types = self::types();
+            // The definition of `_EPROCESS` now refers to a type past the last one.
+            let fields = types.len() - name("_EPROCESS").len() - 4 - 12;
+            types[fields..fields + 4].copy_from_slice(&0x1005u32.to_le_bytes());
+            let pdb = Pdb::parse(&pdb_with(&types)).unwrap();
+            assert_eq!(message(pdb.layout("_EPROCESS").unwrap_err()), "type index out of range in PDB");
+        }
+
+        #[test]
+        fn pdb_id() {
+            let id = PdbId::from_image(&image()).unwrap();
+            assert_eq!(id, PdbId { name: "ntkrnlmp.pdb".into(), guid: GUID, age: 2 });
+            assert_eq!(id.store_path(), "ntkrnlmp.pdb/3844DBB920174967BE7AA4A2C20430FA2/ntkrnlmp.pdb");
+        }
+
+        #[test]
+        fn pdb_id_errors() {
+            let image = image();
+            assert_eq!(message(PdbId::from_image(b"ELF").unwrap_err()), "not a PE image with a PDB");
+            assert_eq!(message(PdbId::from_image(&image[..0x22a]).unwrap_err()), "truncated PE image");
+
+            let mut optional = image.clone();
+            optional[0x58..0x5a].copy_from_slice(&0x107u16.to_le_bytes());
+            assert_eq!(message(PdbId::from_image(&optional).unwrap_err()), "not a PE image with a PDB");
+
+            let mut codeview = image.clone();
+            codeview[0x220..0x224].copy_from_slice(b"NB10");
+            assert_eq!(message(PdbId::from_image(&codeview).unwrap_err()), "not a PE image with a PDB");
+
+            let mut unterminated = image;
+            unterminated.truncate(0x238 + 4);
+            assert_eq!(message(PdbId::from_image(&unterminated).unwrap_err()), "unterminated name in PDB");
+        }
+
+        fn directory(test: &str) -> std::path::PathBuf {
+            let directory = std::env::temp_dir().join(format!("offsetter-{}-{}", test, std::process::id()));
+            let _ = std::fs::remove_dir_all(&directory);
+            std::fs::create_dir_all(&directory).unwrap();
+            directory
+        }
+
+        #[test]
+        fn fetch_from_store() {
+            let directory = directory("fetch-from-store");
+            let image = directory.join("ntoskrnl.exe");
+            std::fs::write(&image, self::image()).unwrap();
+            let store = directory.join("store");
+            let stored = store.join("ntkrnlmp.pdb/3844DBB920174967BE7AA4A2C20430FA2/ntkrnlmp.pdb");
+            std::fs::create_dir_all(stored.parent().unwrap()).unwrap();
+            std::fs::write(&stored, pdb()).unwrap();
+
+            let cache = directory.join("cache");
+            let fetched = Pdb::fetch(&image, store.to_str().unwrap(), &cache).unwrap();
+            assert_eq!(fetched.layout("_KPROCESS").unwrap().unwrap().size, 0x38);
+            assert_eq!(std::fs::read(cache.join(PdbId::read(&image).unwrap().store_path())).unwrap(), pdb());
+
+            let error = Pdb::fetch(&image, directory.join("missing").to_str().unwrap(), directory.join("empty")).err().unwrap();
+            assert_eq!(error.kind(), ErrorKind::NotFound);
+            std::fs::remove_dir_all(directory).unwrap();
+        }
+
+        #[test]
+        fn fetch_with() {
+            let directory = directory("fetch-with");
+            let image = directory.join("ntoskrnl.exe");
+            std::fs::write(&image, self::image()).unwrap();
+            let cache = directory.join("cache");
+
+            let error = Pdb::fetch_with(&image, &cache, |_, path| {
+                std::fs::write(path, b"partial")?;
+                Err(Error::new(ErrorKind::TimedOut, "interrupted"))
+            });
+            assert_eq!(error.err().unwrap().kind(), ErrorKind::TimedOut);
+            let path = cache.join("ntkrnlmp.pdb/3844DBB920174967BE7AA4A2C20430FA2/ntkrnlmp.pdb");
+            assert_eq!(std::fs::read_dir(path.parent().unwrap()).unwrap().count(), 0);
+
+            let mut downloads = Vec::new();
+            for _ in 0..2 {
+                let fetched = Pdb::fetch_with(&image, &cache, |id, path| {
+                    downloads.push(id.clone());
+                    std::fs::write(path, pdb())
+                });
+                assert_eq!(fetched.unwrap().layout("_EPROCESS").unwrap().unwrap().size, 0xa80);
+            }
+            assert_eq!(downloads, [PdbId { name: "ntkrnlmp.pdb".into(), guid: GUID, age: 2 }]);
+            assert!(path.is_file());
+            std::fs::remove_dir_all(directory).unwrap();
+        }
+    }
+}
+
+/// Finds byte patterns in memory, e.g. to discover the addresses structs are viewed at with
+/// `from_ptr`.
+///
+/// Patterns are written in IDA's notation, as hex bytes separated by spaces with `?` or `??` for
+/// bytes that may have any value, e.g. `48 8B 05 ?? ?? ?? ??`. Scanning doesn't allocate, and
+/// with the `std` feature `Pattern::find_all` collects every match into a `Vec`.
+///
+/// ```
+/// use offsetter::scan::Pattern;
+///
+/// let code = [0x90, 0x48, 0x8b, 0x05, 0x10, 0x20, 0x30, 0x40, 0xc3];
+/// let pattern = Pattern::new("48 8B 05 ?? ?? ?? ?? C3").unwrap();
+/// let position = pattern.find(&code).unwrap();
+/// assert_eq!(position, 1);
+///
+/// let displacement = i32::from_le_bytes(code[position + 3..position + 7].try_into().unwrap());
+/// assert_eq!(displacement, 0x40302010);
+/// ```
+#[cfg(feature = "scan")]
+pub mod scan {
+    #[cfg(feature = "std")]
+    extern crate std;
+
+    /// Why a pattern couldn't be parsed.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub enum PatternError {
+        /// The pattern has no bytes.
+        Empty,
+        /// The token at this index, counting from 0, is neither a hex byte nor a wildcard.
+        InvalidToken(usize),
+    }
+
+    impl core::fmt::Display for PatternError {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            match self {
+                Self::Empty => write!(f, "the pattern is empty"),
+                Self::InvalidToken(index) => write!(f, "token {} of the pattern is neither a hex byte nor a wildcard", index),
+            }
+        }
+    }
+
+    impl core::error::Error for PatternError {}
+
+    /// A byte pattern in IDA's notation, e.g. `48 8B 05 ?? ?? ?? ??`.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct Pattern<'a> {
+        text: &'a str,
+        length: usize,
+    }
+
+    // A byte, `None` for a wildcard, or an error for anything else.
+    fn token(text: &str) -> Result<Option<u8>, ()> {
+        match text {
+            "?" | "??" => Ok(None),
+            // `from_str_radix` would take a sign as well, e.g. `+4`.
+            _ if text.len() == 2 && text.bytes().all(|digit| digit.is_ascii_hexdigit()) => u8::from_str_radix(text, 16).map(Some).map_err(|_| ()),
+            _ => Err(()),
+        }
+    }
+
+    impl<'a> Pattern<'a> {
+        /// Parses a pattern.
+        pub fn new(text: &'a str) -> Result<Self, PatternError> {
+            let mut length = 0;
+            for (index, text) in text.split_ascii_whitespace().enumerate() {
+                token(text).map_err(|_| PatternError::InvalidToken(index))?;
+                length += 1;
+            }
+
+            if length == 0 {
+                return Err(PatternError::Empty);
+            }
+            Ok(Self { text, length })
+        }
+
+        /// Returns the number of bytes the pattern matches.
+        pub fn len(&self) -> usize {
+            self.length
+        }
+
+        /// Returns `false`, since a pattern has at least one byte.
+        pub fn is_empty(&self) -> bool {
+            false
+        }
+
+        /// Returns whether `bytes` starts with the pattern.
+        pub fn matches(&self, bytes: &[u8]) -> bool {
+            bytes.len() >= self.length
+                && self
+                    .text
+                    .split_ascii_whitespace()
+                    .zip(bytes)
+                    .all(|(text, &byte)| token(text).is_ok_and(|expected| expected.is_none_or(|expected| expected == byte)))
+        }
+
+        /// Returns the position of the first match in `haystack`.
+        pub fn find(&self, haystack: &[u8]) -> Option<usize> {
+            self.find_iter(haystack).next()
+        }
+
+        /// Returns an iterator over the positions of every match in `haystack`, including
+        /// overlapping ones.
+        pub fn find_iter<'h>(&self, haystack: &'h [u8]) -> Matches<'a, 'h> {
+            Matches { pattern: *self, haystack, position: 0 }
+        }
+
+        /// Returns the positions of every match in `haystack`.
+        #[cfg(feature = "std")]
+        pub fn find_all(&self, haystack: &[u8]) -> std::vec::Vec<usize> {
+            self.find_iter(haystack).collect()
+        }
+    }
+
+    /// An iterator over the positions of the matches of a pattern, see `Pattern::find_iter`.
+    #[derive(Clone, Debug)]
+    pub struct Matches<'a, 'h> {
+        pattern: Pattern<'a>,
+        haystack: &'h [u8],
+        position: usize,
+    }
+
+    impl Iterator for Matches<'_, '_> {
+        type Item = usize;
+
+        fn next(&mut self) -> Option<usize> {
+            while self.position + self.pattern.length <= self.haystack.len() {
+                let position = self.position;
+                self.position += 1;
+                if self.pattern.matches(&self.haystack[position..]) {
+                    return Some(position);
+                }
+            }
+            None
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn parse_errors() {
+            assert_eq!(Pattern::new(""), Err(PatternError::Empty));
+            assert_eq!(Pattern::new(" \t\n"), Err(PatternError::Empty));
+            assert_eq!(Pattern::new("48 8B GG"), Err(PatternError::InvalidToken(2)));
+            assert_eq!(Pattern::new("48 8 05"), Err(PatternError::InvalidToken(1)));
+            assert_eq!(Pattern::new("488B"), Err(PatternError::InvalidToken(0)));
+            assert_eq!(Pattern::new("48 ??? 05"), Err(PatternError::InvalidToken(1)));
+            assert_eq!(Pattern::new("+4 8B"), Err(PatternError::InvalidToken(0)));
+            assert_eq!(Pattern::new("  48\t8b ?  ?? ").map(|pattern| pattern.len()), Ok(4));
+        }
+
+        #[test]
+        fn wildcards() {
+            let pattern = Pattern::new("E8 ? ?? 00 C3").unwrap();
+            assert!(pattern.matches(&[0xe8, 0x12, 0x34, 0x00, 0xc3, 0xff]));
+            assert!(pattern.matches(&[0xe8, 0x00, 0xff, 0x00, 0xc3]));
+            assert!(!pattern.matches(&[0xe8, 0x12, 0x34, 0x01, 0xc3]));
+            assert!(!pattern.matches(&[0xe8, 0x12, 0x34, 0x00]));
+            assert_eq!(pattern.find(&[0xc3, 0xe8, 0xe8, 0x01, 0x00, 0xc3]), Some(1));
+            assert_eq!(pattern.find(&[0xe8, 0x01, 0x02, 0x00]), None);
+
+            let any = Pattern::new("?? ??").unwrap();
+            assert_eq!(any.find_iter(&[1, 2, 3]).count(), 2);
+            assert_eq!(any.find(&[1]), None);
+        }
+
+        #[test]
+        fn overlapping_matches() {
+            let haystack = [0xaa, 0xaa, 0xaa, 0xaa, 0xbb, 0xaa, 0xaa];
+            let pattern = Pattern::new("AA AA").unwrap();
+            let mut matches = pattern.find_iter(&haystack);
+            assert_eq!([matches.next(), matches.next(), matches.next(), matches.next(), matches.next()], [Some(0), Some(1), Some(2), Some(5), None]);
+
+            let pattern = Pattern::new("aa ? aa").unwrap();
+            assert!(pattern.find_iter(&haystack).eq([0, 1, 3]));
+        }
+
+        #[cfg(feature = "std")]
+        #[test]
+        fn find_all() {
+            let pattern = Pattern::new("01 ?? 01").unwrap();
+            assert_eq!(pattern.find_all(&[1, 0, 1, 0, 1, 1, 1]), [0, 2, 4]);
+            assert!(pattern.find_all(&[1, 1]).is_empty());
+        }
+    }
+}
+
+/// A registry of the layouts of structs in the binary, e.g. for a debugging console to list every
+/// reversed structure and look them up by name at runtime.
+///
+/// Structs are added with `register_layouts!`, usually once at startup. The registry is a
+/// lock-free list of statics, so it works without allocating and from several threads.
+///
+/// ```
+/// use offsetter::{offset, register_layouts, registry};
+///
+/// offset!(
+///     pub struct EPROCESS[0xa40] {
+///         0x440 pub unique_process_id: u64,
+///         0x550 pub peb: u64
+///     }
+/// );
+///
+/// offset!(
+///     pub struct KTHREAD[0x430] {
+///         0x220 pub process: *mut EPROCESS
+///     }
+/// );
+///
+/// register_layouts!(EPROCESS, KTHREAD);
+///
+/// let process = registry::find("EPROCESS").unwrap();
+/// assert_eq!(process.field("peb").map(|field| field.offset), Some(0x550));
+///
+/// let mut names: Vec<_> = registry::layouts().map(|layout| layout.name).collect();
+/// names.sort();
+/// assert_eq!(names, ["EPROCESS", "KTHREAD"]);
+/// ```
+#[cfg(feature = "registry")]
+pub mod registry {
+    use core::ptr;
+    use core::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+
+    use crate::Layout;
+
+    static HEAD: AtomicPtr<Registration> = AtomicPtr::new(ptr::null_mut());
+
+    /// The entry of a struct in the registry, a static created by `register_layouts!`.
+    pub struct Registration {
+        layout: &'static Layout,
+        registered: AtomicBool,
+        next: AtomicPtr<Registration>,
+    }
+
+    impl Registration {
+        #[doc(hidden)]
+        pub const fn new(layout: &'static Layout) -> Self {
+            Self { layout, registered: AtomicBool::new(false), next: AtomicPtr::new(ptr::null_mut()) }
+        }
+
+        /// Adds the layout to the registry, unless this entry was added before.
+        pub fn register(&'static self) {
+            if self.registered.swap(true, Ordering::AcqRel) {
+                return;
+            }
+
+            let mut head = HEAD.load(Ordering::Acquire);
+            loop {
+                self.next.store(head, Ordering::Relaxed);
+                let this = self as *const Self as *mut Self;
+                match HEAD.compare_exchange_weak(head, this, Ordering::AcqRel, Ordering::Acquire) {
+                    Ok(_) => return,
+                    Err(current) => head = current,
+                }
+            }
+        }
+    }
+
+    /// Iterates over the registered layouts, the most recently registered first.
+    pub fn layouts() -> Layouts {
+        Layouts { next: HEAD.load(Ordering::Acquire) }
+    }
+
+    /// Looks up a registered layout by the name of its struct.
+    pub fn find(name: &str) -> Option<&'static Layout> {
+        layouts().find(|layout| layout.name == name)
+    }
+
+    /// The iterator returned by [`layouts`].
+    #[derive(Clone)]
+    pub struct Layouts {
+        next: *const Registration,
+    }
+
+    impl Iterator for Layouts {
+        type Item = &'static Layout;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            // Entries are statics that are never removed, so they live as long as the program.
+            let registration: &'static Registration = unsafe { self.next.as_ref()? };
+            self.next = registration.next.load(Ordering::Acquire);
+            Some(registration.layout)
+        }
+    }
+}
+
+/// Layouts of commonly used structures of the 64-bit Windows kernel, declared with `offset!`.
+///
+/// The structures whose layout is the same on every supported build, `LIST_ENTRY`,
+/// `UNICODE_STRING`, `CLIENT_ID`, `DRIVER_OBJECT`, `DEVICE_OBJECT` and `KPCR`, are always
+/// available. `EPROCESS`, `KTHREAD` and `ETHREAD` change between builds, and are declared in a
+/// module per build, enabled by the feature of the same name: `win10_19041` for Windows 10 20H1
+/// to 22H2, and `win11_22621` for Windows 11 22H2 and 23H2. Only the fields drivers commonly need
+/// are declared, and structures whose size changes between builds don't declare one.
+///
+/// ```
+/// use offsetter::windows::DRIVER_OBJECT;
+///
+/// let mut memory = [0u8; DRIVER_OBJECT::SIZE];
+/// memory[0x20..0x24].copy_from_slice(&0x4000u32.to_ne_bytes());
+/// let driver = unsafe { DRIVER_OBJECT::from_ptr(memory.as_ptr()) };
+/// assert_eq!(driver.driver_size(), 0x4000);
+/// assert_eq!(DRIVER_OBJECT::OFFSET_MAJOR_FUNCTION, 0x70);
+/// ```
+#[cfg(all(feature = "windows", target_pointer_width = "64"))]
+#[allow(non_camel_case_types)]
+pub mod windows {
+    use core::ffi::c_void;
+
+    use crate::offset;
+
+    offset!(
+        #[offset(debug, copy, from_ptr)]
+        pub struct LIST_ENTRY[0x10] {
+            0x0 pub flink: *mut LIST_ENTRY,
+            0x8 pub blink: *mut LIST_ENTRY
+        }
+    );
+
+    /// See [`UnicodeString`](crate::UnicodeString), whose `Debug` doesn't read the buffer.
+    pub type UNICODE_STRING = crate::UnicodeString;
+
+    offset!(
+        #[offset(debug, copy, from_ptr)]
+        pub struct CLIENT_ID[0x10] {
+            0x0 pub unique_process: *mut c_void,
+            0x8 pub unique_thread: *mut c_void
+        }
+    );
+
+    offset!(
+        #[offset(debug, from_ptr)]
+        pub struct DRIVER_OBJECT[0x150] {
+            0x0 pub type_: i16,
+            0x2 pub size: i16,
+            0x8 pub device_object: *mut DEVICE_OBJECT,
+            0x10 pub flags: u32,
+            0x18 pub driver_start: *mut c_void,
+            0x20 pub driver_size: u32,
+            0x28 pub driver_section: *mut c_void,
+            0x30 pub driver_extension: *mut c_void,
+            0x38 pub driver_name: UNICODE_STRING,
+            0x48 pub hardware_database: *mut UNICODE_STRING,
+            0x50 pub fast_io_dispatch: *mut c_void,
+            0x58 pub driver_init: *mut c_void,
+            0x60 pub driver_start_io: *mut c_void,
+            0x68 pub driver_unload: *mut c_void,
+            0x70 pub major_function: [*mut c_void; 28]
+        }
+    );
+
+    offset!(
+        #[offset(debug, from_ptr)]
+        pub struct DEVICE_OBJECT[0x150] {
+            0x0 pub type_: i16,
+            0x2 pub size: u16,
+            0x4 pub reference_count: i32,
+            0x8 pub driver_object: *mut DRIVER_OBJECT,
+            0x10 pub next_device: *mut DEVICE_OBJECT,
+            0x18 pub attached_device: *mut DEVICE_OBJECT,
+            0x20 pub current_irp: *mut c_void,
+            0x30 pub flags: u32,
+            0x34 pub characteristics: u32,
+            0x38 pub vpb: *mut c_void,
+            0x40 pub device_extension: *mut c_void,
+            0x48 pub device_type: u32,
+            0x4c pub stack_size: i8,
+            0x98 pub alignment_requirement: u32,
+            0x108 pub active_thread_count: u32,
+            0x110 pub security_descriptor: *mut c_void,
+            0x130 pub sector_size: u16,
+            0x138 pub device_object_extension: *mut c_void
+        }
+    );
+
+    offset!(
+        #[offset(debug, from_ptr)]
+        pub struct KPCR {
+            0x0 pub gdt_base: *mut c_void,
+            0x8 pub tss_base: *mut c_void,
+            0x10 pub user_rsp: u64,
+            0x18 pub self_pointer: *mut KPCR,
+            0x20 pub current_prcb: *mut c_void,
+            0x38 pub idt_base: *mut c_void,
+            0x50 pub irql: u8,
+            0x60 pub major_version: u16,
+            0x62 pub minor_version: u16,
+            0x108 pub kd_version_block: *mut c_void,
+            0x188 pub current_thread: *mut c_void
+        }
+    );
+
+    // The structures of each build live in a module of their own, so the features of several
+    // builds can be enabled at once. Only the offsets of `ETHREAD` differ between them so far.
+    #[cfg(any(feature = "win10_19041", feature = "win11_22621"))]
+    macro_rules! build_structures {
+        ($create_time:literal, $start_address:literal, $cid:literal, $win32_start_address:literal, $thread_list_entry:literal) => {
+            use core::ffi::c_void;
+
+            use super::{CLIENT_ID, LIST_ENTRY};
+            use crate::offset;
+
+            offset!(
+                #[offset(debug, from_ptr)]
+                pub struct EPROCESS {
+                    0x28 pub directory_table_base: u64,
+                    0x440 pub unique_process_id: *mut c_void,
+                    0x448 pub active_process_links: LIST_ENTRY,
+                    0x4b8 pub token: u64,
+                    0x540 pub inherited_from_unique_process_id: *mut c_void,
+                    0x550 pub peb: *mut c_void,
+                    0x5a8 pub image_file_name: [u8; 15],
+                    0x5e0 pub thread_list_head: LIST_ENTRY
+                }
+            );
+
+            offset!(
+                #[offset(debug, from_ptr)]
+                pub struct KTHREAD {
+                    0x28 pub initial_stack: *mut c_void,
+                    0x30 pub stack_limit: *mut c_void,
+                    0x38 pub stack_base: *mut c_void,
+                    0xf0 pub teb: *mut c_void,
+                    0x220 pub process: *mut EPROCESS
+                }
+            );
+
+            offset!(
+                #[offset(debug, from_ptr)]
+                pub struct ETHREAD {
+                    0x0 pub tcb: KTHREAD,
+                    $create_time pub create_time: i64,
+                    $start_address pub start_address: *mut c_void,
+                    $cid pub cid: CLIENT_ID,
+                    $win32_start_address pub win32_start_address: *mut c_void,
+                    $thread_list_entry pub thread_list_entry: LIST_ENTRY
+                }
+            );
+        };
+    }
+
+    /// `EPROCESS`, `KTHREAD` and `ETHREAD` of Windows 10 20H1 to 22H2, builds 19041 to 19045.
+    ///
+    /// ```
+    /// use offsetter::windows::win10_19041::ETHREAD;
+    ///
+    /// assert_eq!(ETHREAD::OFFSET_CID, 0x478);
+    /// ```
+    #[cfg(feature = "win10_19041")]
+    pub mod win10_19041 {
+        build_structures!(0x430, 0x450, 0x478, 0x4d0, 0x4e8);
+    }
+
+    /// `EPROCESS`, `KTHREAD` and `ETHREAD` of Windows 11 22H2 and 23H2, builds 22621 and 22631.
+    ///
+    /// ```
+    /// use offsetter::windows::win11_22621::ETHREAD;
+    ///
+    /// assert_eq!(ETHREAD::OFFSET_CID, 0x4c8);
+    /// ```
+    #[cfg(feature = "win11_22621")]
+    pub mod win11_22621 {
+        build_structures!(0x480, 0x4a0, 0x4c8, 0x520, 0x538);
+    }
+}
+
+/// Layouts and walkers for the RTTI MSVC emits for polymorphic classes, to check that an object
+/// really is of the class a struct is about to be overlaid as.
+///
+/// The slot in front of a vtable points to the complete object locator of its class, which leads
+/// to the type descriptor holding the decorated name of the class, e.g. `.?AVActor@game@@`, and
+/// to the class hierarchy descriptor listing its base classes. In 64-bit images they refer to each
+/// other by offsets from the image base, in 32-bit ones by addresses. `Rtti::read` reads them
+/// from the memory of a 64-bit process through a [`MemoryRead`], such as [`LocalMemory`] for the
+/// current one, and `Rtti::read32` from a 32-bit one.
+///
+/// ```
+/// use offsetter::{rtti::Rtti, MemoryRead};
+///
+/// struct Dump(Vec<u8>);
+///
+/// impl MemoryRead for Dump {
+///     type Error = ();
+///
+///     fn read(&self, address: u64, buffer: &mut [u8]) -> Result<(), ()> {
+///         let bytes = self.0.get(address as usize..address as usize + buffer.len()).ok_or(())?;
+///         buffer.copy_from_slice(bytes);
+///         Ok(())
+///     }
+/// }
+///
+/// // An image loaded at 0x1000, with an object at 0x1000 whose vtable is at 0x1108.
+/// let mut dump = Dump(vec![0; 0x1500]);
+/// let mut put = |address: usize, bytes: &[u8]| dump.0[address..][..bytes.len()].copy_from_slice(bytes);
+/// put(0x1000, &0x1108u64.to_le_bytes());
+/// put(0x1100, &0x1200u64.to_le_bytes());
+/// // The complete object locator, referring to the type descriptor at 0x300 and the class
+/// // hierarchy descriptor at 0x400 of the image.
+/// for (index, value) in [1u32, 0, 0, 0x300, 0x400, 0x200].into_iter().enumerate() {
+///     put(0x1200 + index * 4, &value.to_le_bytes());
+/// }
+/// put(0x1310, b".?AVActor@game@@\0");
+/// put(0x1350, b".?AVEntity@game@@\0");
+/// // Two base classes, the class itself and `game::Entity`.
+/// put(0x1408, &2u32.to_le_bytes());
+/// put(0x140c, &0x420u32.to_le_bytes());
+/// put(0x1420, &0x440u32.to_le_bytes());
+/// put(0x1424, &0x460u32.to_le_bytes());
+/// put(0x1440, &0x300u32.to_le_bytes());
+/// put(0x1460, &0x340u32.to_le_bytes());
+///
+/// let rtti = Rtti::read(&dump, 0x1000).unwrap();
+/// let mut buffer = [0; 64];
+/// assert_eq!(rtti.name(&mut buffer), Ok(".?AVActor@game@@"));
+/// assert_eq!(rtti.is_a("game::Entity"), Ok(true));
+/// assert_eq!(rtti.is_a(".?AVActor@game@@"), Ok(true));
+/// assert_eq!(rtti.is_a("game::Player"), Ok(false));
+/// ```
+#[cfg(feature = "rtti")]
+pub mod rtti {
+    use crate::{offset, read_remote, MemoryRead};
+
+    offset!(
+        /// `_RTTICompleteObjectLocator`, which the slot in front of a vtable points to.
+        #[offset(debug, copy, bytes, plain)]
+        pub struct RTTICompleteObjectLocator[0x18] {
+            /// 1 in 64-bit images and 0 in 32-bit ones.
+            0x0 pub signature: u32,
+            /// The offset of the vtable in the complete object.
+            0x4 pub offset: u32,
+            /// The offset of the constructor displacement.
+            0x8 pub cd_offset: u32,
+            /// The reference to the type descriptor of the class.
+            0xc pub type_descriptor: u32,
+            /// The reference to the class hierarchy descriptor of the class.
+            0x10 pub class_descriptor: u32,
+            /// The offset of the locator itself from the image base, only in 64-bit images.
+            0x14 pub self_offset: u32
+        }
+    );
+
+    offset!(
+        /// `_RTTIClassHierarchyDescriptor`, which lists the base classes of a class.
+        #[offset(debug, copy)]
+        pub struct RTTIClassHierarchyDescriptor[0x10] {
+            0x0 pub signature: u32,
+            0x4 pub attributes: u32,
+            /// The number of base classes, including the class itself.
+            0x8 pub num_base_classes: u32,
+            /// The reference to an array of references to the base class descriptors.
+            0xc pub base_class_array: u32
+        }
+    );
+
+    offset!(
+        /// `_RTTIBaseClassDescriptor`, one of the base classes of a class.
+        #[offset(debug, copy)]
+        pub struct RTTIBaseClassDescriptor[0x1c] {
+            /// The reference to the type descriptor of the base class.
+            0x0 pub type_descriptor: u32,
+            0x4 pub num_contained_bases: u32,
+            /// The offset of the base class in the class.
+            0x8 pub mdisp: i32,
+            0xc pub pdisp: i32,
+            0x10 pub vdisp: i32,
+            0x14 pub attributes: u32,
+            0x18 pub class_descriptor: u32
+        }
+    );
+
+    offset!(
+        /// `TypeDescriptor`, which is followed by the null-terminated decorated name of the type.
+        #[offset(dual, debug, copy)]
+        pub struct TypeDescriptor[0x8/0x10] {
+            0x0 pub vftable: Ptr,
+            0x4/0x8 pub spare: Ptr
+        }
+    );
+
+    /// Why the RTTI of an object couldn't be read.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub enum RttiError<E> {
+        /// The memory couldn't be read.
+        Read(E),
+        /// The complete object locator has the signature of the other pointer width or an offset
+        /// of itself that doesn't fit its address, or a name isn't valid UTF-8, so the object
+        /// likely has no RTTI.
+        Invalid,
+        /// A name is longer than the buffer it's read into.
+        NameTooLong,
+    }
+
+    impl<E: core::fmt::Display> core::fmt::Display for RttiError<E> {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            match self {
+                Self::Read(error) => write!(f, "the RTTI couldn't be read: {}", error),
+                Self::Invalid => f.write_str("the object has no valid RTTI"),
+                Self::NameTooLong => f.write_str("the name of the type doesn't fit into the buffer"),
+            }
+        }
+    }
+
+    impl<E: core::fmt::Debug + core::fmt::Display> core::error::Error for RttiError<E> {}
+
+    /// The RTTI of an object, read through a [`MemoryRead`].
+    pub struct Rtti<'r, R: ?Sized> {
+        reader: &'r R,
+        locator: RTTICompleteObjectLocator,
+        // The address references are relative to, which is 0 in 32-bit images.
+        image_base: u64,
+        name_offset: u64,
+    }
+
+    impl<'r, R: MemoryRead + ?Sized> Rtti<'r, R> {
+        /// Reads the RTTI of the object at `object` in the memory of a 64-bit process.
+        pub fn read(reader: &'r R, object: u64) -> Result<Self, RttiError<R::Error>> {
+            let vtable = unsafe { read_remote::<u64, R>(reader, object) }.map_err(RttiError::Read)?;
+            let address = unsafe { read_remote::<u64, R>(reader, vtable.wrapping_sub(8)) }.map_err(RttiError::Read)?;
+            let locator = unsafe { read_remote::<RTTICompleteObjectLocator, R>(reader, address) }.map_err(RttiError::Read)?;
+            // The locator is in the image, past its headers.
+            if locator.signature != 1 || locator.self_offset == 0 {
+                return Err(RttiError::Invalid);
+            }
+            let image_base = address.checked_sub(u64::from(locator.self_offset)).ok_or(RttiError::Invalid)?;
+            Ok(Self { reader, locator, image_base, name_offset: TypeDescriptor64::SIZE as u64 })
+        }
+
+        /// Reads the RTTI of the object at `object` in the memory of a 32-bit process.
+        pub fn read32(reader: &'r R, object: u64) -> Result<Self, RttiError<R::Error>> {
+            let vtable = unsafe { read_remote::<u32, R>(reader, object) }.map_err(RttiError::Read)?;
+            let address = unsafe { read_remote::<u32, R>(reader, u64::from(vtable.wrapping_sub(4))) }.map_err(RttiError::Read)?;
+            // The locators of 32-bit images end before `self_offset`.
+            let mut bytes = [0; RTTICompleteObjectLocator::SIZE];
+            reader.read(u64::from(address), &mut bytes[..RTTICompleteObjectLocator::OFFSET_SELF_OFFSET]).map_err(RttiError::Read)?;
+            let locator = RTTICompleteObjectLocator::try_from_bytes(&bytes).ok_or(RttiError::Invalid)?;
+            if locator.signature != 0 {
+                return Err(RttiError::Invalid);
+            }
+            Ok(Self { reader, locator, image_base: 0, name_offset: TypeDescriptor32::SIZE as u64 })
+        }
+
+        /// The complete object locator of the class of the object.
+        pub fn locator(&self) -> &RTTICompleteObjectLocator {
+            &self.locator
+        }
+
+        /// The address a reference between the structures refers to.
+        pub fn address(&self, reference: u32) -> u64 {
+            self.image_base.wrapping_add(u64::from(reference))
+        }
+
+        /// Reads the decorated name of the class of the object, e.g. `.?AVActor@game@@`, into
+        /// `buffer`.
+        pub fn name<'b>(&self, buffer: &'b mut [u8]) -> Result<&'b str, RttiError<R::Error>> {
+            self.name_of(self.locator.type_descriptor, buffer)
+        }
+
+        /// Reads the decorated name of the type descriptor `reference` refers to into `buffer`.
+        pub fn name_of<'b>(&self, reference: u32, buffer: &'b mut [u8]) -> Result<&'b str, RttiError<R::Error>> {
+            let address = self.address(reference).wrapping_add(self.name_offset);
+            // The name is read a byte at a time, since the memory after it may not be readable.
+            for length in 0..=buffer.len() {
+                let byte = unsafe { read_remote::<u8, R>(self.reader, address + length as u64) }.map_err(RttiError::Read)?;
+                if byte == 0 {
+                    return core::str::from_utf8(&buffer[..length]).map_err(|_| RttiError::Invalid);
+                }
+                *buffer.get_mut(length).ok_or(RttiError::NameTooLong)? = byte;
+            }
+            Err(RttiError::NameTooLong)
+        }
+
+        /// Reads the class hierarchy descriptor of the class of the object.
+        pub fn hierarchy(&self) -> Result<RTTIClassHierarchyDescriptor, RttiError<R::Error>> {
+            unsafe { read_remote(self.reader, self.address(self.locator.class_descriptor)) }.map_err(RttiError::Read)
+        }
+
+        /// Walks the base classes of the class of the object, starting with the class itself.
+        pub fn base_classes(&self) -> Result<BaseClasses<'_, 'r, R>, RttiError<R::Error>> {
+            let hierarchy = self.hierarchy()?;
+            Ok(BaseClasses { rtti: self, array: self.address(hierarchy.base_class_array), index: 0, count: hierarchy.num_base_classes })
+        }
+
+        /// Whether the class of the object is `class` or derives from it. `class` is either a
+        /// decorated name such as `.?AVActor@game@@`, or a path such as `game::Actor`, which
+        /// matches classes and structs that aren't templates.
+        pub fn is_a(&self, class: &str) -> Result<bool, RttiError<R::Error>> {
+            let mut buffer = [0; 256];
+            for base in self.base_classes()? {
+                if matches(self.name_of(base?.type_descriptor, &mut buffer)?, class) {
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        }
+    }
+
+    /// The iterator returned by [`Rtti::base_classes`].
+    pub struct BaseClasses<'a, 'r, R: ?Sized> {
+        rtti: &'a Rtti<'r, R>,
+        array: u64,
+        index: u32,
+        count: u32,
+    }
+
+    impl<R: MemoryRead + ?Sized> Iterator for BaseClasses<'_, '_, R> {
+        type Item = Result<RTTIBaseClassDescriptor, RttiError<R::Error>>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            if self.index >= self.count {
+                return None;
+            }
+            let slot = self.array + u64::from(self.index) * 4;
+            self.index += 1;
+            let descriptor = unsafe { read_remote::<u32, R>(self.rtti.reader, slot) }
+                .and_then(|reference| unsafe { read_remote(self.rtti.reader, self.rtti.address(reference)) });
+            if descriptor.is_err() {
+                self.index = self.count;
+            }
+            Some(descriptor.map_err(RttiError::Read))
+        }
+    }
+
+    // Decorated names of classes and structs are `.?AV` or `.?AU` followed by the components of
+    // their path in reverse, each followed by `@`, and another `@`.
+    fn matches(name: &str, class: &str) -> bool {
+        if class.starts_with(".?") {
+            return name == class;
+        }
+        match name.strip_prefix(".?AV").or_else(|| name.strip_prefix(".?AU")).and_then(|name| name.strip_suffix("@@")) {
+            Some(path) => path.split('@').eq(class.rsplit("::")),
+            None => false,
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        struct Dump([u8; 0x600]);
+
+        impl MemoryRead for Dump {
+            type Error = ();
+
+            fn read(&self, address: u64, buffer: &mut [u8]) -> Result<(), ()> {
+                let start = usize::try_from(address).map_err(|_| ())?;
+                let bytes = self.0.get(start..).and_then(|bytes| bytes.get(..buffer.len())).ok_or(())?;
+                buffer.copy_from_slice(bytes);
+                Ok(())
+            }
+        }
+
+        impl Dump {
+            fn put(&mut self, address: usize, bytes: &[u8]) {
+                self.0[address..][..bytes.len()].copy_from_slice(bytes);
+            }
+
+            // An image loaded at 0x100 with an object at 0x0 of the class `game::Actor` deriving
+            // from `game::Entity`, and the complete object locator at 0x200.
+            fn new(locator: [u32; 6]) -> Self {
+                let mut dump = Self([0; 0x600]);
+                dump.put(0x0, &0x108u64.to_le_bytes());
+                dump.put(0x100, &0x200u64.to_le_bytes());
+                for (index, value) in locator.into_iter().enumerate() {
+                    dump.put(0x200 + index * 4, &value.to_le_bytes());
+                }
+                dump.put(0x310, b".?AVActor@game@@\0");
+                dump.put(0x350, b".?AUEntity@game@@\0");
+                dump.put(0x408, &2u32.to_le_bytes());
+                dump.put(0x40c, &0x320u32.to_le_bytes());
+                dump.put(0x420, &0x340u32.to_le_bytes());
+                dump.put(0x424, &0x360u32.to_le_bytes());
+                dump.put(0x440, &0x200u32.to_le_bytes());
+                dump.put(0x460, &0x240u32.to_le_bytes());
+                dump
+            }
+        }
+
+        const LOCATOR: [u32; 6] = [1, 0, 0, 0x200, 0x300, 0x100];
+
+        #[test]
+        fn read() {
+            let dump = Dump::new(LOCATOR);
+            let rtti = Rtti::read(&dump, 0).unwrap();
+            assert_eq!(rtti.address(0x200), 0x300);
+            assert_eq!(rtti.hierarchy().unwrap().num_base_classes(), 2);
+            assert_eq!(rtti.name(&mut [0; 16]), Ok(".?AVActor@game@@"));
+            assert_eq!(rtti.name(&mut [0; 15]), Err(RttiError::NameTooLong));
+            assert!(rtti.is_a("game::Actor").unwrap());
+            assert!(rtti.is_a("game::Entity").unwrap());
+            assert!(rtti.is_a(".?AUEntity@game@@").unwrap());
+            assert!(!rtti.is_a("Entity").unwrap());
+            assert!(!rtti.is_a("other::game::Entity").unwrap());
+        }
+
+        #[test]
+        fn bad_signature() {
+            let dump = Dump::new([0, 0, 0, 0x200, 0x300, 0x100]);
+            assert_eq!(Rtti::read(&dump, 0).err(), Some(RttiError::Invalid));
+            assert_eq!(Rtti::read(&dump, 0x100).err(), Some(RttiError::Invalid));
+
+            let dump = Dump::new([2, 0, 0, 0x200, 0x300, 0x100]);
+            assert_eq!(Rtti::read(&dump, 0).err(), Some(RttiError::Invalid));
+
+            // 32-bit objects point to their vtable with 4 bytes, and a 64-bit locator is invalid.
+            let mut dump = Dump::new(LOCATOR);
+            dump.put(0x500, &0x104u32.to_le_bytes());
+            assert_eq!(Rtti::read32(&dump, 0x500).err(), Some(RttiError::Invalid));
+        }
+
+        #[test]
+        fn bad_self_offset() {
+            for self_offset in [0, 0x201, u32::MAX] {
+                let dump = Dump::new([1, 0, 0, 0x200, 0x300, self_offset]);
+                assert_eq!(Rtti::read(&dump, 0).err(), Some(RttiError::Invalid));
+            }
+            let dump = Dump::new([1, 0, 0, 0x200, 0x300, 0x200]);
+            assert_eq!(Rtti::read(&dump, 0).unwrap().address(0), 0);
+        }
+
+        #[test]
+        fn bad_memory() {
+            let mut dump = Dump::new(LOCATOR);
+            assert_eq!(Rtti::read(&dump, 0x5fc).err(), Some(RttiError::Read(())));
+            dump.put(0x1b, &[0xff]);
+            assert_eq!(Rtti::read(&dump, 0x1b - 7).err(), Some(RttiError::Read(())));
+
+            dump.put(0x314, &[0xff]);
+            assert_eq!(Rtti::read(&dump, 0).unwrap().name(&mut [0; 32]), Err(RttiError::Invalid));
+            dump.put(0x408, &3u32.to_le_bytes());
+            dump.put(0x428, &0x1000u32.to_le_bytes());
+            let rtti = Rtti::read(&dump, 0).unwrap();
+            let bases: [_; 4] = core::array::from_fn({
+                let mut bases = rtti.base_classes().unwrap();
+                move |_| bases.next().map(|base| base.map(|base| base.type_descriptor))
+            });
+            assert_eq!(bases, [Some(Ok(0x200)), Some(Ok(0x240)), Some(Err(RttiError::Read(()))), None]);
+        }
+    }
+}
+
+/// Reads the base addresses per-thread and per-CPU structures are found at, such as the `TEB` of
+/// the current thread or the `KPCR` of the current processor on Windows, to view them with the
+/// `from_ptr` of a struct declared with `offset!`.
+///
+/// On x86 and x86-64 these structures start with a pointer to themselves, which `gs_pointer` and
+/// `fs_pointer` read at an offset from the segment base, e.g. `gs_pointer(0x30)` for the `TEB` in
+/// 64-bit Windows processes, `gs_pointer(0x18)` for the `KPCR` in the 64-bit Windows kernel,
+/// `fs_pointer(0x18)` for the `TEB` in 32-bit Windows processes, and `fs_pointer(0)` for the
+/// thread control block on x86-64 Linux. On AArch64 the thread ID registers hold the addresses
+/// themselves, e.g. `tpidr_el0` the thread pointer on Linux.
+///
+/// ```
+/// # #[cfg(all(target_os = "linux", target_arch = "x86_64"))] {
+/// use offsetter::{offset, segment::fs_pointer};
+///
+/// offset!(
+///     #[offset(from_ptr)]
+///     pub struct TCB {
+///         0x0 pub self_: *mut u8
+///     }
+/// );
+///
+/// let tcb = unsafe { TCB::from_ptr(fs_pointer(0)) };
+/// assert_eq!(tcb.self_(), unsafe { fs_pointer(0) });
+/// # }
+/// ```
+#[cfg(any(target_arch = "x86_64", target_arch = "x86", target_arch = "aarch64"))]
+pub mod segment {
+    use core::arch::asm;
+
+    /// Reads the pointer at `offset` from the base of the `gs` segment.
+    ///
+    /// # Safety
+    ///
+    /// The pointer-sized bytes at `offset` from the base have to be readable.
+    #[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+    pub unsafe fn gs_pointer(offset: usize) -> *mut u8 {
+        let pointer: usize;
+        unsafe { asm!("mov {}, gs:[{}]", out(reg) pointer, in(reg) offset, options(nostack, readonly, preserves_flags)) };
+        pointer as *mut u8
+    }
+
+    /// Reads the pointer at `offset` from the base of the `fs` segment.
+    ///
+    /// # Safety
+    ///
+    /// The pointer-sized bytes at `offset` from the base have to be readable.
+    #[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+    pub unsafe fn fs_pointer(offset: usize) -> *mut u8 {
+        let pointer: usize;
+        unsafe { asm!("mov {}, fs:[{}]", out(reg) pointer, in(reg) offset, options(nostack, readonly, preserves_flags)) };
+        pointer as *mut u8
+    }
+
+    /// Reads `TPIDR_EL0`, the thread pointer of user mode, which points to the thread control
+    /// block on Linux.
+    #[cfg(target_arch = "aarch64")]
+    pub fn tpidr_el0() -> *mut u8 {
+        let pointer: usize;
+        unsafe { asm!("mrs {}, tpidr_el0", out(reg) pointer, options(nomem, nostack, preserves_flags)) };
+        pointer as *mut u8
+    }
+
+    /// Reads `TPIDRRO_EL0`, the thread ID register user mode can only read.
+    #[cfg(target_arch = "aarch64")]
+    pub fn tpidrro_el0() -> *mut u8 {
+        let pointer: usize;
+        unsafe { asm!("mrs {}, tpidrro_el0", out(reg) pointer, options(nomem, nostack, preserves_flags)) };
+        pointer as *mut u8
+    }
+
+    /// Reads `TPIDR_EL1`, the thread ID register of the kernel, which points to the `KPCR` of the
+    /// current processor in the Windows kernel.
+    ///
+    /// # Safety
+    ///
+    /// Has to be called at EL1 or higher, it traps in user mode.
+    #[cfg(target_arch = "aarch64")]
+    pub unsafe fn tpidr_el1() -> *mut u8 {
+        let pointer: usize;
+        unsafe { asm!("mrs {}, tpidr_el1", out(reg) pointer, options(nomem, nostack, preserves_flags)) };
+        pointer as *mut u8
+    }
+
+    #[cfg(all(test, any(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")), all(windows, any(target_arch = "x86_64", target_arch = "x86")))))]
+    mod tests {
+        extern crate std;
+
+        use super::*;
+
+        // The address of the block of the current thread, the `TEB` on Windows.
+        fn thread_pointer() -> usize {
+            #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+            let pointer = unsafe { fs_pointer(0) };
+            #[cfg(target_arch = "aarch64")]
+            let pointer = tpidr_el0();
+            #[cfg(all(windows, target_arch = "x86_64"))]
+            let pointer = unsafe { gs_pointer(0x30) };
+            #[cfg(all(windows, target_arch = "x86"))]
+            let pointer = unsafe { fs_pointer(0x18) };
+            pointer as usize
+        }
+
+        #[test]
+        fn per_thread() {
+            let here = thread_pointer();
+            assert_ne!(here, 0);
+            assert_eq!(thread_pointer(), here);
+            assert_ne!(std::thread::spawn(thread_pointer).join().unwrap(), here);
+        }
+
+        #[cfg(target_os = "linux")]
+        #[test]
+        fn thread_locals() {
+            // Static thread locals are allocated right next to the thread control block.
+            std::thread_local!(static LOCAL: u8 = const { 0 });
+            let local = LOCAL.with(|local| local as *const u8 as usize);
+            assert!(local.abs_diff(thread_pointer()) < 0x10000, "{:#x} is far from {:#x}", local, thread_pointer());
+        }
+
+        #[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+        #[test]
+        fn self_pointer() {
+            // The block holds a pointer to itself at the offset it was read from.
+            let offset = if cfg!(target_os = "linux") { 0 } else if cfg!(target_arch = "x86_64") { 0x30 } else { 0x18 };
+            let block = thread_pointer();
+            assert_eq!(unsafe { *((block + offset) as *const usize) }, block);
+        }
+
+        #[cfg(target_arch = "aarch64")]
+        #[test]
+        fn read_only_thread_id() {
+            // Linux leaves the read-only register 0 in user mode.
+            assert!(tpidrro_el0().is_null());
+        }
+    }
+}
+
+/// The build number of a Windows release, e.g. 19045 for Windows 10 22H2, which picks the
+/// version of a struct declared with `offset_versions!` at runtime.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OsBuild(pub u32);
+
+impl OsBuild {
+    /// The build of the running Windows, as reported by `RtlGetVersion`.
+    #[cfg(all(feature = "std", windows))]
+    pub fn current() -> Self {
+        #[repr(C)]
+        struct OsVersionInfo {
+            size: u32,
+            major_version: u32,
+            minor_version: u32,
+            build_number: u32,
+            platform_id: u32,
+            service_pack: [u16; 128],
+        }
+
+        #[link(name = "ntdll")]
+        extern "system" {
+            fn RtlGetVersion(info: *mut OsVersionInfo) -> i32;
+        }
+
+        let mut info = OsVersionInfo {
+            size: core::mem::size_of::<OsVersionInfo>() as u32,
+            major_version: 0,
+            minor_version: 0,
+            build_number: 0,
+            platform_id: 0,
+            service_pack: [0; 128],
+        };
+        // RtlGetVersion can't fail for an OSVERSIONINFOW of the right size.
+        unsafe { RtlGetVersion(&mut info) };
+        Self(info.build_number)
+    }
+}
+
+/// Implemented for the types `atomic` fields can have, which are stored as their atomic
+/// counterpart with the same size.
+pub trait Atomic: Sized {
+    /// The atomic type the field is stored as, e.g. `AtomicU32` for `u32`.
+    type Atomic;
+
+    /// Loads the value of the field.
+    fn load(atomic: &Self::Atomic, ordering: Ordering) -> Self;
+
+    /// Stores a value into the field.
+    fn store(atomic: &Self::Atomic, value: Self, ordering: Ordering);
+
+    /// Returns the value of an atomic that isn't shared.
+    fn into_inner(atomic: Self::Atomic) -> Self;
+}
+
+macro_rules! impl_atomic {
+    ($($width:literal: $($ty:ty => $atomic:ident),+;)*) => {
+        $($(
+            #[cfg(target_has_atomic = $width)]
+            impl Atomic for $ty {
+                type Atomic = core::sync::atomic::$atomic;
+
+                fn load(atomic: &Self::Atomic, ordering: Ordering) -> Self {
+                    atomic.load(ordering)
+                }
+
+                fn store(atomic: &Self::Atomic, value: Self, ordering: Ordering) {
+                    atomic.store(value, ordering)
+                }
+
+                fn into_inner(atomic: Self::Atomic) -> Self {
+                    atomic.into_inner()
+                }
+            }
+        )+)*
+    };
+}
+
+impl_atomic! {
+    "8": bool => AtomicBool, u8 => AtomicU8, i8 => AtomicI8;
+    "16": u16 => AtomicU16, i16 => AtomicI16;
+    "32": u32 => AtomicU32, i32 => AtomicI32;
+    "64": u64 => AtomicU64, i64 => AtomicI64;
+    "ptr": usize => AtomicUsize, isize => AtomicIsize;
+}
+
+#[cfg(target_has_atomic = "ptr")]
+impl<T> Atomic for *mut T {
+    type Atomic = core::sync::atomic::AtomicPtr<T>;
+
+    fn load(atomic: &Self::Atomic, ordering: Ordering) -> Self {
+        atomic.load(ordering)
+    }
+
+    fn store(atomic: &Self::Atomic, value: Self, ordering: Ordering) {
+        atomic.store(value, ordering)
+    }
+
+    fn into_inner(atomic: Self::Atomic) -> Self {
+        atomic.into_inner()
+    }
+}
+
+/// Implemented for the types the fields of structs with the `ffi` option can have, which have a
+/// layout C code agrees on.
+///
+/// # Safety
+///
+/// The type has to be `#[repr(C)]`, `#[repr(transparent)]` over an FFI-safe type, or a primitive
+/// with a C equivalent. Structs declared with the `ffi` option implement it themselves.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` isn't known to be FFI-safe",
+    note = "implement `offsetter::FfiSafe` for types that are `#[repr(C)]`, wide pointers and `#[repr(Rust)]` types can't cross an `extern \"C\"` boundary"
+)]
+pub unsafe trait FfiSafe {}
+
+macro_rules! impl_ffi_safe {
+    ($($ty:ty),*) => {
+        $(unsafe impl FfiSafe for $ty {})*
+    };
+}
+
+impl_ffi_safe!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64, bool);
+
+unsafe impl<T> FfiSafe for *const T {}
+unsafe impl<T> FfiSafe for *mut T {}
+unsafe impl<T> FfiSafe for &T {}
+unsafe impl<T> FfiSafe for &mut T {}
+unsafe impl<T> FfiSafe for Option<&T> {}
+unsafe impl<T> FfiSafe for Option<&mut T> {}
+unsafe impl<T> FfiSafe for core::ptr::NonNull<T> {}
+unsafe impl<T> FfiSafe for Option<core::ptr::NonNull<T>> {}
+unsafe impl<T: FfiSafe, const N: usize> FfiSafe for [T; N] {}
+
+macro_rules! impl_ffi_safe_fn {
+    ($($argument:ident)*) => {
+        unsafe impl<R $(, $argument)*> FfiSafe for extern "C" fn($($argument),*) -> R {}
+        unsafe impl<R $(, $argument)*> FfiSafe for Option<extern "C" fn($($argument),*) -> R> {}
+        unsafe impl<R $(, $argument)*> FfiSafe for unsafe extern "C" fn($($argument),*) -> R {}
+        unsafe impl<R $(, $argument)*> FfiSafe for Option<unsafe extern "C" fn($($argument),*) -> R> {}
+        unsafe impl<R $(, $argument)*> FfiSafe for extern "system" fn($($argument),*) -> R {}
+        unsafe impl<R $(, $argument)*> FfiSafe for Option<extern "system" fn($($argument),*) -> R> {}
+        unsafe impl<R $(, $argument)*> FfiSafe for unsafe extern "system" fn($($argument),*) -> R {}
+        unsafe impl<R $(, $argument)*> FfiSafe for Option<unsafe extern "system" fn($($argument),*) -> R> {}
+    };
+}
+
+impl_ffi_safe_fn!();
+impl_ffi_safe_fn!(A);
+impl_ffi_safe_fn!(A B);
+impl_ffi_safe_fn!(A B C);
+impl_ffi_safe_fn!(A B C D);
+impl_ffi_safe_fn!(A B C D E);
+impl_ffi_safe_fn!(A B C D E F);
+
+/// Fails to compile unless `T` implements `FfiSafe`, see the `ffi` option.
+#[doc(hidden)]
+pub const fn assert_ffi_safe<T: FfiSafe + ?Sized>() {}
+
+/// Reads memory that isn't part of the current process, e.g. that of another process, a virtual
+/// machine or an emulator, see the `remote` option.
+pub trait MemoryRead {
+    /// The error returned when the memory can't be read.
+    type Error;
+
+    /// Fills `buffer` with the bytes at `address`.
+    fn read(&self, address: u64, buffer: &mut [u8]) -> Result<(), Self::Error>;
+}
+
+/// Writes memory that isn't part of the current process, see [`MemoryRead`].
+pub trait MemoryWrite: MemoryRead {
+    /// Writes `data` to the bytes at `address`.
+    fn write(&self, address: u64, data: &[u8]) -> Result<(), Self::Error>;
+}
+
+impl<R: MemoryRead + ?Sized> MemoryRead for &R {
+    type Error = R::Error;
+
+    fn read(&self, address: u64, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        (**self).read(address, buffer)
+    }
+}
+
+impl<R: MemoryWrite + ?Sized> MemoryWrite for &R {
+    fn write(&self, address: u64, data: &[u8]) -> Result<(), Self::Error> {
+        (**self).write(address, data)
+    }
+}
+
+/// Reads the memory of the current process, so the walkers taking a [`MemoryRead`] also work on
+/// local objects.
+#[derive(Clone, Copy, Debug)]
+pub struct LocalMemory(());
+
+impl LocalMemory {
+    /// Creates the reader.
+    ///
+    /// # Safety
+    ///
+    /// Every address read through it has to be readable for the length of the read.
+    pub const unsafe fn new() -> Self {
+        Self(())
+    }
+}
+
+impl MemoryRead for LocalMemory {
+    type Error = core::convert::Infallible;
+
+    fn read(&self, address: u64, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        unsafe { core::ptr::copy_nonoverlapping(address as usize as *const u8, buffer.as_mut_ptr(), buffer.len()) };
+        Ok(())
+    }
+}
+
+/// Caches the `SIZE` bytes of a struct at `base` in memory read through `R`, so that every byte
+/// is read at most once until the next `refresh`, see the `cached` method of the views generated
+/// by the `remote` option.
+///
+/// Bytes are read the first time they're accessed, and written bytes stay in the cache and are
+/// marked dirty until `flush` writes them back. Accesses that aren't entirely within the struct go
+/// straight to `R`.
+///
+/// ```
+/// # use offsetter::{offset, MemoryRead, MemoryWrite};
+/// # use std::cell::{Cell, RefCell};
+/// struct Process {
+///     memory: RefCell<Vec<u8>>,
+///     reads: Cell<usize>,
+/// }
+///
+/// impl MemoryRead for Process {
+///     type Error = ();
+///
+///     fn read(&self, address: u64, buffer: &mut [u8]) -> Result<(), ()> {
+///         self.reads.set(self.reads.get() + 1);
+///         buffer.copy_from_slice(&self.memory.borrow()[address as usize..][..buffer.len()]);
+///         Ok(())
+///     }
+/// }
+///
+/// impl MemoryWrite for Process {
+///     fn write(&self, address: u64, data: &[u8]) -> Result<(), ()> {
+///         self.memory.borrow_mut()[address as usize..][..data.len()].copy_from_slice(data);
+///         Ok(())
+///     }
+/// }
+///
+/// offset!(
+///     #[offset(remote)]
+///     pub struct Entity {
+///         0x0 pub health: u32,
+///         0x8 pub armor: u32
+///     }
+/// );
+///
+/// let process = Process { memory: RefCell::new(vec![0; 0x20]), reads: Cell::new(0) };
+/// let entity = unsafe { EntityRemote::new(&process, 0x10) }.cached();
+/// assert_eq!(entity.health(), Ok(0));
+/// assert_eq!(entity.health(), Ok(0));
+/// assert_eq!(process.reads.get(), 1);
+///
+/// entity.set_armor(50).unwrap();
+/// assert_eq!(entity.armor(), Ok(50));
+/// assert_eq!(process.memory.borrow()[0x18], 0);
+///
+/// entity.reader().flush().unwrap();
+/// assert_eq!(process.memory.borrow()[0x18], 50);
+///
+/// entity.reader().refresh();
+/// assert_eq!(entity.health(), Ok(0));
+/// assert_eq!(process.reads.get(), 2);
+/// ```
+pub struct Cached<R, const SIZE: usize> {
+    reader: R,
+    base: u64,
+    bytes: core::cell::UnsafeCell<[u8; SIZE]>,
+    loaded: [core::cell::Cell<bool>; SIZE],
+    dirty: [core::cell::Cell<bool>; SIZE],
+}
+
+impl<R, const SIZE: usize> Cached<R, SIZE> {
+    /// Creates an empty cache of the bytes at `base`.
+    pub fn new(reader: R, base: u64) -> Self {
+        Self {
+            reader,
+            base,
+            bytes: core::cell::UnsafeCell::new([0; SIZE]),
+            loaded: [const { core::cell::Cell::new(false) }; SIZE],
+            dirty: [const { core::cell::Cell::new(false) }; SIZE],
+        }
+    }
+
+    /// Returns the reader the bytes are read through.
+    pub fn reader(&self) -> &R {
+        &self.reader
+    }
+
+    /// Forgets the bytes that were read, so they're read again the next time they're accessed.
+    /// Dirty bytes are kept until they're flushed.
+    pub fn refresh(&self) {
+        for (loaded, dirty) in self.loaded.iter().zip(&self.dirty) {
+            loaded.set(dirty.get());
+        }
+    }
+
+    /// Returns whether any bytes were written but not flushed yet.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty.iter().any(core::cell::Cell::get)
+    }
+
+    // The range of the cached bytes an access covers, if it's entirely within the struct.
+    fn range(&self, address: u64, len: usize) -> Option<core::ops::Range<usize>> {
+        let start = usize::try_from(address.checked_sub(self.base)?).ok()?;
+        let end = start.checked_add(len)?;
+        (end <= SIZE).then_some(start..end)
+    }
+}
+
+impl<R: MemoryWrite, const SIZE: usize> Cached<R, SIZE> {
+    /// Writes the dirty bytes back, one run of consecutive bytes at a time.
+    pub fn flush(&self) -> Result<(), R::Error> {
+        let mut start = 0;
+        while start < SIZE {
+            if !self.dirty[start].get() {
+                start += 1;
+                continue;
+            }
+
+            let end = (start..SIZE).find(|&index| !self.dirty[index].get()).unwrap_or(SIZE);
+            // The bytes are only borrowed for the write, and `Cached` isn't `Sync`.
+            let bytes = unsafe { &(&*self.bytes.get())[start..end] };
+            self.reader.write(self.base + start as u64, bytes)?;
+            self.dirty[start..end].iter().for_each(|dirty| dirty.set(false));
+            start = end;
+        }
+        Ok(())
+    }
+}
+
+impl<R: MemoryRead, const SIZE: usize> MemoryRead for Cached<R, SIZE> {
+    type Error = R::Error;
+
+    fn read(&self, address: u64, buffer: &mut [u8]) -> Result<(), R::Error> {
+        let Some(range) = self.range(address, buffer.len()) else {
+            return self.reader.read(address, buffer);
+        };
+
+        // The missing bytes are read in one go, using the buffer as scratch space.
+        let loaded = &self.loaded[range.clone()];
+        if let (Some(first), Some(last)) = (loaded.iter().position(|loaded| !loaded.get()), loaded.iter().rposition(|loaded| !loaded.get())) {
+            self.reader.read(address + first as u64, &mut buffer[first..=last])?;
+            let bytes = unsafe { &mut *self.bytes.get() };
+            for index in first..=last {
+                if !loaded[index].get() {
+                    bytes[range.start + index] = buffer[index];
+                    loaded[index].set(true);
+                }
+            }
+        }
+
+        buffer.copy_from_slice(unsafe { &(&*self.bytes.get())[range] });
+        Ok(())
+    }
+}
+
+impl<R: MemoryWrite, const SIZE: usize> MemoryWrite for Cached<R, SIZE> {
+    fn write(&self, address: u64, data: &[u8]) -> Result<(), R::Error> {
+        let Some(range) = self.range(address, data.len()) else {
+            return self.reader.write(address, data);
+        };
+
+        unsafe { (&mut *self.bytes.get())[range.clone()].copy_from_slice(data) };
+        for index in range {
+            self.loaded[index].set(true);
+            self.dirty[index].set(true);
+        }
+        Ok(())
+    }
+}
+
+/// Reads a `T` from the bytes at `address`, see the `remote` option.
+///
+/// # Safety
+///
+/// The bytes have to be a valid `T`.
+#[doc(hidden)]
+pub unsafe fn read_remote<T, R: MemoryRead + ?Sized>(reader: &R, address: u64) -> Result<T, R::Error> {
+    let mut value = core::mem::MaybeUninit::<T>::zeroed();
+    let buffer = unsafe { core::slice::from_raw_parts_mut(value.as_mut_ptr().cast::<u8>(), core::mem::size_of::<T>()) };
+    reader.read(address, buffer)?;
+    Ok(unsafe { value.assume_init() })
+}
+
+/// Reads the field `getter` returns from the bytes at `address`, see `pointer_path!`.
+///
+/// # Safety
+///
+/// The bytes have to be a valid `F`.
+#[doc(hidden)]
+pub unsafe fn read_field<T, F, R: MemoryRead + ?Sized>(reader: &R, address: u64, _getter: fn(&T) -> F) -> Result<F, R::Error> {
+    unsafe { read_remote(reader, address) }
+}
+
+/// Writes the bytes of `value` to `address`, see the `remote` option.
+#[doc(hidden)]
+pub fn write_remote<T, W: MemoryWrite + ?Sized>(writer: &W, address: u64, value: &T) -> Result<(), W::Error> {
+    let data = unsafe { core::slice::from_raw_parts((value as *const T).cast::<u8>(), core::mem::size_of::<T>()) };
+    writer.write(address, data)
+}
+
+/// Walks a circular linked list of structs whose links point to the links of the next struct,
+/// like `LIST_ENTRY` in Windows, yielding a pointer to every struct, see `list_entries!`.
+///
+/// The walk follows the forward pointer at the start of every link until it's back at the head
+/// or reaches a null pointer. The head itself isn't yielded.
+pub struct ListEntries<T> {
+    head: *const u8,
+    next: *const u8,
+    offset: usize,
+    _entries: core::marker::PhantomData<*const T>,
+}
+
+impl<T> ListEntries<T> {
+    /// Walks the list whose head is at `head`, with the links at `offset` in every struct.
+    ///
+    /// # Safety
+    ///
+    /// `head` and the links of every struct in the list have to start with a readable pointer to
+    /// the next link, for as long as the list is walked.
+    pub unsafe fn new(head: *const u8, offset: usize) -> Self {
+        let next = unsafe { head.cast::<*const u8>().read_unaligned() };
+        Self { head, next, offset, _entries: core::marker::PhantomData }
+    }
+}
+
+impl<T> Iterator for ListEntries<T> {
+    type Item = *const T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next.is_null() || self.next == self.head {
+            return None;
+        }
+        let link = self.next;
+        self.next = unsafe { link.cast::<*const u8>().read_unaligned() };
+        Some(link.wrapping_sub(self.offset).cast::<T>())
+    }
+}
+
+/// Walks a list like [`ListEntries`] in memory read through a [`MemoryRead`], yielding the
+/// address of every struct, see `list_entries!`. Forward pointers are read as 64-bit addresses.
+pub struct RemoteListEntries<'r, R: ?Sized> {
+    reader: &'r R,
+    head: u64,
+    // The link whose forward pointer leads to the next struct, or `None` once the walk ended.
+    link: Option<u64>,
+    offset: u64,
+}
+
+impl<'r, R: MemoryRead + ?Sized> RemoteListEntries<'r, R> {
+    /// Walks the list whose head is at `head`, with the links at `offset` in every struct.
+    pub fn new(reader: &'r R, head: u64, offset: u64) -> Self {
+        Self { reader, head, link: Some(head), offset }
+    }
+}
+
+impl<R: MemoryRead + ?Sized> Iterator for RemoteListEntries<'_, R> {
+    type Item = Result<u64, R::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // A link that can't be read ends the walk after its error.
+        let next = match unsafe { read_remote::<u64, R>(self.reader, self.link?) } {
+            Ok(next) => next,
+            Err(error) => {
+                self.link = None;
+                return Some(Err(error));
+            }
+        };
+        if next == 0 || next == self.head {
+            self.link = None;
+            return None;
+        }
+        self.link = Some(next);
+        Some(Ok(next.wrapping_sub(self.offset)))
+    }
+}
+
+/// Returns the function in slot `index` of the vtable whose pointer is at `offset` in the object
+/// at `object`, as an `F` such as `unsafe extern "C" fn(*mut c_void) -> u32`, see `vtable!`.
+///
+/// # Safety
+///
+/// The object has to hold a pointer to its vtable at `offset`, whose slot `index` has to hold a
+/// function of type `F`.
+pub unsafe fn virtual_function<F: Copy>(object: *const u8, offset: usize, index: usize) -> F {
+    const { assert!(core::mem::size_of::<F>() == core::mem::size_of::<usize>(), "virtual functions have to be called through function pointers") };
+    let vtable = unsafe { object.add(offset).cast::<*const F>().read_unaligned() };
+    unsafe { vtable.add(index).read() }
+}
+
+/// A counted UTF-16 string, laid out like `UNICODE_STRING` in Windows: the length and the
+/// capacity of the buffer in bytes, and a pointer to the buffer, which isn't null-terminated.
+///
+/// `Debug` shows the lengths and the buffer address without reading the buffer, since it may
+/// belong to another process. [`UnicodeString::display`] shows the string of a local buffer and
+/// [`UnicodeString::read`] copies the string of a remote one, both bounded by the lengths.
+///
+/// ```
+/// use offsetter::UnicodeString;
+///
+/// let text: Vec<u16> = "Driver\u{0}".encode_utf16().collect();
+/// let name = UnicodeString { length: 12, maximum_length: 14, buffer: text.as_ptr().cast_mut() };
+/// assert_eq!(name.len(), 6);
+/// assert_eq!(unsafe { name.display() }.to_string(), "Driver");
+///
+/// let invalid = [0xd800, u16::from(b'!')];
+/// let name = UnicodeString { length: 4, maximum_length: 4, buffer: invalid.as_ptr().cast_mut() };
+/// assert_eq!(format!("{:?}", unsafe { name.display() }), "\"\u{fffd}!\"");
+/// ```
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UnicodeString {
+    /// The length of the string in bytes.
+    pub length: u16,
+    /// The capacity of the buffer in bytes.
+    pub maximum_length: u16,
+    /// The UTF-16 code units of the string.
+    pub buffer: *mut u16,
+}
+
+unsafe impl FfiSafe for UnicodeString {}
+
+impl UnicodeString {
+    /// The number of code units of the string, with `length` bounded by `maximum_length`.
+    pub const fn len(&self) -> usize {
+        let length = if self.length < self.maximum_length { self.length } else { self.maximum_length };
+        length as usize / 2
+    }
+
+    /// Whether the string is empty or has no buffer.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0 || self.buffer.is_null()
+    }
+
+    /// The code units of the string, empty when the buffer is null.
+    ///
+    /// # Safety
+    ///
+    /// The buffer has to hold [`len`](Self::len) readable code units while the slice is used.
+    pub unsafe fn as_slice(&self) -> &[u16] {
+        if self.is_empty() {
+            return &[];
+        }
+        unsafe { core::slice::from_raw_parts(self.buffer, self.len()) }
+    }
+
+    /// Displays the string, replacing invalid UTF-16 with U+FFFD.
+    ///
+    /// # Safety
+    ///
+    /// See [`as_slice`](Self::as_slice).
+    pub unsafe fn display(&self) -> Utf16Lossy<'_> {
+        Utf16Lossy(unsafe { self.as_slice() })
+    }
+
+    /// Copies the code units of a string whose buffer belongs to the memory `reader` reads into
+    /// `buffer`, truncating it to the length of `buffer`, and returns the copied code units.
+    pub fn read<'b, R: MemoryRead + ?Sized>(&self, reader: &R, buffer: &'b mut [u16]) -> Result<&'b [u16], R::Error> {
+        let units = if self.is_empty() { 0 } else { self.len().min(buffer.len()) };
+        let buffer = &mut buffer[..units];
+        if units > 0 {
+            let bytes = unsafe { core::slice::from_raw_parts_mut(buffer.as_mut_ptr().cast::<u8>(), units * 2) };
+            reader.read(self.buffer as usize as u64, bytes)?;
+        }
+        Ok(buffer)
+    }
+}
+
+impl core::fmt::Debug for UnicodeString {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("UnicodeString")
+            .field("length", &self.length)
+            .field("maximum_length", &self.maximum_length)
+            .field("buffer", &self.buffer)
+            .finish()
+    }
+}
+
+/// Displays UTF-16 code units, replacing invalid UTF-16 with U+FFFD, see [`UnicodeString`].
+/// `Debug` quotes and escapes the string like `str` does.
+#[derive(Clone, Copy)]
+pub struct Utf16Lossy<'a>(pub &'a [u16]);
+
+impl core::fmt::Display for Utf16Lossy<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        use core::fmt::Write;
+
+        for character in char::decode_utf16(self.0.iter().copied()) {
+            f.write_char(character.unwrap_or(char::REPLACEMENT_CHARACTER))?;
+        }
+        Ok(())
+    }
+}
+
+impl core::fmt::Debug for Utf16Lossy<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        use core::fmt::Write;
+
+        f.write_char('"')?;
+        for character in char::decode_utf16(self.0.iter().copied()) {
+            for escaped in character.unwrap_or(char::REPLACEMENT_CHARACTER).escape_debug() {
+                f.write_char(escaped)?;
+            }
+        }
+        f.write_char('"')
+    }
+}
+
+/// Reads and writes the memory of other processes on Windows and Linux, so the views generated by
+/// the `remote` option work against live processes.
+///
+/// `Process::open` opens a process by its id, and implements [`MemoryRead`] and [`MemoryWrite`]
+/// with `ReadProcessMemory` and `WriteProcessMemory` on Windows, and with `process_vm_readv` and
+/// `process_vm_writev` on Linux. Accesses fail with the error of the operating system, e.g. when
+/// the caller lacks the rights to access the process, or with `UnexpectedEof` when only some of
+/// the bytes could be accessed.
+///
+/// ```
+/// use offsetter::{offset, process::Process};
+///
+/// offset!(
+///     #[offset(remote, new)]
+///     pub struct Counter {
+///         0x0 pub hits: u32,
+///         0x8 pub misses: u64
+///     }
+/// );
+///
+/// let counter = Counter::new(3, 4);
+/// let process = Process::open(std::process::id()).unwrap();
+/// let remote = unsafe { CounterRemote::new(&process, &counter as *const Counter as u64) };
+/// assert_eq!(remote.hits().unwrap(), 3);
+/// assert_eq!(remote.read().unwrap().misses(), 4);
+/// ```
+#[cfg(all(feature = "std", any(windows, target_os = "linux")))]
+pub mod process {
+    extern crate std;
+
+    use std::io::{Error, ErrorKind, Result};
+
+    /// A process whose memory is accessed by its id.
+    #[derive(Debug)]
+    pub struct Process {
+        #[cfg(windows)]
+        handle: sys::Handle,
+        #[cfg(target_os = "linux")]
+        id: u32,
+    }
+
+    impl Process {
+        /// Opens the process with the id `id` for reading and writing its memory.
+        #[cfg(windows)]
+        pub fn open(id: u32) -> Result<Self> {
+            let access = sys::PROCESS_VM_READ | sys::PROCESS_VM_WRITE | sys::PROCESS_VM_OPERATION | sys::PROCESS_QUERY_LIMITED_INFORMATION;
+            let handle = unsafe { sys::OpenProcess(access, 0, id) };
+            if handle.is_null() {
+                return Err(Error::last_os_error());
+            }
+            Ok(Self { handle })
+        }
+
+        /// Opens the process with the id `id` for reading and writing its memory.
+        ///
+        /// Linux checks the rights to access the process on every access instead, so this only
+        /// fails if there is no process with the id.
+        #[cfg(target_os = "linux")]
+        pub fn open(id: u32) -> Result<Self> {
+            std::fs::metadata(std::format!("/proc/{}", id))?;
+            Ok(Self { id })
+        }
+    }
+
+    // Process handles can be used from any thread.
+    #[cfg(windows)]
+    unsafe impl Send for Process {}
+
+    #[cfg(windows)]
+    unsafe impl Sync for Process {}
+
+    #[cfg(windows)]
+    impl Drop for Process {
+        fn drop(&mut self) {
+            unsafe { sys::CloseHandle(self.handle) };
+        }
+    }
+
+    // A partial access only reports how many bytes were accessed, which is an error here since
+    // the fields would be left half read or half written.
+    fn complete(done: usize, expected: usize) -> Result<()> {
+        if done != expected {
+            return Err(Error::new(ErrorKind::UnexpectedEof, std::format!("only 0x{:x} of 0x{:x} bytes were accessed", done, expected)));
+        }
+        Ok(())
+    }
+
+    impl crate::MemoryRead for Process {
+        type Error = Error;
+
+        #[cfg(windows)]
+        fn read(&self, address: u64, buffer: &mut [u8]) -> Result<()> {
+            let mut done = 0;
+            if unsafe { sys::ReadProcessMemory(self.handle, address as usize as *const _, buffer.as_mut_ptr().cast(), buffer.len(), &mut done) } == 0 {
+                return Err(Error::last_os_error());
+            }
+            complete(done, buffer.len())
+        }
+
+        #[cfg(target_os = "linux")]
+        fn read(&self, address: u64, buffer: &mut [u8]) -> Result<()> {
+            let local = sys::IoVec { base: buffer.as_mut_ptr().cast(), len: buffer.len() };
+            let remote = sys::IoVec { base: address as usize as *mut _, len: buffer.len() };
+            let done = unsafe { sys::process_vm_readv(self.id as i32, &local, 1, &remote, 1, 0) };
+            if done < 0 {
+                return Err(Error::last_os_error());
+            }
+            complete(done as usize, buffer.len())
+        }
+    }
+
+    impl crate::MemoryWrite for Process {
+        #[cfg(windows)]
+        fn write(&self, address: u64, data: &[u8]) -> Result<()> {
+            let mut done = 0;
+            if unsafe { sys::WriteProcessMemory(self.handle, address as usize as *mut _, data.as_ptr().cast(), data.len(), &mut done) } == 0 {
+                return Err(Error::last_os_error());
+            }
+            complete(done, data.len())
+        }
+
+        #[cfg(target_os = "linux")]
+        fn write(&self, address: u64, data: &[u8]) -> Result<()> {
+            let local = sys::IoVec { base: data.as_ptr() as *mut _, len: data.len() };
+            let remote = sys::IoVec { base: address as usize as *mut _, len: data.len() };
+            let done = unsafe { sys::process_vm_writev(self.id as i32, &local, 1, &remote, 1, 0) };
+            if done < 0 {
+                return Err(Error::last_os_error());
+            }
+            complete(done as usize, data.len())
+        }
+    }
+
+    #[cfg(windows)]
+    mod sys {
+        use core::ffi::c_void;
+
+        pub type Handle = *mut c_void;
+
+        pub const PROCESS_VM_OPERATION: u32 = 0x8;
+        pub const PROCESS_VM_READ: u32 = 0x10;
+        pub const PROCESS_VM_WRITE: u32 = 0x20;
+        pub const PROCESS_QUERY_LIMITED_INFORMATION: u32 = 0x1000;
+
+        #[link(name = "kernel32")]
+        extern "system" {
+            pub fn OpenProcess(access: u32, inherit: i32, id: u32) -> Handle;
+            pub fn CloseHandle(handle: Handle) -> i32;
+            pub fn ReadProcessMemory(process: Handle, address: *const c_void, buffer: *mut c_void, size: usize, read: *mut usize) -> i32;
+            pub fn WriteProcessMemory(process: Handle, address: *mut c_void, buffer: *const c_void, size: usize, written: *mut usize) -> i32;
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    mod sys {
+        use core::ffi::{c_int, c_ulong, c_void};
+
+        #[repr(C)]
+        pub struct IoVec {
+            pub base: *mut c_void,
+            pub len: usize,
+        }
+
+        extern "C" {
+            pub fn process_vm_readv(pid: c_int, local: *const IoVec, local_count: c_ulong, remote: *const IoVec, remote_count: c_ulong, flags: c_ulong) -> isize;
+            pub fn process_vm_writev(pid: c_int, local: *const IoVec, local_count: c_ulong, remote: *const IoVec, remote_count: c_ulong, flags: c_ulong) -> isize;
+        }
+    }
+
+    #[cfg(all(test, target_os = "linux"))]
+    mod tests {
+        use super::*;
+        use crate::{MemoryRead, MemoryWrite};
+        use std::string::ToString;
+
+        #[test]
+        fn read_and_write() {
+            let process = Process::open(std::process::id()).unwrap();
+            let mut local = [1u8, 2, 3, 4, 5, 6, 7, 8];
+            let address = local.as_mut_ptr() as u64;
+
+            let mut buffer = [0; 4];
+            process.read(address + 2, &mut buffer).unwrap();
+            assert_eq!(buffer, [3, 4, 5, 6]);
+
+            process.write(address + 4, &[0xa, 0xb]).unwrap();
+            assert_eq!(core::hint::black_box(local), [1, 2, 3, 4, 0xa, 0xb, 7, 8]);
+
+            assert!(process.read(0, &mut buffer).is_err());
+            assert_eq!(Process::open(u32::MAX).unwrap_err().kind(), ErrorKind::NotFound);
+        }
+
+        #[test]
+        fn short_read() {
+            // Reads the last bytes of a writable mapping followed by unmapped memory, so only
+            // those are read.
+            let maps = std::fs::read_to_string("/proc/self/maps").unwrap();
+            let ranges: std::vec::Vec<(u64, u64, bool)> = maps
+                .lines()
+                .map(|line| {
+                    let mut words = line.split_whitespace();
+                    let (start, end) = words.next().unwrap().split_once('-').unwrap();
+                    let writable = words.next().unwrap().starts_with("rw");
+                    (u64::from_str_radix(start, 16).unwrap(), u64::from_str_radix(end, 16).unwrap(), writable)
+                })
+                .collect();
+            let end = ranges.windows(2).find(|pair| pair[0].2 && pair[0].1 != pair[1].0).map(|pair| pair[0].1).unwrap();
+
+            let process = Process::open(std::process::id()).unwrap();
+            let error = process.read(end - 8, &mut [0; 16]).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+            assert_eq!(error.to_string(), "only 0x8 of 0x10 bytes were accessed");
+            assert!(complete(0x10, 0x10).is_ok());
+        }
+    }
+}
+
+/// Watches files for changes, e.g. the layouts `offset_dynamic!` structs read their offsets from,
+/// so long running tools pick up corrected offsets without restarting, see their `watch_offsets`.
+///
+/// ```
+/// use offsetter::offset_dynamic;
+/// use std::time::{Duration, Instant};
+///
+/// offset_dynamic!(
+///     pub struct Player {
+///         pub health: u32,
+///     }
+/// );
+///
+/// let path = std::env::temp_dir().join(format!("offsetter-watch-{}.json", std::process::id()));
+/// std::fs::write(&path, r#"{ "Player": { "fields": { "health": { "offset": 16 } } } }"#).unwrap();
+///
+/// let watcher = unsafe { Player::watch_offsets(&path, Duration::from_millis(10), |error| panic!("{}", error)) }.unwrap();
+/// assert_eq!(Player::offsets().unwrap().health, 0x10);
+///
+/// std::fs::write(&path, r#"{ "Player": { "fields": { "health": { "offset": 32 } } } }"#).unwrap();
+/// let start = Instant::now();
+/// while Player::offsets().unwrap().health != 0x20 {
+///     assert!(start.elapsed() < Duration::from_secs(10));
+///     std::thread::sleep(Duration::from_millis(10));
+/// }
+///
+/// drop(watcher);
+/// std::fs::remove_file(path).unwrap();
+/// ```
+#[cfg(feature = "std")]
+pub mod watch {
+    extern crate std;
+
+    #[doc(hidden)]
+    pub use std::{io, path::PathBuf};
+
+    use std::string::String;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread::JoinHandle;
+    use std::time::Duration;
+
+    /// Calls a function with the contents of a file whenever they change, until it's dropped.
+    #[derive(Debug)]
+    pub struct Watcher {
+        stop: Arc<AtomicBool>,
+        thread: Option<JoinHandle<()>>,
+    }
+
+    impl Watcher {
+        /// Reads the file at `path` and passes its contents to `reload`, and then does so again
+        /// from a thread whenever they changed, checking every `interval`. Fails if the file
+        /// can't be read at first, later failures are retried at the next check, e.g. while the
+        /// file is being replaced.
+        pub fn spawn<F>(path: impl Into<PathBuf>, interval: Duration, mut reload: F) -> io::Result<Self>
+        where
+            F: FnMut(&str) + Send + 'static,
+        {
+            let path = path.into();
+            let mut contents = std::fs::read_to_string(&path)?;
+            reload(&contents);
+
+            let stop = Arc::new(AtomicBool::new(false));
+            let thread = std::thread::Builder::new().name(String::from("offsetter-watch")).spawn({
+                let stop = stop.clone();
+                move || loop {
+                    std::thread::park_timeout(interval);
+                    if stop.load(Ordering::Acquire) {
+                        break;
+                    }
+
+                    if let Ok(current) = std::fs::read_to_string(&path) {
+                        if current != contents {
+                            reload(&current);
+                            contents = current;
+                        }
+                    }
+                }
+            })?;
+            Ok(Self { stop, thread: Some(thread) })
+        }
+    }
+
+    impl Drop for Watcher {
+        fn drop(&mut self) {
+            self.stop.store(true, Ordering::Release);
+            if let Some(thread) = self.thread.take() {
+                thread.thread().unpark();
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+/// Converts layout files into `offset!` definitions in build scripts, see `include_layout!`, so
+/// reverse engineering tools can emit one canonical file that C and Rust code are generated from.
+///
+/// A layout file declares structs with a `struct` line holding the name and an optional total
+/// size, followed by a line for every field holding its offset, name and type:
+///
+/// ```text
+/// # Offsets of Windows 11 22H2.
+/// struct EPROCESS 0xa40
+/// 0x440 UniqueProcessId uint64_t
+/// 0x550 Peb void*
+/// 0x5a8 ImageFileName uint8_t[15]
+/// ```
+///
+/// Lines starting with `#` or `//` are comments. Types are either C types, where the fixed width
+/// integers, `char`, `float`, `double`, `void*`, pointers and arrays are understood, or Rust types
+/// such as `[u16; 4]`. Field names are converted to snake case, e.g. `unique_process_id`.
+///
+/// ```
+/// let source = offsetter::layout::convert("struct POINT 0x8\n0x0 X int32_t\n0x4 Y int32_t\n").unwrap();
+/// assert!(source.contains("0x4 pub y: i32,"));
+/// ```
+///
+/// C headers with a `.h` extension are read as well, see `convert_header`.
+#[cfg(feature = "std")]
+pub mod layout {
+    extern crate std;
+
+    use std::borrow::ToOwned;
+    use std::format;
+    use std::io::{Error, ErrorKind, Result};
+    use std::path::Path;
+    use std::string::String;
+    use std::vec::Vec;
+
+    use crate::names::constant_name;
+
+    /// Converts the layout file at `path` into `OUT_DIR/<name>.rs`, named after the file without
+    /// its extension, for `include_layout!("<name>")` to include. Files with a `.h` extension are
+    /// read as C headers, and files with an `.idc` extension as IDA scripts. Meant to be called
+    /// from a build script, which is rerun whenever the file changes.
+    pub fn build(path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let name = path.file_stem().ok_or_else(|| Error::new(ErrorKind::InvalidInput, format!("`{}` isn't a file", path.display())))?;
+        let out_dir = std::env::var_os("OUT_DIR").ok_or_else(|| Error::new(ErrorKind::NotFound, "`OUT_DIR` isn't set, `build` has to be called from a build script"))?;
+
+        std::println!("cargo:rerun-if-changed={}", path.display());
+        let contents = std::fs::read_to_string(path)?;
+        let source = if path.extension().is_some_and(|extension| extension == "idc") {
+            convert_idc(&contents)?
+        } else if path.extension().is_some_and(|extension| extension == "h") {
+            // Build scripts run on the host, the pointers of the structs are those of the target.
+            let pointer_size = std::env::var("CARGO_CFG_TARGET_POINTER_WIDTH").ok().and_then(|width| width.parse::<usize>().ok());
+            convert_header(&contents, pointer_size.map_or(core::mem::size_of::<usize>(), |width| width / 8))?
+        } else {
+            convert(&contents)?
+        };
+        std::fs::write(Path::new(&out_dir).join(name).with_extension("rs"), source)
+    }
+
+    /// Converts the contents of a layout file into `offset!` definitions.
+    pub fn convert(layout: &str) -> Result<String> {
+        let mut source = String::from("// Generated by offsetter from a layout file, don't edit by hand.\n");
+        let mut in_struct = false;
+        for (index, line) in layout.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with("//") {
+                continue;
+            }
+
+            let error = |message: String| Error::new(ErrorKind::InvalidData, format!("line {}: {}", index + 1, message));
+            let mut words = line.splitn(3, char::is_whitespace);
+            let (first, second, rest) = (words.next().unwrap_or(""), words.next().unwrap_or("").trim(), words.next().unwrap_or("").trim());
+
+            if first == "struct" {
+                if second.is_empty() {
+                    return Err(error("a struct needs a name".to_owned()));
+                }
+                if in_struct {
+                    source.push_str("    }\n);\n");
+                }
+
+                let size = if rest.is_empty() { String::new() } else { format!(" [{}]", number(rest).ok_or_else(|| error(format!("`{}` isn't a size", rest)))?) };
+                source.push_str(&format!("\n::offsetter::offset!(\n    pub struct {}{} {{\n", second, size));
+                in_struct = true;
+                continue;
+            }
+
+            if rest.is_empty() {
+                return Err(error("a field needs an offset, a name and a type".to_owned()));
+            }
+            if !in_struct {
+                return Err(error(format!("the field `{}` comes before any struct", second)));
+            }
+
+            let offset = number(first).ok_or_else(|| error(format!("`{}` isn't an offset", first)))?;
+            source.push_str(&format!("        {} pub {}: {},\n", offset, constant_name(second).to_ascii_lowercase(), rust_type(rest)));
+        }
+
+        if in_struct {
+            source.push_str("    }\n);\n");
+        }
+        Ok(source)
+    }
+
+    /// Converts the structs declared in a C header into `offset!` definitions, given the size of
+    /// pointers on the target.
+    ///
+    /// Only a subset of C is understood: `struct NAME { ... };` and `typedef struct { ... } NAME;`
+    /// declarations whose members are laid out back to back, as in headers that are packed or
+    /// spell out their padding. Members have the types of layout files, structs declared before
+    /// them or pointers, and may be arrays. Members named `pad...` or `reserved...`, ignoring case
+    /// and leading underscores, are padding and left out. Comments, preprocessor lines and other
+    /// declarations are skipped, while unions, bitfields, nested declarations and several members
+    /// in one declaration are errors.
+    ///
+    /// This covers the C exported by Ghidra, whose types such as `dword` and `pointer` are
+    /// understood, and whose `undefined` members are left out like padding.
+    ///
+    /// ```
+    /// let header = "
+    ///     #pragma pack(push, 1)
+    ///     typedef struct _POINT {
+    ///         int32_t x;
+    ///         char _padding[4];
+    ///         struct _POINT *next;
+    ///         uint16_t name[8];
+    ///     } POINT;
+    /// ";
+    /// let source = offsetter::layout::convert_header(header, 8).unwrap();
+    /// assert!(source.contains("pub struct POINT [0x20]"));
+    /// assert!(source.contains("0x8 pub next: *mut POINT,"));
+    /// assert!(source.contains("0x10 pub name: [u16; 8],"));
+    /// ```
+    pub fn convert_header(header: &str, pointer_size: usize) -> Result<String> {
+        let tokens = c_tokens(header);
+        let token = |index: usize| tokens.get(index).map_or("", |&(_, token)| token);
+        let error = |index: usize, message: String| {
+            let line = tokens.get(index).or(tokens.last()).map_or(1, |&(line, _)| line);
+            Error::new(ErrorKind::InvalidData, format!("line {}: {}", line, message))
+        };
+
+        // Members may refer to structs by their tag before the typedef names them, so the structs
+        // are collected first and written once all their names are known.
+        let mut structs: Vec<CStruct> = Vec::new();
+        let mut index = 0;
+        while index < tokens.len() {
+            let typedef = token(index) == "typedef";
+            let start = index + usize::from(typedef);
+            let tag = Some(token(start + 1)).filter(|&tag| tag != "{");
+            let body = start + 1 + usize::from(tag.is_some());
+            if token(start) == "union" && token(body) == "{" {
+                return Err(error(start, "unions aren't supported".to_owned()));
+            }
+
+            // Anything but a struct declaration is skipped up to its end.
+            if token(start) != "struct" || token(body) != "{" {
+                let mut depth = 0usize;
+                while index < tokens.len() && !(token(index) == ";" && depth == 0) {
+                    match token(index) {
+                        "{" => depth += 1,
+                        "}" => depth = depth.saturating_sub(1),
+                        _ => {}
+                    }
+                    index += 1;
+                }
+                index += 1;
+                continue;
+            }
+
+            let mut declared = CStruct { name: String::new(), tag, size: 0, fields: Vec::new() };
+            index = body + 1;
+            while token(index) != "}" {
+                let end = (index..tokens.len()).find(|&end| token(end) == ";").ok_or_else(|| error(index, "a member doesn't end in `;`".to_owned()))?;
+                let mut member: Vec<&str> = (index..end).map(token).filter(|&token| token != "const" && token != "volatile").collect();
+                if let Some(&unsupported) = member.iter().find(|&&token| matches!(token, "{" | "}" | ":" | ",")) {
+                    let message = match unsupported {
+                        ":" => "bitfields aren't supported",
+                        "," => "every member has to be declared on its own",
+                        _ => "nested declarations aren't supported",
+                    };
+                    return Err(error(index, message.to_owned()));
+                }
+
+                let mut length = None;
+                if member.len() >= 5 && member[member.len() - 1] == "]" && member[member.len() - 3] == "[" {
+                    let count = member[member.len() - 2];
+                    length = Some(c_number(count).ok_or_else(|| error(index, format!("`{}` isn't an array length", count)))?);
+                    member.truncate(member.len() - 3);
+                }
+                let name = member.pop().filter(|name| name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_'));
+                let name = name.ok_or_else(|| error(index, "a member needs a type and a name".to_owned()))?;
+                let pointers = member.iter().rev().take_while(|&&token| token == "*").count();
+                let ty = member[..member.len() - pointers].join(" ");
+                if ty.is_empty() {
+                    return Err(error(index, format!("the member `{}` needs a type", name)));
+                }
+
+                let size = if pointers > 0 {
+                    Some(pointer_size)
+                } else {
+                    let ty = ty.strip_prefix("struct ").unwrap_or(&ty);
+                    rust_size(&rust_type(ty), pointer_size)
+                        .or_else(|| structs.iter().find(|declared| declared.name == ty || declared.tag == Some(ty)).map(|declared| declared.size))
+                };
+                let size = size.ok_or_else(|| error(index, format!("the size of the type `{}` of `{}` isn't known", ty, name)))?;
+
+                let bare = name.trim_start_matches('_').to_ascii_lowercase();
+                let undefined = pointers == 0 && ty.starts_with("undefined");
+                if !bare.starts_with("pad") && !bare.starts_with("reserved") && !undefined {
+                    let array = length.map_or(String::new(), |length| format!("[{}]", length));
+                    declared.fields.push((declared.size, name, ty, format!("{}{}", "*".repeat(pointers), array)));
+                }
+                declared.size += size * length.unwrap_or(1);
+                index = end + 1;
+            }
+
+            let name = Some(token(index + 1)).filter(|&name| typedef && name != ";").or(tag);
+            declared.name = name.ok_or_else(|| error(index, "a struct needs a name".to_owned()))?.to_owned();
+            structs.push(declared);
+            while index < tokens.len() && token(index) != ";" {
+                index += 1;
+            }
+            index += 1;
+        }
+
+        let mut layout = String::new();
+        for declared in &structs {
+            layout.push_str(&format!("struct {} {:#x}\n", declared.name, declared.size));
+            for (offset, name, ty, suffix) in &declared.fields {
+                let ty = match ty.strip_prefix("struct ") {
+                    Some(tag) => structs.iter().find(|declared| declared.tag == Some(tag)).map_or(tag, |declared| &declared.name),
+                    None => ty,
+                };
+                layout.push_str(&format!("{:#x} {} {}{}\n", offset, name, ty, suffix));
+            }
+        }
+        convert(&layout)
+    }
+
+    /// Converts the structs of an IDC script exported by IDA into `offset!` definitions.
+    ///
+    /// Structs are created with `add_struc` and their members added with `add_struc_member`, or
+    /// the `AddStrucEx` and `AddStrucMember` of older versions, while the rest of the script is
+    /// skipped. The type of a member follows from its flags and size: bytes, words, dwords,
+    /// qwords, floats and doubles become the matching primitives, offsets become pointers, members
+    /// of a struct type embed that struct, and anything else is an array of bytes. Every struct
+    /// ends with its last member.
+    ///
+    /// ```
+    /// let script = r#"
+    ///     static Structures_0(id) {
+    ///         auto mid;
+    ///         id = add_struc(-1, "_EPROCESS", 0);
+    ///         mid = add_struc_member(id, "UniqueProcessId", 0X440, 0x30000400, -1, 8);
+    ///         mid = add_struc_member(id, "Peb", 0X550, 0x35500400, 0, 8);
+    ///         mid = add_struc_member(id, "ImageFileName", 0X5A8, 0x000400, -1, 15);
+    ///     }
+    /// "#;
+    /// let source = offsetter::layout::convert_idc(script).unwrap();
+    /// assert!(source.contains("pub struct _EPROCESS [0x5b7]"));
+    /// assert!(source.contains("0x550 pub peb: *mut ::core::ffi::c_void,"));
+    /// assert!(source.contains("0x5a8 pub image_file_name: [u8; 15],"));
+    /// ```
+    pub fn convert_idc(script: &str) -> Result<String> {
+        // The structs in the order they're created, with the layout file lines of their members.
+        let mut structs: Vec<(&str, usize, String)> = Vec::new();
+        let mut current = None;
+        for (line, statement) in idc_statements(script) {
+            let error = |message: String| Error::new(ErrorKind::InvalidData, format!("line {}: {}", line, message));
+            let Some((function, arguments)) = idc_call(statement) else {
+                continue;
+            };
+
+            match function {
+                "add_struc" | "AddStrucEx" => {
+                    let name = arguments.get(1).and_then(|name| idc_string(name)).ok_or_else(|| error(format!("`{}` needs a name", function)))?;
+                    if arguments.get(2).is_some_and(|union| union.trim() != "0") {
+                        return Err(error(format!("`{}` is a union, and unions aren't supported", name)));
+                    }
+                    if !structs.iter().any(|&(declared, _, _)| declared == name) {
+                        structs.push((name, 0, String::new()));
+                    }
+                    current = structs.iter().position(|&(declared, _, _)| declared == name);
+                }
+                "get_struc_id" | "GetStrucIdByName" => {
+                    current = arguments.first().and_then(|name| idc_string(name)).and_then(|name| structs.iter().position(|&(declared, _, _)| declared == name));
+                }
+                "add_struc_member" | "AddStrucMember" => {
+                    let (Some(current), [_, name, offset, flags, type_id, length, ..]) = (current, &arguments[..]) else {
+                        return Err(error(format!("`{}` needs a struct and six arguments", function)));
+                    };
+                    let name = idc_string(name).ok_or_else(|| error(format!("`{}` isn't a member name", name)))?;
+                    let number = |text: &str| c_number(text.trim()).ok_or_else(|| error(format!("`{}` isn't a number", text.trim())));
+                    let (offset, flags, length) = (number(offset)?, number(flags)?, number(length)?);
+
+                    // The type is in the top nibble of the flags, and offsets are marked in the
+                    // representation of the operand.
+                    let element = match flags & 0xf000_0000 {
+                        _ if flags & 0x00f0_0000 == 0x0050_0000 && (length == 4 || length == 8) => Some(("*mut ::core::ffi::c_void".to_owned(), length)),
+                        0x0000_0000 => Some(("u8".to_owned(), 1)),
+                        0x1000_0000 => Some(("u16".to_owned(), 2)),
+                        0x2000_0000 => Some(("u32".to_owned(), 4)),
+                        0x3000_0000 => Some(("u64".to_owned(), 8)),
+                        0x7000_0000 => Some(("u128".to_owned(), 16)),
+                        0x8000_0000 => Some(("f32".to_owned(), 4)),
+                        0x9000_0000 => Some(("f64".to_owned(), 8)),
+                        0x6000_0000 => {
+                            let (_, arguments) = idc_call(type_id).ok_or_else(|| error(format!("the struct type of `{}` isn't known", name)))?;
+                            let ty = arguments.first().and_then(|ty| idc_string(ty)).ok_or_else(|| error(format!("the struct type of `{}` isn't known", name)))?;
+                            let size = structs.iter().find(|&&(declared, _, _)| declared == ty).map_or(length, |&(_, size, _)| size);
+                            Some((ty.to_owned(), size.max(1)))
+                        }
+                        _ => None,
+                    };
+
+                    let ty = match element {
+                        Some((ty, element_size)) if length == element_size => ty,
+                        Some((ty, element_size)) if length % element_size == 0 => format!("[{}; {}]", ty, length / element_size),
+                        _ => format!("[u8; {}]", length),
+                    };
+                    let (_, size, fields) = &mut structs[current];
+                    fields.push_str(&format!("{:#x} {} {}\n", offset, name, ty));
+                    *size = (*size).max(offset + length);
+                }
+                _ => {}
+            }
+        }
+
+        let mut layout = String::new();
+        for (name, size, fields) in &structs {
+            layout.push_str(&format!("struct {} {:#x}\n{}", name, size, fields));
+        }
+        convert(&layout)
+    }
+
+    // Splits an IDC script into statements with the line they start on, keeping semicolons in
+    // strings and leaving out comments.
+    fn idc_statements(script: &str) -> Vec<(usize, &str)> {
+        let mut statements = Vec::new();
+        let (mut start, mut line, mut start_line) = (0, 1, 1);
+        let (mut in_string, mut escaped) = (false, false);
+        let bytes = script.as_bytes();
+        let mut index = 0;
+        while index < bytes.len() {
+            match bytes[index] {
+                b'\n' => line += 1,
+                _ if in_string => match (escaped, bytes[index]) {
+                    (true, _) => escaped = false,
+                    (false, b'\\') => escaped = true,
+                    (false, b'"') => in_string = false,
+                    _ => {}
+                },
+                b'"' => in_string = true,
+                b'/' if bytes.get(index + 1) == Some(&b'/') => {
+                    let comment = index;
+                    while index < bytes.len() && bytes[index] != b'\n' {
+                        index += 1;
+                    }
+                    if start == comment {
+                        start = index;
+                    }
+                    continue;
+                }
+                b';' | b'{' | b'}' => {
+                    statements.push((start_line, script[start..index].trim()));
+                    (start, start_line) = (index + 1, line);
+                }
+                _ => {}
+            }
+            if start == index && bytes[index].is_ascii_whitespace() {
+                (start, start_line) = (index + 1, line);
+            }
+            index += 1;
+        }
+        statements
+    }
+
+    // The function and the top level arguments of a call, after any assignment of its result.
+    fn idc_call(statement: &str) -> Option<(&str, Vec<&str>)> {
+        let statement = statement.trim();
+        let statement = match statement.split_once('=') {
+            Some((target, call)) if target.trim().chars().all(|c| c.is_ascii_alphanumeric() || c == '_') => call.trim(),
+            _ => statement,
+        };
+        let (function, rest) = statement.split_once('(')?;
+        let rest = rest.trim_end().strip_suffix(')')?;
+
+        let mut arguments = Vec::new();
+        let (mut depth, mut in_string, mut start) = (0usize, false, 0);
+        for (index, c) in rest.char_indices() {
+            match c {
+                '"' => in_string = !in_string,
+                '(' if !in_string => depth += 1,
+                ')' if !in_string => depth = depth.saturating_sub(1),
+                ',' if !in_string && depth == 0 => {
+                    arguments.push(rest[start..index].trim());
+                    start = index + 1;
+                }
+                _ => {}
+            }
+        }
+        arguments.push(rest[start..].trim());
+        Some((function.trim(), arguments))
+    }
+
+    // The contents of a string literal, which IDA only writes names into without escapes.
+    fn idc_string(text: &str) -> Option<&str> {
+        text.trim().strip_prefix('"')?.strip_suffix('"')
+    }
+
+    // A struct of a C header, with the offset, name, type and pointer or array suffix of every
+    // member that isn't padding.
+    struct CStruct<'a> {
+        name: String,
+        tag: Option<&'a str>,
+        size: usize,
+        fields: Vec<(usize, &'a str, String, String)>,
+    }
+
+    // Splits a header into identifiers, numbers and punctuation with their line numbers, without
+    // comments and preprocessor lines.
+    fn c_tokens(header: &str) -> Vec<(usize, &str)> {
+        let mut tokens = Vec::new();
+        let mut in_comment = false;
+        for (index, mut line) in header.lines().enumerate() {
+            if !in_comment && line.trim_start().starts_with('#') {
+                continue;
+            }
+            while !line.is_empty() {
+                if in_comment {
+                    match line.find("*/") {
+                        Some(end) => (line, in_comment) = (&line[end + 2..], false),
+                        None => line = "",
+                    }
+                    continue;
+                }
+
+                line = line.trim_start();
+                if line.starts_with("//") {
+                    break;
+                }
+                if let Some(rest) = line.strip_prefix("/*") {
+                    (line, in_comment) = (rest, true);
+                    continue;
+                }
+
+                let length = line.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(line.len());
+                let length = if length == 0 { line.chars().next().map_or(0, char::len_utf8) } else { length };
+                if length > 0 {
+                    tokens.push((index + 1, &line[..length]));
+                }
+                line = &line[length..];
+            }
+        }
+        tokens
+    }
+
+    // Array lengths in decimal or hex, with an optional unsigned suffix.
+    fn c_number(text: &str) -> Option<usize> {
+        let text = text.trim_end_matches(['u', 'U']);
+        match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+            Some(digits) => usize::from_str_radix(digits, 16).ok(),
+            None => text.parse().ok(),
+        }
+    }
+
+    // The sizes of the primitive types `rust_type` translates C types into.
+    fn rust_size(ty: &str, pointer_size: usize) -> Option<usize> {
+        match ty {
+            "u8" | "i8" | "bool" => Some(1),
+            "u16" | "i16" => Some(2),
+            "u32" | "i32" | "f32" => Some(4),
+            "u64" | "i64" | "f64" => Some(8),
+            "usize" | "isize" => Some(pointer_size),
+            _ if ty.starts_with('*') => Some(pointer_size),
+            _ => None,
+        }
+    }
+
+    // Offsets and sizes are copied as they're written, after making sure they're numbers.
+    fn number(text: &str) -> Option<&str> {
+        let valid = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+            Some(digits) => !digits.is_empty() && digits.chars().all(|digit| digit.is_ascii_hexdigit() || digit == '_'),
+            None => text.chars().all(|digit| digit.is_ascii_digit() || digit == '_') && text.starts_with(|digit: char| digit.is_ascii_digit()),
+        };
+        valid.then_some(text)
+    }
+
+    // Anything that isn't a known C type is taken to be a Rust type already.
+    fn rust_type(text: &str) -> String {
+        if let Some((element, length)) = text.strip_suffix(']').and_then(|text| text.rsplit_once('[')).filter(|(element, _)| !element.is_empty()) {
+            return format!("[{}; {}]", rust_type(element.trim()), length.trim());
+        }
+        if let Some(pointee) = text.strip_suffix('*') {
+            return match pointee.trim() {
+                "void" => "*mut ::core::ffi::c_void".to_owned(),
+                pointee => format!("*mut {}", rust_type(pointee)),
+            };
+        }
+
+        match text {
+            "uint8_t" | "unsigned char" | "UCHAR" | "BYTE" | "byte" | "uchar" | "undefined" | "undefined1" => "u8",
+            "uint16_t" | "unsigned short" | "USHORT" | "WORD" | "word" | "ushort" | "undefined2" => "u16",
+            "uint32_t" | "unsigned int" | "ULONG" | "DWORD" | "dword" | "uint" | "undefined4" => "u32",
+            "uint64_t" | "unsigned long long" | "ULONGLONG" | "DWORD64" | "qword" | "ulonglong" | "undefined8" => "u64",
+            "int8_t" | "char" | "CHAR" => "i8",
+            "int16_t" | "short" | "SHORT" => "i16",
+            "int32_t" | "int" | "LONG" => "i32",
+            "int64_t" | "long long" | "LONGLONG" | "longlong" => "i64",
+            "pointer" => "*mut ::core::ffi::c_void",
+            "uintptr_t" | "size_t" | "ULONG_PTR" => "usize",
+            "intptr_t" | "ptrdiff_t" | "LONG_PTR" => "isize",
+            "float" => "f32",
+            "double" => "f64",
+            "bool" | "_Bool" => "bool",
+            other => other,
+        }
+        .to_owned()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::string::ToString;
+
+        const HEADER: &str = "
+            #pragma pack(push, 1)
+            /* A node of a list. */
+            struct _NODE {
+                struct _NODE *next; // The next node.
+                const uint32_t flags;
+                uint8_t reserved[4];
+            };
+
+            typedef struct {
+                struct _NODE head;
+                unsigned int count;
+                float weights[0x2];
+                void *context;
+            } LIST;
+        ";
+
+        const GHIDRA: &str = "
+            typedef struct Player Player;
+            struct Player {
+                pointer vtable;
+                dword health;
+                undefined field2_0xc;
+                undefined field3_0xd;
+                word ammo;
+                qword score;
+                undefined4 field6_0x18;
+                double speed;
+            };
+        ";
+
+        const IDC: &str = r#"
+            static Structures_0(id) {
+                auto mid;
+                // Vectors are embedded in entities.
+                id = add_struc(-1, "Vector", 0);
+                id = add_struc(-1, "Entity", 0);
+                id = get_struc_id("Vector");
+                mid = add_struc_member(id, "x", 0, 0x80000400, -1, 4);
+                mid = add_struc_member(id, "y", 0X4, 0x80000400, -1, 4);
+                id = get_struc_id("Entity");
+                mid = add_struc_member(id, "vtable", 0, 0x35500400, 0, 8);
+                mid = add_struc_member(id, "position", 0X8, 0x60000400, get_struc_id("Vector"), 8);
+                mid = add_struc_member(id, "ids", 0X10, 0x20000400, -1, 12);
+                mid = add_struc_member(id, "name", 0X1C, 0x000400, -1, 5);
+                mid = add_struc_member(id, "flags", 0X21, 0x40000400, -1, 3);
+            }
+        "#;
+
+        // The message of the error a conversion failed with.
+        fn rejection(result: Result<String>) -> String {
+            let error = result.unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidData);
+            error.to_string()
+        }
+
+        #[test]
+        fn layout_file() {
+            let layout = "# Points.\nstruct POINT 0x8\n0x0 X int32_t\n0x4 Y int32_t\n\n// Rectangles.\nstruct Rect\n0x0 TopLeft POINT\n0x8 Bottom_right POINT\n0x10 Tag [u16; 4]\n";
+            let expected = "// Generated by offsetter from a layout file, don't edit by hand.
+
+::offsetter::offset!(
+    pub struct POINT [0x8] {
+        0x0 pub x: i32,
+        0x4 pub y: i32,
+    }
+);
+
+::offsetter::offset!(
+    pub struct Rect {
+        0x0 pub top_left: POINT,
+        0x8 pub bottom_right: POINT,
+        0x10 pub tag: [u16; 4],
+    }
+);
+";
+            assert_eq!(convert(layout).unwrap(), expected);
+        }
+
+        #[test]
+        fn header() {
+            let expected = "// Generated by offsetter from a layout file, don't edit by hand.
+
+::offsetter::offset!(
+    pub struct _NODE [0x10] {
+        0x0 pub next: *mut _NODE,
+        0x8 pub flags: u32,
+    }
+);
+
+::offsetter::offset!(
+    pub struct LIST [0x24] {
+        0x0 pub head: _NODE,
+        0x10 pub count: u32,
+        0x14 pub weights: [f32; 2],
+        0x1c pub context: *mut ::core::ffi::c_void,
+    }
+);
+";
+            assert_eq!(convert_header(HEADER, 8).unwrap(), expected);
+
+            let narrow = convert_header(HEADER, 4).unwrap();
+            assert!(narrow.contains("pub struct _NODE [0xc] {\n        0x0 pub next: *mut _NODE,\n        0x4 pub flags: u32,\n"));
+            assert!(narrow.contains("pub struct LIST [0x1c] {"));
+            assert!(narrow.contains("0x18 pub context: *mut ::core::ffi::c_void,"));
+        }
+
+        #[test]
+        fn ghidra() {
+            let expected = "// Generated by offsetter from a layout file, don't edit by hand.
+
+::offsetter::offset!(
+    pub struct Player [0x24] {
+        0x0 pub vtable: *mut ::core::ffi::c_void,
+        0x8 pub health: u32,
+        0xe pub ammo: u16,
+        0x10 pub score: u64,
+        0x1c pub speed: f64,
+    }
+);
+";
+            assert_eq!(convert_header(GHIDRA, 8).unwrap(), expected);
+        }
+
+        #[test]
+        fn idc() {
+            let expected = "// Generated by offsetter from a layout file, don't edit by hand.
+
+::offsetter::offset!(
+    pub struct Vector [0x8] {
+        0x0 pub x: f32,
+        0x4 pub y: f32,
+    }
+);
+
+::offsetter::offset!(
+    pub struct Entity [0x24] {
+        0x0 pub vtable: *mut ::core::ffi::c_void,
+        0x8 pub position: Vector,
+        0x10 pub ids: [u32; 3],
+        0x1c pub name: [u8; 5],
+        0x21 pub flags: [u8; 3],
+    }
+);
+";
+            assert_eq!(convert_idc(IDC).unwrap(), expected);
+        }
+
+        #[test]
+        fn layout_file_rejections() {
+            assert_eq!(rejection(convert("struct\n")), "line 1: a struct needs a name");
+            assert_eq!(rejection(convert("struct A 8 bytes\n")), "line 1: `8 bytes` isn't a size");
+            assert_eq!(rejection(convert("0x0 x u32\n")), "line 1: the field `x` comes before any struct");
+            assert_eq!(rejection(convert("struct A\n\n0x0 x\n")), "line 3: a field needs an offset, a name and a type");
+            assert_eq!(rejection(convert("struct A\n0xg x u32\n")), "line 2: `0xg` isn't an offset");
+        }
+
+        #[test]
+        fn header_rejections() {
+            let header = |body: &str| convert_header(&format!("struct A {{\n{}\n}};", body), 8);
+            assert_eq!(rejection(convert_header("\nunion A { int32_t x; float y; };", 8)), "line 2: unions aren't supported");
+            assert_eq!(rejection(header("uint32_t flag : 1;")), "line 2: bitfields aren't supported");
+            assert_eq!(rejection(header("int32_t x, y;")), "line 2: every member has to be declared on its own");
+            assert_eq!(rejection(header("struct { int32_t x; } inner;")), "line 2: nested declarations aren't supported");
+            assert_eq!(rejection(header("struct B other;")), "line 2: the size of the type `struct B` of `other` isn't known");
+            assert_eq!(rejection(header("uint8_t name[N];")), "line 2: `N` isn't an array length");
+            assert_eq!(rejection(header("uint32_t *;")), "line 2: a member needs a type and a name");
+            assert_eq!(rejection(header("x;")), "line 2: the member `x` needs a type");
+            assert_eq!(rejection(convert_header("struct A {\nuint32_t x", 8)), "line 2: a member doesn't end in `;`");
+            assert_eq!(rejection(convert_header("typedef struct { int32_t x; };", 8)), "line 1: a struct needs a name");
+        }
+
+        #[test]
+        fn idc_rejections() {
+            assert_eq!(rejection(convert_idc("id = add_struc(-1, \"U\", 1);")), "line 1: `U` is a union, and unions aren't supported");
+            assert_eq!(rejection(convert_idc("id = add_struc(-1, name, 0);")), "line 1: `add_struc` needs a name");
+            assert_eq!(rejection(convert_idc("\nadd_struc_member(id, \"x\", 0, 0x20000400, -1, 4);")), "line 2: `add_struc_member` needs a struct and six arguments");
+            assert_eq!(rejection(convert_idc("add_struc(-1, \"A\", 0);\nadd_struc_member(id, \"x\", 0, 0x20000400, -1);")), "line 2: `add_struc_member` needs a struct and six arguments");
+            assert_eq!(rejection(convert_idc("add_struc(-1, \"A\", 0);\nadd_struc_member(id, \"x\", OFFSET, 0x20000400, -1, 4);")), "line 2: `OFFSET` isn't a number");
+            assert_eq!(rejection(convert_idc("add_struc(-1, \"A\", 0);\nadd_struc_member(id, \"v\", 0, 0x60000400, -1, 8);")), "line 2: the struct type of `v` isn't known");
+        }
+    }
+}
+
+#[macro_export]
+/// Creates a struct with fields placed at specific memory offsets.
+///
+/// This macro allows you to define a struct with precise memory layout by specifying
+/// the byte offset of each field. The macro automatically inserts padding between fields
+/// to ensure proper alignment.
+///
+/// # Optional Total Size
+///
+/// You can optionally specify the total size of the struct by adding a size value in
+/// square brackets after the struct name. This will ensure the struct has exactly that
+/// size by adding padding at the end if necessary. A field reaching past the total size is a
+/// compile error naming it and telling how many bytes the size falls short.
+///
+/// ```compile_fail,E0080
+/// # use offsetter::offset;
+/// offset!(
+///     struct Truncated [0x10] {
+///         0x0 header: u64,
+///         0xc length: u64,
+///     }
+/// );
+/// ```
+///
+/// That includes strided arrays, which end with their last element:
+///
+/// ```compile_fail,E0080
+/// # use offsetter::offset;
+/// offset!(
+///     struct Table [0x20] {
+///         0x0 count: u32,
+///         0x8 entries: [u32; 4] stride 0x8,
+///     }
+/// );
+/// ```
+///
+/// The message names the field reaching the furthest, which may be an overlapping one, and how
+/// many bytes the total size falls short of its end:
+///
+/// ```compile_fail,E0080
+/// # use offsetter::offset;
+/// offset!(
+///     struct Short [0x6] {
+///         0x0 low: u32,
+///         0x4 high: u16,
+///         overlap 0x0 whole: u64,
+///     }
+/// );
+/// ```
+///
+/// # Offset Expressions
+///
+/// Offsets aren't limited to literals, any constant expression evaluating to a `usize` can be
+/// used, e.g. `0x10 + 0x8` or `core::mem::size_of::<Header>()`. Named constants, including ones
+/// from other modules such as `offsets::EPROCESS_PEB`, work the same way.
+///
+/// An offset starting with `+`, e.g. `+0x8`, is relative to the end of the previous field, so
+/// fields following an absolute anchor move along with it when its offset changes.
+///
+/// Fields have to be declared in ascending order of their offsets, a field declared after one
+/// with a higher offset is a compile error naming both. Overlapping fields and bitfields are
+/// exempt, since they alias other fields anyway. Likewise, a name declared for two fields whose
+/// cfg predicates both hold is a compile error naming it.
+///
+/// ```compile_fail,E0080
+/// # use offsetter::offset;
+/// offset!(
+///     struct Duplicate {
+///         0x0 length: u32,
+///         0x4 length: u32,
+///     }
+/// );
+/// ```
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     struct Exclusive {
+///         0x0 length: u32,
+///         #[cfg(target_pointer_width = "64")]
+///         0x8 tail: u64,
+///         #[cfg(not(target_pointer_width = "64"))]
+///         0x8 tail: u32,
+///     }
+/// );
+/// ```
+///
+/// A field that doesn't fit before the next one, e.g. because its type grew, is a compile error as
+/// well, which the `checked` feature reports by naming both fields and the size of the first:
+///
+/// ```compile_fail,E0080
+/// # use offsetter::offset;
+/// offset!(
+///     struct Grown {
+///         0x0 inner: u64,
+///         0x4 tail: u32,
+///     }
+/// );
+/// ```
+///
+/// # Size Constant
+///
+/// Every struct gets a `SIZE` associated constant with its size in bytes, which is the total size
+/// if one was given, or the end of the last field otherwise.
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     pub struct Header {
+///         0x0 pub magic: u32,
+///         0x8 pub length: u16
+///     }
+/// );
+///
+/// offset!(
+///     pub struct Page[0x1000] {
+///         0x0 pub header: u64
+///     }
+/// );
+///
+/// assert_eq!(Header::SIZE, 0xa);
+/// assert_eq!(Page::SIZE, 0x1000);
+/// assert_eq!(Page::SIZE, core::mem::size_of::<Page>());
+/// ```
+///
+/// # Reading From Bytes
+///
+/// `#[offset(bytes)]` generates `try_from_bytes_unchecked`, which copies a struct out of a byte
+/// slice, e.g. a buffer read from a memory dump, after checking that the slice is at least `SIZE`
+/// bytes long. It's unsafe since the bytes have to be valid for the types of the fields, which is
+/// the case for plain data like integers. Together with `#[offset(plain)]`, which checks exactly
+/// that, it also generates a safe `try_from_bytes`.
+///
+/// # Constructor
+///
+/// `#[offset(new)]` generates `new`, which takes the value of every field stored in the struct in
+/// declaration order, e.g. `Example::new(field1, field2)`, and zeroes everything else. That
+/// includes the padding, the storage of bitfields, overlapping fields and strided arrays, which can
+/// be set through their setters afterwards, and reserved regions, which have to be valid when
+/// zeroed.
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     #[offset(new, bytes)]
+///     pub struct Entry[0x10] {
+///         0x0 pub kind: u8,
+///         0x4:0..4 pub flags: u8,
+///         0x8 pub length: u32
+///     }
+/// );
+///
+/// let entry = Entry::new(1, 0x20);
+/// assert_eq!((entry.kind(), entry.flags(), entry.length()), (1, 0, 0x20));
+/// assert_eq!(entry.as_bytes(), &[1, 0, 0, 0, 0, 0, 0, 0, 0x20, 0, 0, 0, 0, 0, 0, 0]);
+/// ```
+///
+/// Without the option, a field can be named `new` itself:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     pub struct Counters {
+///         0x0 pub new: u32,
+///         0x4 pub old: u32
+///     }
+/// );
+///
+/// let counters: Counters = unsafe { core::mem::transmute([3u8, 0, 0, 0, 4, 0, 0, 0]) };
+/// assert_eq!((counters.new(), counters.old()), (3, 4));
+/// ```
+///
+/// # Default Values
+///
+/// A field can be given a default value after its type, e.g. `0x4 pub flags: u32 = 0x1`, which
+/// implements `Default` for the struct. Fields without a default value are zeroed, like everything
+/// else the constructor zeroes, so they have to be valid when zeroed. `#[offset(default)]`
+/// implements `Default` without any default values.
+///
+/// # Pointer Views
+///
+/// `#[offset(from_ptr)]` generates the unsafe `from_ptr` and `from_mut_ptr`, which turn an address,
+/// e.g. one found by a scanner, into a reference to the struct. Since the structs are packed, the
+/// address doesn't need to be aligned.
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     #[offset(from_ptr)]
+///     pub struct Entry {
+///         0x0 pub kind: u8,
+///         0x1 pub length: u32
+///     }
+/// );
+///
+/// let mut buffer = [0u8; 0x10];
+/// buffer[0x3] = 2;
+///
+/// let entry = unsafe { Entry::from_mut_ptr(buffer[0x3..].as_mut_ptr()) };
+/// entry.set_length(0x40);
+/// assert_eq!(entry.kind(), 2);
+/// assert_eq!(unsafe { Entry::from_ptr(buffer[0x3..].as_ptr()) }.length(), 0x40);
+/// assert_eq!(buffer[0x4..0x8], [0x40, 0, 0, 0]);
+/// ```
+///
+/// # Byte Views
+///
+/// `#[offset(bytes)]` also generates `as_bytes`, which returns the bytes of a struct as an array of
+/// `SIZE` bytes, e.g. to write it back to process memory or to a file, and the unsafe
+/// `as_bytes_mut`, which allows changing them in place. They aren't generated for structs with type
+/// parameters, whose size isn't known up front.
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     #[offset(new, bytes)]
+///     pub struct Entry {
+///         0x0 pub kind: u8,
+///         0x4 pub length: u32
+///     }
+/// );
+///
+/// let mut entry = Entry::new(1, 0x20);
+/// assert_eq!(entry.as_bytes(), &[1, 0, 0, 0, 0x20, 0, 0, 0]);
+///
+/// unsafe { entry.as_bytes_mut()[4] = 0x30 };
+/// assert_eq!(entry.length(), 0x30);
+/// ```
+///
+/// # Offset Constants
+///
+/// Every field gets an associated constant holding its offset, named after the field in upper
+/// case, e.g. `Example::OFFSET_FIELD1`, with the visibility of the field. This lets code doing
+/// pointer arithmetic refer to offsets by name instead of repeating them.
+///
+/// # Getters and Setters
+///
+/// Every field gets a getter of the same name, e.g. `example.field1()`, returning a copy of the
+/// value read with `read_unaligned`, and a `set_` setter, e.g. `example.set_field1(value)`,
+/// storing the value with `write_unaligned`. Since the structs are packed, references to their
+/// fields may be unaligned, and the accessors avoid taking them at the call site. Like any
+/// unaligned write, the setter doesn't drop the value it replaces.
+///
+/// Reading fields at unaligned offsets:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     pub struct Entry {
+///         0x0 pub kind: u8,
+///         0x1 pub address: u64,
+///         0x9 pub length: u32
+///     }
+/// );
+///
+/// let bytes: [u8; 0xd] = [7, 0x88, 0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11, 0x10, 0, 0, 0];
+/// let entry: Entry = unsafe { core::mem::transmute(bytes) };
+/// assert_eq!(entry.kind(), 7);
+/// assert_eq!(entry.address(), 0x1122334455667788);
+/// assert_eq!(entry.length(), 0x10);
+/// ```
+///
+/// Writing them through their setters, which leave the bytes around them alone:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     pub struct Entry {
+///         0x0 pub kind: u8,
+///         0x1 pub address: u64,
+///         0x9 pub length: u32
+///     }
+/// );
+///
+/// let mut entry: Entry = unsafe { core::mem::transmute([0xffu8; 0xd]) };
+/// entry.set_address(0x1122334455667788);
+/// entry.set_length(0x10);
+/// assert_eq!((entry.kind(), entry.address(), entry.length()), (0xff, 0x1122334455667788, 0x10));
+///
+/// let bytes: [u8; 0xd] = unsafe { core::mem::transmute(entry) };
+/// assert_eq!(bytes, [0xff, 0x88, 0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11, 0x10, 0, 0, 0]);
+/// ```
+///
+/// Getters are only callable for fields whose type is `Copy`, since a copy of anything else would
+/// own its resources a second time:
+///
+/// ```rust,compile_fail,E0277
+/// # use offsetter::offset;
+/// offset!(
+///     pub struct Owner {
+///         0x0 pub boxed: Box<u32>,
+///         0x8 pub count: u32
+///     }
+/// );
+///
+/// fn take(owner: &Owner) -> Box<u32> {
+///     owner.boxed()
+/// }
+/// ```
+///
+/// # Field Metadata
+///
+/// A `FIELDS` associated constant lists the name, offset and size of every field as
+/// [`FieldInfo`] values, in declaration order and without reserved regions. Tools such as
+/// dumpers and validators can walk it without knowing the struct.
+///
+/// A `LAYOUT` constant describes the struct as a [`Layout`] value, with its name, its size and
+/// a [`FieldDescriptor`] for every field that also holds the type as it was declared, so generic
+/// code can take the layout of any struct as an argument.
+///
+/// # Validation
+///
+/// Fields can declare invariants with `#[validate(...)]`, e.g. `#[validate(magic == 0x5A4D)]` or
+/// `#[validate(!next.is_null())]`, where the name of the field stands for its value. The generated
+/// `validate` method checks them in declaration order and returns a [`LayoutError`] naming the
+/// first one that doesn't hold, e.g. before trusting a struct read from an untrusted dump. Structs
+/// without invariants don't get the method, and neither do tuple structs, whose elements can't
+/// have them.
+///
+/// # Layout Report
+///
+/// `#[offset(describe)]` generates `describe`, which writes a table of the fields, reserved regions
+/// and padding runs with their offsets and sizes to any `core::fmt::Write`, e.g. to log the layout
+/// a driver was built with when debugging version mismatches.
+///
+/// With the `json` feature, the option also generates `layout_json`, which writes the size and the
+/// fields in the JSON format of `verify_layout!` instead, so external tools can check the layouts
+/// baked into a binary, e.g. against the symbols of the target it runs on.
+///
+/// `#[offset(c_declaration)]` generates `c_declaration`, which writes an equivalent C struct,
+/// packed and with `char` arrays for the padding, so a component written in C shares the layout
+/// instead of transcribing it. Fields are declared with the C counterparts of primitive types,
+/// pointers and arrays of them, and as byte arrays otherwise. Bitfields share a storage member
+/// named after their offset, and overlapping fields are listed in comments.
+///
+/// # Offset Alternatives
+///
+/// An offset can be a list of alternatives selected by cfg predicates, e.g.
+/// `{ feature = "win10": 0x550, feature = "win11": 0x558 }`. The first alternative whose
+/// predicate holds is used, and it's a compile error if none of them does. This lets the
+/// features of the crate using the struct choose its layout.
+///
+/// # Offsets From the Environment
+///
+/// `env("EPROCESS_PEB")` takes an offset from an environment variable at build time, e.g. one set
+/// by CI for every target, in decimal or in hex with a `0x` prefix. Without the variable the build
+/// fails, unless a fallback is given, e.g. `env("EPROCESS_PEB", 0x550)`. A value that isn't an
+/// offset is a compile error naming the variable.
+///
+/// # Declared Field Sizes
+///
+/// The expected size of a field's type can be written in brackets after the type, e.g.
+/// `0x10 pub name: UNICODE_STRING [0x10]`. The `checked` feature then verifies the size of the
+/// type, catching nested types whose size silently changed and shifted every following field.
+///
+/// # Conditional Fields
+///
+/// Fields can be prefixed with `#[cfg(...)]` attributes to only exist in some configurations.
+/// The padding in front of the following fields is recomputed for every configuration, so one
+/// definition can serve multiple targets.
+///
+/// # Field Attributes
+///
+/// Doc comments and any other attributes in front of a field, e.g. `#[deprecated]`, are kept on
+/// the generated struct field, and on the accessors of fields that are generated as methods.
+///
+/// # Generic Parameters
+///
+/// Structs can take lifetime parameters followed by type parameters, each type parameter with
+/// an optional single bound, e.g. `pub struct View<'a, T: Copy>`. The padding in front of a
+/// field can't depend on a type parameter, so a field whose size depends on one has to be the
+/// last field, and the struct can't be given a total size. The `checked` feature doesn't verify
+/// generic structs.
+///
+/// # Auto Sized Fields
+///
+/// A field declared with the type `auto`, e.g. `0x40 pub blob: auto`, is a byte array spanning
+/// from its offset to the offset of the next field, or to the total size of the struct if it's
+/// the last field. This is handy for regions that haven't been reversed yet, since the gap
+/// doesn't have to be recomputed when the offsets around it change.
+///
+/// # Overlapping Fields
+///
+/// Prefixing a field with `overlap` allows it to alias the fields around it, e.g. to view the
+/// halves of a 64-bit value at offsets `0x0` and `0x4`. Such fields don't get a struct field,
+/// they are read and written through a getter and a `set_` setter instead, and the `checked`
+/// feature doesn't validate their placement.
+///
+/// # Atomic Fields
+///
+/// Prefixing a field with `atomic`, e.g. `atomic 0x8 pub flags: u32`, stores it as its
+/// [`Atomic`] counterpart, e.g. `AtomicU32`, for lock-free flags in memory shared between threads
+/// or processes. Instead of a getter and a setter it gets `load_flags(ordering)` and
+/// `store_flags(value, ordering)`, which work through shared references. Atomic operations have
+/// to be aligned, so they panic when the field isn't aligned in memory.
+///
+/// # Reserved Regions
+///
+/// Writing `reserved` in place of the visibility, e.g. `0x20 reserved _unknown: [u8; 0x10]`,
+/// documents a region that is known to be in use but isn't exposed. The field is always private
+/// and is left out of accessors and Debug output, and the `checked` feature verifies that it
+/// doesn't overlap the fields around it.
+///
+/// # Flexible Array Members
+///
+/// The last field can be declared as `[T; ...]` for structures that end in a variable length
+/// array, like the `items[1]` idiom in C. The array takes up no space in the struct, and is
+/// reached through unsafe accessors taking the number of elements, which return a raw slice and
+/// a mutable raw slice named with a `_mut` suffix.
+///
+/// # Bitfields
+///
+/// A field declared as `offset:low..high` occupies bits `low..high` of the unsigned integer
+/// stored at `offset`. No struct field is generated for it, instead a getter and a `set_` setter
+/// shift and mask the storage in place, so several bitfields can share the same storage.
+/// A bit range that doesn't fit in the storage type is a compile error.
+///
+/// # Flag Fields
+///
+/// A field declared as `flags ProcessFlags: u32 { CREATE = 1, EXITING = 4 }` has a flags type
+/// generated next to the struct, with the visibility of the field, a constant per flag, and the
+/// usual `contains`, `insert`, `remove` and bit operators. Its getter and setter take the flags
+/// type instead of the integer. Bits that aren't declared are kept, and shown in hex by Debug.
+///
+/// # Strided Arrays
+///
+/// An array field followed by `stride`, e.g. `[Slot; 16] stride 0x28`, places its elements
+/// `stride` bytes apart instead of `size_of::<Slot>()`. Like bitfields such arrays don't get a
+/// struct field, they are read and written through generated accessors taking an index.
+///
+/// # Embedded Structs
+///
+/// Structs defined by this macro can be used as field types like any other type. Prefixing such
+/// a field with `embed` additionally makes the `checked` feature verify that the embedded struct
+/// ends exactly at the offset of the next field, catching nested layouts that grew or shrank.
+///
+/// # Base Structs
+///
+/// `pub struct Derived extends Base` places a struct defined by this macro at offset 0, the way
+/// C++ classes contain their base class. The base is stored in a private field named `base`, and
+/// is reached through `as_base()`, `as_base_mut()` and `Deref`, so the fields of the base can be
+/// used as if they were declared on the derived struct. The fields of the derived struct start
+/// after the base.
+///
+/// # Tuple Structs
+///
+/// Fields can be listed in parentheses without names, e.g. `pub struct Raw(0x0 pub u32, 0x8
+/// u64);`, for quick throwaway views of memory. The fields are named `_0`, `_1` and so on by
+/// their position, and accessed as `raw._0` or through the getter `raw._0()`.
+/// Everything a named field supports works the same way, up to 32 fields.
+///
+/// # Struct Options
+///
+/// An `#[offset(...)]` attribute on the struct enables additional behavior instead of being
+/// placed on the struct. `#[offset(debug)]` generates the Debug implementation of
+/// `offset_debug!`, `#[offset(private)]` makes the fields private like `offset_accessors!`, and
+/// `#[offset(dual)]` and `#[offset(wow64)]` generate two layouts as described below.
+///
+/// The constructor and the helper methods described above are only generated with their options,
+/// i.e. `new`, `bytes`, `from_ptr`, `describe` and `c_declaration`, so they can't clash with the
+/// getter of a field of the same name or with methods the crate using the macro defines itself.
+///
+/// `#[offset(debug_padding)]` implements Debug like `#[offset(debug)]`, and additionally lists
+/// the bytes that aren't covered by any field, padding and reserved regions alike, as hex under
+/// `padding`, since the unknown bytes are often the interesting part when reversing.
+///
+/// `#[offset(debug_truncate)]` implements Debug like `#[offset(debug)]`, but shows fields larger
+/// than 16 bytes, e.g. a `[u8; 0x200]` buffer, as their first 16 bytes followed by their length.
+///
+/// `#[offset(display)]` implements `Display` as a single line of `name=value` pairs, e.g.
+/// `field1=1 field2=2`, for logging where the multi-line Debug output is awkward. The values are
+/// formatted with their Debug implementation.
+///
+/// `#[offset(copy)]` implements `Copy` and a `Clone` that copies the whole struct, since the
+/// packed fields can't be borrowed to clone them one by one.
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     #[offset(new, copy)]
+///     pub struct Point {
+///         0x0 pub x: u8,
+///         0x1 pub y: u32
+///     }
+/// );
+///
+/// let mut point = Point::new(1, 2);
+/// let copy = point;
+/// let clone = point.clone();
+/// point.set_y(3);
+/// assert_eq!((copy.x(), copy.y()), (1, 2));
+/// assert_eq!((clone.x(), clone.y()), (1, 2));
+/// assert_eq!(point.y(), 3);
+/// ```
+///
+/// A struct with a field that isn't `Copy` can't be:
+///
+/// ```rust,compile_fail,E0204
+/// # use offsetter::offset;
+/// offset!(
+///     #[offset(copy)]
+///     pub struct Owner {
+///         0x0 pub boxed: Box<u32>,
+///         0x8 pub count: u32
+///     }
+/// );
+/// ```
+///
+/// `#[offset(partial_eq)]` implements `PartialEq` by comparing the fields, leaving out padding
+/// and reserved regions, which may differ between structs copied from live memory. Adding
+/// `#[offset(eq)]` also implements `Eq`, and `#[offset(hash)]` implements a matching `Hash`.
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     #[offset(partial_eq, eq)]
+///     pub struct Entry {
+///         0x0 pub kind: u8,
+///         0x4 pub length: u32
+///     }
+/// );
+///
+/// let first: Entry = unsafe { core::mem::transmute([1u8, 0, 0, 0, 0x20, 0, 0, 0]) };
+/// let padded: Entry = unsafe { core::mem::transmute([1u8, 0xff, 0xff, 0xff, 0x20, 0, 0, 0]) };
+/// let longer: Entry = unsafe { core::mem::transmute([1u8, 0, 0, 0, 0x30, 0, 0, 0]) };
+/// assert!(first == padded);
+/// assert!(first != longer);
+/// ```
+///
+/// The hash of a struct likewise only depends on its fields:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// # use std::collections::HashSet;
+/// offset!(
+///     #[offset(partial_eq, eq, hash)]
+///     pub struct Entry {
+///         0x0 pub kind: u8,
+///         0x4 pub length: u32
+///     }
+/// );
+///
+/// let mut entries = HashSet::new();
+/// entries.insert(unsafe { core::mem::transmute::<_, Entry>([1u8, 0, 0, 0, 0x20, 0, 0, 0]) });
+/// entries.insert(unsafe { core::mem::transmute::<_, Entry>([1u8, 0xff, 0xff, 0xff, 0x20, 0, 0, 0]) });
+/// entries.insert(unsafe { core::mem::transmute::<_, Entry>([2u8, 0, 0, 0, 0x20, 0, 0, 0]) });
+/// assert_eq!(entries.len(), 2);
+/// ```
+///
+/// `#[offset(zeroed)]` generates a `const fn new_zeroed()` returning the struct with every byte
+/// set to zero, e.g. for statics. Since that's only valid if every field can be zero, a struct
+/// with fields such as references or function pointers then doesn't compile.
+///
+/// `#[offset(builder)]` generates a builder that starts out zeroed the same way, e.g.
+/// `Example::builder().field1(1).build()` returns an `Example` with only `field1` set. The
+/// builder is named after the struct, e.g. `ExampleBuilder`, and has a method for every field
+/// that has a setter.
+///
+/// `#[offset(remote)]` generates a view of the struct in memory that isn't part of the process,
+/// e.g. `ExampleRemote<R>`, which holds an address and a reader implementing [`MemoryRead`]. Its
+/// getters read one field each through the reader and return its errors, and with
+/// [`MemoryWrite`] its setters write them back, so the same definitions serve external processes,
+/// hypervisors and emulators. `read` and `write` copy the whole struct. `cached` wraps the reader
+/// in a [`Cached`], which reads every byte at most once until its `refresh` and keeps writes until
+/// its `flush`, e.g. for structures polled in a loop. Reserved regions and flexible arrays aren't
+/// accessible, and structs with generic parameters aren't supported.
+///
+/// `#[offset(volatile)]` makes every getter and setter use `read_volatile` and `write_volatile`,
+/// e.g. for memory mapped registers or memory shared with another process, where accesses must
+/// neither be elided nor merged. Volatile accesses have to be aligned, which the fields of packed
+/// structs aren't guaranteed to be, so the accessors panic when called on an unaligned field.
+///
+/// `#[offset(aligned)]` makes it a compile error if a field's offset isn't a multiple of the
+/// alignment of its type, e.g. for targets where unaligned accesses fault, or for fields accessed
+/// with `volatile` or `atomic`. It relies on the struct itself being placed at an address aligned
+/// to its largest field. Reserved regions are exempt, and structs with type parameters aren't
+/// supported.
+///
+/// `#[offset(plain)]` makes it a compile error if a field's type needs to be dropped, or if not
+/// every bit pattern is a valid value of it, like `bool`, enums, references or function pointers.
+/// Such a field makes reading the struct from arbitrary memory undefined behavior, e.g. through
+/// `from_ptr` or `try_from_bytes_unchecked`, so with `#[offset(bytes)]` plain structs also get a
+/// safe `try_from_bytes`. Structs with type parameters aren't supported.
+///
+/// `#[offset(strict)]` makes it a compile error if any byte of the struct isn't covered by a
+/// field or a reserved region, e.g. once a structure is believed to be fully reversed. The error
+/// names the first uncovered range. Structs with type parameters aren't supported.
+///
+/// `#[offset(ffi)]` makes it a compile error if a field's type doesn't implement [`FfiSafe`],
+/// e.g. a `&str`, a trait object or a `#[repr(Rust)]` struct, so a layout meant to cross an
+/// `extern "C"` boundary can't pick one up by accident. The struct then implements `FfiSafe`
+/// itself, so it can be nested in other such structs. Structs with type parameters aren't
+/// supported.
+///
+/// `#[offset(target32)]` and `#[offset(target64)]` make it a compile error to build the struct
+/// for a target whose pointers aren't 32 or 64 bits wide respectively, since the offsets of a
+/// struct taken from e.g. an x64 binary silently end up wrong once pointer and `usize` fields
+/// shrink. Dual layouts are meant for both widths, so they don't need either.
+///
+/// With the `serde` feature, `#[offset(serialize)]` implements `Serialize` as a struct of the
+/// fields, leaving out padding and reserved regions, e.g. to snapshot reversed structures to
+/// JSON. `#[offset(deserialize)]` implements `Deserialize` the other way around, zeroing
+/// everything but the fields.
+///
+/// ```
+/// # #[cfg(feature = "serde")] {
+/// use offsetter::offset;
+///
+/// offset!(
+///     #[offset(serialize, deserialize, new)]
+///     pub struct Player {
+///         0x10 pub health: u32,
+///         0x20 pub position: [f32; 2]
+///     }
+/// );
+///
+/// let json = serde_json::to_string(&Player::new(100, [1.5, -2.0])).unwrap();
+/// assert_eq!(json, r#"{"health":100,"position":[1.5,-2.0]}"#);
+///
+/// let player: Player = serde_json::from_str(&json).unwrap();
+/// assert_eq!(player.health(), 100);
+/// assert!(serde_json::from_str::<Player>(r#"{"health":100}"#).is_err());
+/// # }
+/// ```
+///
+/// With the `arbitrary` feature, `#[offset(arbitrary)]` implements `Arbitrary` by filling every
+/// field with fuzz data and leaving the padding and reserved regions zeroed, e.g. for fuzzing
+/// parsers that consume the struct.
+///
+/// ```
+/// # #[cfg(feature = "arbitrary")] {
+/// use arbitrary::{Arbitrary, Unstructured};
+/// use offsetter::offset;
+///
+/// offset!(
+///     #[offset(arbitrary)]
+///     pub struct Packet[0x10] {
+///         0x2 pub kind: u8,
+///         0x8 pub length: u32
+///     }
+/// );
+///
+/// let data = [3, 0x10, 0x20, 0x30, 0x40];
+/// let packet = Packet::arbitrary(&mut Unstructured::new(&data)).unwrap();
+/// assert_eq!(packet.kind(), 3);
+/// assert_eq!(packet.length(), u32::arbitrary(&mut Unstructured::new(&data[1..])).unwrap());
+/// # }
+/// ```
+///
+/// With the `kani` feature, `#[offset(kani)]` generates proof harnesses for the Kani model checker,
+/// which prove that every field stored in the struct is at its declared offset, that none of them
+/// overlap, and that copying the struct out of any bytes gives back the same bytes. They're only
+/// compiled under `cargo kani`, and are named after the struct, e.g. `example_layout_proof` and
+/// `example_bytes_proof`. The round trip reads arbitrary bytes as the struct, so it's combined with
+/// `#[offset(plain)]`. Structs with lifetimes or type parameters aren't supported. The crate using
+/// the macro has to declare `cfg(kani)` in the `check-cfg` of its `unexpected_cfgs` lint to avoid
+/// warnings. There's no dependency on `kani`, since `cargo kani` provides it to every crate it
+/// builds.
+///
+/// ```
+/// # #[cfg(feature = "kani")] {
+/// use offsetter::offset;
+///
+/// offset!(
+///     #[offset(kani, plain)]
+///     pub struct Example[0x10] {
+///         0x0 pub id: u32,
+///         0x8 pub value: u64
+///     }
+/// );
+///
+/// // Outside of `cargo kani` the harnesses aren't compiled, and the struct is used as usual.
+/// assert_eq!(Example::SIZE, 0x10);
+/// # }
+/// ```
+///
+/// With the `bytemuck` feature, `#[offset(pod)]` implements bytemuck's `Zeroable` and `Pod`,
+/// e.g. for `bytemuck::cast_slice` over memory dumps holding arrays of the struct. It's a compile
+/// error if a field type isn't `Pod`, and since `Pod` requires `Copy`, it's combined with
+/// `#[offset(copy)]`.
+///
+/// ```
+/// # #[cfg(feature = "bytemuck")] {
+/// use offsetter::offset;
+///
+/// offset!(
+///     #[offset(pod, copy)]
+///     pub struct Vertex[0x10] {
+///         0x0 pub x: f32,
+///         0x8 pub color: u32
+///     }
+/// );
+///
+/// let mut dump = [0u32; 8];
+/// dump[6] = 0xff00ff;
+/// let vertices: &[Vertex] = bytemuck::cast_slice(&dump);
+/// assert_eq!(vertices.len(), 2);
+/// assert_eq!(vertices[1].color(), 0xff00ff);
+/// assert_eq!(bytemuck::bytes_of(&vertices[0]), &[0; 0x10]);
+/// # }
+/// ```
+///
+/// With the `rkyv` feature, `#[offset(rkyv)]` implements rkyv's `Archive` and `Serialize` by
+/// archiving the bytes of the struct as a `[u8; SIZE]`, e.g. to store captured structures and map
+/// them back later. An archived struct is read back with `from_ptr` or `try_from_bytes_unchecked`
+/// of the `from_ptr` and `bytes` options, which are unsafe since the bytes may not hold valid
+/// fields, or with the safe `try_from_bytes` of plain structs. Structs with generic parameters
+/// aren't supported, and the implementations are for rkyv 0.8.
+///
+/// ```
+/// # #[cfg(feature = "rkyv")] {
+/// use offsetter::offset;
+/// use rkyv::rancor::Error;
+///
+/// offset!(
+///     #[offset(rkyv, bytes, plain, new)]
+///     pub struct Sample[0x10] {
+///         0x4 pub id: u32,
+///         0x8 pub value: u64
+///     }
+/// );
+///
+/// let archive = rkyv::to_bytes::<Error>(&Sample::new(7, 42)).unwrap();
+/// let archived = rkyv::access::<[u8; Sample::SIZE], Error>(&archive).unwrap();
+/// let sample = Sample::try_from_bytes(archived).unwrap();
+/// assert_eq!((sample.id(), sample.value()), (7, 42));
+/// # }
+/// ```
+///
+/// With the `zerocopy` feature, `#[offset(zerocopy)]` derives zerocopy's `FromBytes`,
+/// `IntoBytes`, `Unaligned`, `KnownLayout` and `Immutable` for the struct, so it can be cast from
+/// and to byte buffers throughout the zerocopy ecosystem. The padding is made of plain bytes, so
+/// the derives only fail if a field type doesn't qualify. The derives refer to zerocopy through
+/// offsetter by its name, so the dependency on offsetter can't be renamed.
+///
+/// ```
+/// # #[cfg(feature = "zerocopy")] {
+/// use offsetter::offset;
+/// use zerocopy::{FromBytes, IntoBytes};
+///
+/// offset!(
+///     #[offset(zerocopy)]
+///     pub struct Header[0x8] {
+///         0x0 pub magic: [u8; 4],
+///         0x6 pub count: u8
+///     }
+/// );
+///
+/// let bytes = *b"MZ\0\0\xff\xff\x03\0";
+/// let header = Header::ref_from_bytes(&bytes).unwrap();
+/// assert_eq!(header.count, 3);
+/// assert_eq!(header.as_bytes(), &bytes);
+/// assert!(Header::ref_from_bytes(&bytes[..4]).is_err());
+/// # }
+/// ```
+///
+/// With the `defmt` feature, `#[offset(defmt)]` implements `defmt::Format` over the fields, see
+/// [`offset_debug!`]. With the `ufmt` feature, `#[offset(ufmt)]` does the same for
+/// `ufmt::uDebug`.
+///
+/// # Dual Layouts
+///
+/// With `#[offset(dual)]`, a `Foo32` and a `Foo64` struct are generated from the definition of
+/// `Foo`, e.g. for the 32-bit structures of WOW64 processes. Offsets and the total size can be
+/// given as `32-bit/64-bit` pairs such as `0x4/0x8`, where each side is a single token, so
+/// expressions have to be parenthesized. Within the fields `Ptr` is an integer as large as a
+/// pointer of the layout. Both layouts are generated in hidden modules and reexported, so fields
+/// that should be usable next to the definition have to be public.
+///
+/// `#[offset(wow64)]` generates the same layouts, and additionally implements `From<&Foo32>` for
+/// `Foo64` and `TryFrom<&Foo64>` for `Foo32`, which copy the fields between the layouts, e.g. to
+/// inspect the structures of a WOW64 process from 64-bit code with the same types. The pointers
+/// in `Ptr` fields and arrays of them are zero-extended, or fail the conversion if they don't fit
+/// into 32 bits. Any other field whose size differs between the layouts is a compile error, and
+/// so are generic parameters.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     pub struct Example {
+///         0x0 pub field1: u32,
+///         0x4 pub field2: u16,
+///         0x8 pub field3: u64
+///     }
+/// );
+/// ```
+///
+/// With explicit total size:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     pub struct ExampleWithSize[0x20] {
+///         0x0 pub field1: u32,
+///         0x4 pub field2: u16,
+///         0x8 pub field3: u64
+///         // The struct will be padded to exactly 0x20 bytes
+///     }
+/// );
+/// ```
+///
+/// For platform compatibility (e.g., Windows driver structures):
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     pub struct DEVICE_OBJECT[0x150] {
+///         0x0 pub type_: u16,
+///         0x2 pub size: u16,
+///         0x8 pub next: *mut DEVICE_OBJECT,
+///         // More fields...
+///     }
+/// );
+/// ```
+///
+/// Referring to the offset of a field:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     pub struct Example {
+///         0x0 pub field1: u32,
+///         0x8 pub field2: u64
+///     }
+/// );
+///
+/// assert_eq!(Example::OFFSET_FIELD2, 0x8);
+/// assert_eq!(Example::SIZE, 0x10);
+/// ```
+///
+/// Walking the fields of a struct:
+///
+/// ```rust
+/// # use offsetter::{offset, FieldInfo};
+/// offset!(
+///     #[offset(new)]
+///     pub struct Example {
+///         0x0 pub field1: u32,
+///         0x8 pub field2: u64
+///     }
+/// );
+///
+/// let mut example = Example::new(1, 2);
+/// example.set_field2(3);
+/// assert_eq!(example.field2(), 3);
+/// assert_eq!(Example::FIELDS[1], FieldInfo { name: "field2", offset: 0x8, size: 8 });
+/// ```
+///
+/// Handling any struct through its layout:
+///
+/// ```rust
+/// # use offsetter::{offset, Layout};
+/// offset!(
+///     pub struct Node[0x18] {
+///         0x0 pub next: *mut Node,
+///         0x10 pub value: [u16; 4]
+///     }
+/// );
+///
+/// fn field_types(layout: &Layout) -> Vec<String> {
+///     layout.fields.iter().map(|field| format!("{}: {}", field.name, field.type_name)).collect()
+/// }
+///
+/// assert_eq!(field_types(&Node::LAYOUT), ["next: *mut Node", "value: [u16; 4]"]);
+/// assert_eq!(Node::LAYOUT.size, 0x18);
+/// assert_eq!(Node::LAYOUT.field_at(0x12).map(|field| field.name), Some("value"));
+/// ```
+///
+/// Reading a struct from a buffer:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     #[offset(bytes, plain, from_ptr)]
+///     pub struct Header {
+///         0x0 pub magic: u32,
+///         0x4 pub length: u16
+///     }
+/// );
+///
+/// let buffer = [0x4D, 0x5A, 0x90, 0x00, 0x10, 0x00, 0xFF];
+/// let header = Header::try_from_bytes(&buffer).unwrap();
+/// assert_eq!(header.magic(), 0x905A4D);
+/// assert_eq!(header.length(), 0x10);
+/// assert!(Header::try_from_bytes(&buffer[..4]).is_none());
+/// assert_eq!(header.as_bytes(), &buffer[..6]);
+///
+/// let shifted = unsafe { Header::try_from_bytes_unchecked(&buffer[1..]) }.unwrap();
+/// assert_eq!(shifted.magic(), 0x1000905A);
+///
+/// let view = unsafe { Header::from_ptr(buffer[1..].as_ptr()) };
+/// assert_eq!(view.length(), 0xFF00);
+/// ```
+///
+/// Default values:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     pub struct Packet {
+///         0x0 pub version: u16 = 2,
+///         0x4 pub flags: u32 = 0x1,
+///         0x8:0..4 pub kind: u8 = 3,
+///         0x10 pub length: u64
+///     }
+/// );
+///
+/// let packet = Packet::default();
+/// assert_eq!((packet.version(), packet.flags(), packet.kind(), packet.length()), (2, 0x1, 3, 0));
+/// ```
+///
+/// A zeroed static:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     #[offset(zeroed)]
+///     pub struct Counters {
+///         0x0 pub hits: u32,
+///         0x8 pub misses: u64
+///     }
+/// );
+///
+/// static COUNTERS: Counters = Counters::new_zeroed();
+/// assert_eq!(COUNTERS.misses(), 0);
+/// ```
+///
+/// Memory mapped registers:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     #[offset(volatile, aligned, from_ptr)]
+///     pub struct Timer {
+///         0x0 pub control: u32,
+///         0x4:0..1 pub enabled: u32,
+///         0x8 pub counter: u32
+///     }
+/// );
+///
+/// let mut memory = [0u32; 3];
+/// let timer = unsafe { Timer::from_mut_ptr(memory.as_mut_ptr().cast()) };
+/// timer.set_enabled(1);
+/// timer.set_counter(100);
+/// assert_eq!((timer.enabled(), timer.counter()), (1, 100));
+/// ```
+///
+/// A register that isn't aligned doesn't compile with `aligned`:
+///
+/// ```rust,compile_fail,E0080
+/// # use offsetter::offset;
+/// offset!(
+///     #[offset(aligned)]
+///     pub struct Timer {
+///         0x0 pub control: u8,
+///         0x2 pub counter: u32
+///     }
+/// );
+/// ```
+///
+/// Neither does a `bool` register with `plain`, since not every byte is a valid `bool`:
+///
+/// ```rust,compile_fail,E0080
+/// # use offsetter::offset;
+/// offset!(
+///     #[offset(plain)]
+///     pub struct Timer {
+///         0x0 pub enabled: bool,
+///         0x4 pub counter: u32
+///     }
+/// );
+/// ```
+///
+/// Nor does a struct with a hole with `strict`:
+///
+/// ```rust,compile_fail,E0080
+/// # use offsetter::offset;
+/// offset!(
+///     #[offset(strict)]
+///     pub struct Timer {
+///         0x0 pub control: u32,
+///         0x8 pub counter: u32
+///     }
+/// );
+/// ```
+///
+/// Declaring a structure passed to C code:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     #[offset(ffi)]
+///     pub struct Callback {
+///         0x0 pub context: *mut core::ffi::c_void,
+///         0x8 pub function: Option<extern "C" fn(*mut core::ffi::c_void) -> i32>
+///     }
+/// );
+///
+/// offset!(
+///     #[offset(ffi)]
+///     pub struct Registration {
+///         0x0 pub id: u32,
+///         0x8 pub callback: Callback
+///     }
+/// );
+/// ```
+///
+/// A wide pointer can't be part of it:
+///
+/// ```rust,compile_fail,E0277
+/// # use offsetter::offset;
+/// offset!(
+///     #[offset(ffi)]
+///     pub struct Message<'a> {
+///         0x0 pub text: &'a str
+///     }
+/// );
+/// ```
+///
+/// Sharing a flag between threads:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// use core::sync::atomic::Ordering;
+///
+/// offset!(
+///     #[offset(from_ptr)]
+///     pub struct Mailbox {
+///         0x0 pub length: u32,
+///         atomic 0x4 pub ready: u32
+///     }
+/// );
+///
+/// let mut memory = [0u32; 2];
+/// let mailbox = unsafe { Mailbox::from_ptr(memory.as_mut_ptr().cast()) };
+/// mailbox.store_ready(1, Ordering::Release);
+/// assert_eq!(mailbox.load_ready(Ordering::Acquire), 1);
+/// ```
+///
+/// Validating a struct read from a dump:
+///
+/// ```rust
+/// # use offsetter::{offset, LayoutError};
+/// offset!(
+///     #[offset(bytes, plain)]
+///     pub struct Header {
+///         #[validate(magic == 0x5A4D)]
+///         0x0 pub magic: u16,
+///         #[validate(matches!(kind, 1..=3))]
+///         0x4 pub kind: u8,
+///         0x8 pub length: u32
+///     }
+/// );
+///
+/// let header = Header::try_from_bytes(&[0x4D, 0x5A, 0, 0, 7, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+/// assert_eq!(
+///     header.validate(),
+///     Err(LayoutError { struct_name: "Header", field: "kind", invariant: "matches!(kind, 1..=3)" })
+/// );
+/// ```
+///
+/// Logging the layout:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     #[offset(describe)]
+///     pub struct Header[0x10] {
+///         0x0 pub magic: u16,
+///         0x4 reserved _unknown: u32,
+///         0x8 pub length: u32
+///     }
+/// );
+///
+/// let mut table = String::new();
+/// Header::describe(&mut table).unwrap();
+/// assert_eq!(table, "\
+/// Header (0x10 bytes)
+/// offset   size     field
+/// 0x0      0x2      magic
+/// 0x2      0x2      (padding)
+/// 0x4      0x4      _unknown (reserved)
+/// 0x8      0x4      length
+/// 0xc      0x4      (padding)
+/// ");
+/// ```
+///
+/// Exporting the layout as JSON, with the `describe` option and the `json` feature:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     #[offset(describe)]
+///     pub struct Header[0x10] {
+///         0x0 pub magic: u16,
+///         0x4 reserved _unknown: u32,
+///         0x8 pub length: u32
+///     }
+/// );
+///
+/// # #[cfg(feature = "json")] {
+/// let mut json = String::new();
+/// Header::layout_json(&mut json).unwrap();
+/// assert_eq!(json, r#"{
+///     "Header": {
+///         "size": 16,
+///         "fields": {
+///             "magic": { "offset": 0, "size": 2 },
+///             "length": { "offset": 8, "size": 4 }
+///         }
+///     }
+/// }
+/// "#);
+/// # }
+/// ```
+///
+/// Sharing the layout with C:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     #[offset(c_declaration)]
+///     pub struct Header[0x18] {
+///         0x0 pub magic: u16,
+///         0x4 reserved _unknown: u32,
+///         0x8 pub next: *mut Header,
+///         0x10:0..4 pub kind: u32,
+///         0x10:4..8 pub state: u32
+///     }
+/// );
+///
+/// let mut header = String::new();
+/// Header::c_declaration(&mut header).unwrap();
+/// assert_eq!(header, "\
+/// #pragma pack(push, 1)
+/// struct Header {
+///     uint16_t magic; /* 0x0 */
+///     char _padding_0x2[0x2];
+///     uint32_t _unknown; /* 0x4, reserved */
+///     void *next; /* 0x8 */
+///     uint32_t _bits_0x10; /* 0x10 */
+///     /* kind: bits of _bits_0x10 */
+///     /* state: bits of _bits_0x10 */
+///     char _padding_0x14[0x4];
+/// };
+/// #pragma pack(pop)
+/// ");
+/// ```
+///
+/// Building a struct:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     #[offset(builder, copy, partial_eq, eq, hash, bytes)]
+///     pub struct Request {
+///         0x0 pub code: u32,
+///         0x8 pub length: u64
+///     }
+/// );
+///
+/// let request = Request::builder().code(0x22).length(0x100).build();
+/// let copy = request;
+/// assert_eq!(request.code(), 0x22);
+/// assert_eq!(request.as_bytes()[4..8], [0; 4]);
+/// assert!(copy == request);
+/// ```
+///
+/// Reading a struct from another address space:
+///
+/// ```rust
+/// # use offsetter::{offset, MemoryRead, MemoryWrite};
+/// # use std::cell::RefCell;
+/// struct Emulator {
+///     ram: RefCell<Vec<u8>>,
+/// }
+///
+/// impl MemoryRead for Emulator {
+///     type Error = u64;
+///
+///     fn read(&self, address: u64, buffer: &mut [u8]) -> Result<(), u64> {
+///         let ram = self.ram.borrow();
+///         let bytes = ram.get(address as usize..address as usize + buffer.len()).ok_or(address)?;
+///         buffer.copy_from_slice(bytes);
+///         Ok(())
+///     }
+/// }
+///
+/// impl MemoryWrite for Emulator {
+///     fn write(&self, address: u64, data: &[u8]) -> Result<(), u64> {
+///         let mut ram = self.ram.borrow_mut();
+///         let bytes = ram.get_mut(address as usize..address as usize + data.len()).ok_or(address)?;
+///         bytes.copy_from_slice(data);
+///         Ok(())
+///     }
+/// }
+///
+/// offset!(
+///     #[offset(remote)]
+///     pub struct Actor {
+///         0x0 pub health: u32,
+///         0x4:0..1 pub alive: u8,
+///         0x8 pub position: [f32; 3]
+///     }
+/// );
+///
+/// let emulator = Emulator { ram: RefCell::new(vec![0; 0x100]) };
+/// let actor = unsafe { ActorRemote::new(&emulator, 0x40) };
+/// actor.set_health(100).unwrap();
+/// actor.set_alive(1).unwrap();
+/// assert_eq!(actor.health(), Ok(100));
+/// assert_eq!(actor.read().unwrap().position(), [0.0; 3]);
+/// assert_eq!(emulator.ram.borrow()[0x44], 1);
+///
+/// let outside = unsafe { ActorRemote::new(&emulator, 0xf8) };
+/// assert_eq!(outside.position(), Err(0x100));
+/// ```
+///
+/// Logging a struct on a single line:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     #[offset(display, new)]
+///     pub struct Irp {
+///         0x0 pub kind: u16,
+///         0x8 pub status: i32
+///     }
+/// );
+///
+/// assert_eq!(Irp::new(6, -1).to_string(), "kind=6 status=-1");
+/// ```
+///
+/// A tuple struct with positional fields:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(pub struct Raw(0x0 pub u32, 0x8 pub u64););
+///
+/// let raw = Raw { _0: 1, _1: 2, ..unsafe { core::mem::zeroed() } };
+/// assert_eq!(core::mem::size_of::<Raw>(), 0x10);
+/// assert_eq!({ raw._1 }, 2);
+/// ```
+///
+/// Offsets computed from constant expressions:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     pub struct Header {
+///         0x0 pub magic: u32,
+///         0x4 pub version: u32
+///     }
+/// );
+///
+/// offset!(
+///     pub struct Packet {
+///         0x0 pub header: Header,
+///         core::mem::size_of::<Header>() + 0x8 pub length: u32,
+///         0x10 + 0x8 pub checksum: u64
+///     }
+/// );
+/// ```
+///
+/// Extending a base struct:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     pub struct CObject[0x10] {
+///         0x0 pub vtable: usize,
+///         0x8 pub ref_count: u32
+///     }
+/// );
+///
+/// offset!(
+///     pub struct CWindow extends CObject [0x30] {
+///         0x10 pub handle: usize,
+///         0x18 pub style: u32
+///     }
+/// );
+///
+/// let mut window: CWindow = unsafe { core::mem::zeroed() };
+/// window.ref_count = 1;
+/// assert_eq!({ window.as_base().ref_count }, 1);
+/// ```
+///
+/// 32-bit and 64-bit layouts from one definition:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     #[offset(dual)]
+///     pub struct LIST_ENTRY[0x8/0x10] {
+///         0x0 pub flink: Ptr,
+///         0x4/0x8 pub blink: Ptr
+///     }
+/// );
+///
+/// assert_eq!(core::mem::size_of::<LIST_ENTRY32>(), 0x8);
+/// assert_eq!(core::mem::size_of::<LIST_ENTRY64>(), 0x10);
+/// assert_eq!(core::mem::offset_of!(LIST_ENTRY64, blink), 0x8);
+/// ```
+///
+/// Converting between them for WOW64 processes:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     #[offset(wow64)]
+///     pub struct PEB_LDR_DATA[0x30/0x58] {
+///         0x0 pub length: u32,
+///         0x4 pub initialized: u8,
+///         0x8 pub ss_handle: Ptr,
+///         0xc/0x10 pub in_load_order_module_list: [Ptr; 2]
+///     }
+/// );
+///
+/// let mut narrow: PEB_LDR_DATA32 = unsafe { core::mem::zeroed() };
+/// narrow.length = 0x30;
+/// narrow.in_load_order_module_list = [0x7ffd_1000, 0x7ffd_2000];
+/// let wide = PEB_LDR_DATA64::from(&narrow);
+/// assert_eq!({ wide.length }, 0x30);
+/// assert_eq!({ wide.in_load_order_module_list }, [0x7ffd_1000, 0x7ffd_2000]);
+/// assert_eq!({ PEB_LDR_DATA32::try_from(&wide).unwrap().in_load_order_module_list }, [0x7ffd_1000, 0x7ffd_2000]);
+///
+/// let mut wide = wide;
+/// wide.ss_handle = 0x1_0000_0000;
+/// assert!(PEB_LDR_DATA32::try_from(&wide).is_err());
+/// ```
+///
+/// Offsets relative to the previous field:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     pub struct OBJECT_HEADER {
+///         0x0 pub pointer_count: u64,
+///         +0x0 pub handle_count: u64,
+///         +0x8 pub type_index: u8,
+///         0x20 pub body: u64
+///     }
+/// );
+///
+/// assert_eq!(core::mem::offset_of!(OBJECT_HEADER, handle_count), 0x8);
+/// assert_eq!(core::mem::offset_of!(OBJECT_HEADER, type_index), 0x18);
+/// ```
+///
+/// Offsets selected by cfg predicates:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     pub struct EPROCESS[0xA40] {
+///         0x440 pub unique_process_id: usize,
+///         { target_pointer_width = "64": 0x87A, target_pointer_width = "32": 0x6CA } pub protection: u8
+///     }
+/// );
+/// ```
+///
+/// Offsets from environment variables:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     pub struct EPROCESS {
+///         0x440 pub unique_process_id: usize,
+///         env("OFFSETTER_EXAMPLE_EPROCESS_PEB", 0x550) pub peb: u64
+///     }
+/// );
+///
+/// assert_eq!(EPROCESS::OFFSET_PEB, 0x550);
+/// ```
+///
+/// Fields with a declared size:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// #[derive(Clone, Copy)]
+/// pub struct UNICODE_STRING {
+///     pub length: u16,
+///     pub maximum_length: u16,
+///     pub buffer: u64,
+/// }
+///
+/// offset!(
+///     pub struct LDR_DATA_TABLE_ENTRY {
+///         0x48 pub full_dll_name: UNICODE_STRING [0x10],
+///         0x58 pub base_dll_name: UNICODE_STRING [0x10]
+///     }
+/// );
+/// ```
+///
+/// Fields that only exist on some targets:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     pub struct CONTEXT {
+///         0x0 pub flags: u32,
+///         #[cfg(target_arch = "x86_64")] 0x8 pub gs_base: u64,
+///         #[cfg(target_arch = "x86")] 0x8 pub fs_base: u32,
+///         0x10 pub stack: usize
+///     }
+/// );
+/// ```
+///
+/// Documented fields:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     pub struct KPRCB {
+///         /// Number of the processor this block belongs to.
+///         0x24 pub number: u16,
+///         /// Whether the processor is idle.
+///         0x28:0..1 pub idle: u8,
+///         #[deprecated = "use `number` instead"]
+///         0x2C pub legacy_number: u8
+///     }
+/// );
+/// ```
+///
+/// A view borrowing its data:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     pub struct View<'a> {
+///         0x0 pub data: &'a [u8; 16],
+///         0x8 pub length: u32
+///     }
+/// );
+/// ```
+///
+/// A generic container:
+///
+/// ```rust
+/// # use offsetter::offset_debug;
+/// offset_debug!(
+///     pub struct Wrapper<T: Copy> {
+///         0x0 pub header: u32,
+///         0x10 pub payload: T
+///     }
+/// );
+///
+/// assert_eq!(core::mem::size_of::<Wrapper<u64>>(), 0x18);
+/// ```
+///
+/// Unknown regions spanning until the next field:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     pub struct KTHREAD_STATE[0x80] {
+///         0x0 pub header: u64,
+///         0x8 pub unknown: auto,
+///         0x40 pub state: u32,
+///         0x44 pub tail: auto
+///     }
+/// );
+///
+/// let state: KTHREAD_STATE = unsafe { core::mem::zeroed() };
+/// assert_eq!(state.unknown.len(), 0x38);
+/// assert_eq!(state.tail.len(), 0x3C);
+/// ```
+///
+/// Fields aliasing one another:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     pub struct KSYSTEM_TIME {
+///         0x0 pub quad_part: u64,
+///         overlap 0x0 pub low_part: u32,
+///         overlap 0x4 pub high_part: u32,
+///         0x8 pub high2_time: u32
+///     }
+/// );
+///
+/// let mut time: KSYSTEM_TIME = unsafe { core::mem::zeroed() };
+/// time.set_high_part(1);
+/// assert_eq!({ time.quad_part }, 1 << 32);
+/// assert_eq!(time.low_part(), 0);
+/// ```
+///
+/// Regions that are in use but not understood yet:
+///
+/// ```rust
+/// # use offsetter::offset_debug;
+/// offset_debug!(
+///     pub struct KTIMER[0x40] {
+///         0x0 pub header: [u8; 0x18],
+///         0x18 pub due_time: u64,
+///         0x20 reserved _unknown: [u8; 0x10],
+///         0x30 pub dpc: usize
+///     }
+/// );
+/// ```
+///
+/// A header followed by a variable number of entries:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     pub struct LIST {
+///         0x0 pub count: u32,
+///         0x8 pub entries: [u64; ...]
+///     }
+/// );
+///
+/// let buffer: [u64; 4] = [3, 10, 20, 30];
+/// let list = unsafe { &*buffer.as_ptr().cast::<LIST>() };
+/// let entries = unsafe { &*list.entries(list.count as usize) };
+/// assert_eq!(entries, &[10, 20, 30]);
+/// assert_eq!(core::mem::size_of::<LIST>(), 0x8);
+/// ```
+///
+/// Bitfields sharing a storage byte:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     pub struct KTHREAD[0x40] {
+///         0x0 pub header: u64,
+///         0x30:0..3 pub state: u8,
+///         0x30:3..8 pub priority: u8,
+///         0x38 pub teb: usize
+///     }
+/// );
+///
+/// let mut thread: KTHREAD = unsafe { core::mem::zeroed() };
+/// thread.set_state(5);
+/// thread.set_priority(0x1f);
+/// assert_eq!(thread.state(), 5);
+/// assert_eq!(thread.priority(), 0x1f);
+/// ```
+///
+/// Flags declared with their field:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     pub struct EPROCESS[0x40] {
+///         0x0 pub unique_process_id: usize,
+///         0x30 pub flags: flags ProcessFlags: u32 {
+///             /// The process is being created.
+///             CREATE = 0x1,
+///             EXITING = 0x4,
+///         }
+///     }
+/// );
+///
+/// let mut process: EPROCESS = unsafe { core::mem::zeroed() };
+/// process.set_flags(ProcessFlags::EXITING | ProcessFlags::from_bits_retain(0x100));
+/// assert!(process.flags().contains(ProcessFlags::EXITING));
+/// assert!(!process.flags().contains(ProcessFlags::CREATE));
+/// assert_eq!(format!("{:?}", process.flags()), "ProcessFlags(EXITING | 0x100)");
+/// assert_eq!(ProcessFlags::from_bits(0x100), None);
+/// ```
+///
+/// Array elements with a stride larger than their size:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// #[derive(Clone, Copy)]
+/// pub struct Slot {
+///     pub key: u32,
+///     pub value: u32,
+/// }
+///
+/// offset!(
+///     pub struct Table {
+///         0x0 pub count: u32,
+///         0x10 pub slots: [Slot; 4] stride 0x20
+///     }
+/// );
+///
+/// let mut table: Table = unsafe { core::mem::zeroed() };
+/// table.set_slots(2, Slot { key: 1, value: 2 });
+/// assert_eq!(table.slots(2).value, 2);
+/// assert_eq!(core::mem::size_of::<Table>(), 0x10 + 3 * 0x20 + 8);
+/// ```
+///
+/// Embedding a struct that was defined by this macro:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// offset!(
+///     pub struct DISPATCHER_HEADER[0x18] {
+///         0x0 pub type_: u8,
+///         0x4 pub signal_state: i32,
+///         0x8 pub wait_list_head: [usize; 2]
+///     }
+/// );
+///
+/// offset!(
+///     pub struct KPROCESS[0x40] {
+///         embed 0x0 pub header: DISPATCHER_HEADER,
+///         0x18 pub profile_list_head: [usize; 2],
+///         0x28 pub directory_table_base: u64
+///     }
+/// );
+/// ```
+///
+/// Offsets sourced from a module of constants:
+///
+/// ```rust
+/// # use offsetter::offset;
+/// mod offsets {
+///     pub const EPROCESS_UNIQUE_PROCESS_ID: usize = 0x440;
+///     pub const EPROCESS_PEB: usize = 0x550;
+/// }
+///
+/// offset!(
+///     pub struct EPROCESS[0xA40] {
+///         offsets::EPROCESS_UNIQUE_PROCESS_ID pub unique_process_id: usize,
+///         offsets::EPROCESS_PEB pub peb: *mut u8,
+///     }
+/// );
+/// ```
+macro_rules! offset {
+    (@emit {[$($option:ident)*] [$(#[$attr:meta])*] $vis:vis struct $name:ident [$($struct_size:expr)?] [$($lifetime:lifetime),*] [$($param:ident $(: $bound:path)?),*]} ($current_offset:expr) -> {$($body:tt)*} {$($fields:tt)*}) => {
+        $crate::offset_zerocopy!([$($option)*] [$($option)*] {[$(#[$attr])*] $vis struct $name [$($struct_size)?] [$($lifetime),*] [$($param $(: $bound)?),*]} ($current_offset) {$($body)*});
+
+        impl<$($lifetime,)* $($param $(: $bound)?),*> $name<$($lifetime,)* $($param),*> {
+            /// The size of the struct in bytes, either the declared total size or the end of the last field.
+            pub const SIZE: usize = core::mem::size_of::<Self>();
+
+            // The layout as declared, next to the offsets the compiler chose, for `offset_tests!`.
+            #[doc(hidden)]
+            pub const __LAYOUT_SIZE: usize = $crate::offset!(@layout_size [$($struct_size)?] ($current_offset) [$($param),*]);
+
+            $crate::offset!(@layout_offsets [] $($fields)*);
+        }
+
+        $crate::offset!(@unique $name {$($fields)*});
+        $crate::offset!(@fits {[$($option)*] [$(#[$attr])*] $vis struct $name [$($struct_size)?] [$($lifetime),*] [$($param $(: $bound)?),*]} $name [$($struct_size)?] [$($param),*] {$($fields)*});
+        $crate::offset!(@order $name {$($fields)*});
+        $crate::offset!(@bytes_fns [$($option)*] [$($option)*] $name [$($lifetime),*] [$($param $(: $bound)?),*] [$($struct_size)?] ($current_offset));
+        $crate::offset!(@constructor [$($option)*] $name [$($lifetime),*] [$($param $(: $bound)?),*] pointer [] [] {$($fields)*});
+        $crate::offset!(@debug_impl [$($option)*] [] [] [] $name [$($lifetime),*] [$($param $(: $bound)?),*] {$($fields)*});
+        $crate::offset!(@default_impl [$($option)*] $name [$($lifetime),*] [$($param $(: $bound)?),*] {$($fields)*});
+
+        $crate::offset!(@accessors [$($option)*] $name [$($lifetime),*] [$($param $(: $bound)?),*] {$($fields)*});
+        $crate::offset!(@options [$($option)*] $vis $name [$($lifetime),*] [$($param $(: $bound)?),*] {$($fields)*});
+        $crate::offset_checker!($name [$($param),*] {$($fields)*});
+        $crate::offset_checker!(@total_size $name [$($param),*] [$($struct_size)?] ($current_offset));
+        $crate::offset_checker!(@overlaps {[$($option)*] [$(#[$attr])*] $vis struct $name [$($struct_size)?] [$($lifetime),*] [$($param),*]} {$($fields)*});
+    };
+
+    // The constructor takes every field stored in the struct, the padding and the storage of fields
+    // that are only reachable through accessors is zeroed, and so are reserved regions, but only
+    // after checking that they can be zero. It's private, for `Default` and the other trait
+    // implementations building the struct, and only public as `new` with the `new` option, so it
+    // can't clash with a field or method of that name otherwise.
+    (@constructor $options:tt $name:ident [$($lifetime:lifetime),*] [$($param:ident $(: $bound:path)?),*] $pointer:ident [$($argument:tt)*] [$($write:tt)*] {}) => {
+        impl<$($lifetime,)* $($param $(: $bound)?),*> $name<$($lifetime,)* $($param),*> {
+            #[allow(dead_code, clippy::too_many_arguments, clippy::not_unsafe_ptr_arg_deref)]
+            fn __new($($argument)*) -> Self {
+                let mut value = core::mem::MaybeUninit::<Self>::zeroed();
+                let $pointer = value.as_mut_ptr();
+                unsafe {
+                    $($write)*
+                    value.assume_init()
+                }
+            }
+
+            $crate::offset!(@new_fn $options $pointer [$($argument)*] [$($write)*]);
+        }
+    };
+
+    (@constructor $options:tt $name:ident $lifetimes:tt $params:tt $pointer:ident [$($argument:tt)*] [$($write:tt)*] {([field] [$($predicate:tt)?] $attrs:tt $offset:tt $default:tt $vis_field:vis $id:ident: $ty:ty) $($fields:tt)*}) => {
+        $crate::offset!(@constructor $options $name $lifetimes $params $pointer [$($argument)* $(#[cfg $predicate])? $id: $ty,] [$($write)*
+            $(#[cfg $predicate])?
+            core::ptr::addr_of_mut!((*$pointer).$id).write_unaligned($id);
+        ] {$($fields)*});
+    };
+
+    (@constructor $options:tt $name:ident $lifetimes:tt $params:tt $pointer:ident [$($argument:tt)*] [$($write:tt)*] {([atomic] [$($predicate:tt)?] $attrs:tt $offset:tt $default:tt $vis_field:vis $id:ident: $ty:ty) $($fields:tt)*}) => {
+        $crate::offset!(@constructor $options $name $lifetimes $params $pointer [$($argument)* $(#[cfg $predicate])? $id: $ty,] [$($write)*
+            $(#[cfg $predicate])?
+            core::ptr::addr_of_mut!((*$pointer).$id).cast::<$ty>().write_unaligned($id);
+        ] {$($fields)*});
+    };
+
+    (@constructor $options:tt $name:ident $lifetimes:tt $params:tt $pointer:ident $arguments:tt [$($write:tt)*] {([reserved] [$($predicate:tt)?] $attrs:tt $offset:tt $default:tt $id:ident: $ty:ty) $($fields:tt)*}) => {
+        $crate::offset!(@constructor $options $name $lifetimes $params $pointer $arguments [$($write)*
+            $(#[cfg $predicate])?
+            core::ptr::addr_of_mut!((*$pointer).$id).write_unaligned(const { core::mem::MaybeUninit::<$ty>::zeroed().assume_init() });
+        ] {$($fields)*});
+    };
+
+    (@constructor $options:tt $name:ident $lifetimes:tt $params:tt $pointer:ident $arguments:tt $writes:tt {$field:tt $($fields:tt)*}) => {
+        $crate::offset!(@constructor $options $name $lifetimes $params $pointer $arguments $writes {$($fields)*});
+    };
+
+    (@new_fn [new $($option:ident)*] $pointer:ident [$($argument:tt)*] [$($write:tt)*]) => {
+        /// Creates the struct from the values of its fields, with everything else set to zero.
+        #[allow(dead_code, clippy::too_many_arguments, clippy::not_unsafe_ptr_arg_deref)]
+        pub fn new($($argument)*) -> Self {
+            let mut value = core::mem::MaybeUninit::<Self>::zeroed();
+            let $pointer = value.as_mut_ptr();
+            unsafe {
+                $($write)*
+                value.assume_init()
+            }
+        }
+    };
+
+    (@new_fn [$other:ident $($option:ident)*] $($input:tt)*) => {
+        $crate::offset!(@new_fn [$($option)*] $($input)*);
+    };
+
+    (@new_fn [] $($input:tt)*) => {};
+
+    // `Default` is implemented if it's asked for with the `default` option, or if any field has a
+    // default value.
+    (@default_impl [default $($option:ident)*] $name:ident $lifetimes:tt $params:tt $fields:tt) => {
+        $crate::offset!(@default_fn $name $lifetimes $params value [] [] $fields);
+    };
+
+    (@default_impl [$other:ident $($option:ident)*] $($input:tt)*) => {
+        $crate::offset!(@default_impl [$($option)*] $($input)*);
+    };
+
+    (@default_impl [] $name:ident $lifetimes:tt $params:tt {$($field:tt)*}) => {
+        $crate::offset!(@default_scan $name $lifetimes $params {$($field)*} $($field)*);
+    };
+
+    (@default_scan $name:ident $lifetimes:tt $params:tt $fields:tt ($kind:tt $predicate:tt $attrs:tt $offset:tt [$default:expr] $($declaration:tt)*) $($field:tt)*) => {
+        $crate::offset!(@default_fn $name $lifetimes $params value [] [] $fields);
+    };
+
+    (@default_scan $name:ident $lifetimes:tt $params:tt $fields:tt $next:tt $($field:tt)*) => {
+        $crate::offset!(@default_scan $name $lifetimes $params $fields $($field)*);
+    };
+
+    (@default_scan $name:ident $lifetimes:tt $params:tt $fields:tt) => {};
+
+    // The stored fields are passed to `new`, while the defaults of fields that are only reachable
+    // through accessors are set afterwards.
+    (@default_fn $name:ident [$($lifetime:lifetime),*] [$($param:ident $(: $bound:path)?),*] $value:ident [$($argument:tt)*] [$($set:tt)*] {}) => {
+        impl<$($lifetime,)* $($param $(: $bound)?),*> core::default::Default for $name<$($lifetime,)* $($param),*> {
+            fn default() -> Self {
+                #[allow(unused_mut)]
+                let mut $value = Self::__new($($argument)*);
+                $($set)*
+                $value
+            }
+        }
+    };
+
+    (@default_fn $name:ident $lifetimes:tt $params:tt $value:ident [$($argument:tt)*] $sets:tt {([field] [$($predicate:tt)?] $attrs:tt $offset:tt [$($default:expr)?] $vis_field:vis $id:ident: $ty:ty) $($fields:tt)*}) => {
+        $crate::offset!(@default_fn $name $lifetimes $params $value [$($argument)* $(#[cfg $predicate])? $crate::offset!(@default_value [$($default)?] $ty),] $sets {$($fields)*});
+    };
+
+    (@default_fn $name:ident $lifetimes:tt $params:tt $value:ident [$($argument:tt)*] $sets:tt {([atomic] [$($predicate:tt)?] $attrs:tt $offset:tt [$($default:expr)?] $vis_field:vis $id:ident: $ty:ty) $($fields:tt)*}) => {
+        $crate::offset!(@default_fn $name $lifetimes $params $value [$($argument)* $(#[cfg $predicate])? $crate::offset!(@default_value [$($default)?] $ty),] $sets {$($fields)*});
+    };
+
+    (@default_fn $name:ident $lifetimes:tt $params:tt $value:ident $arguments:tt [$($set:tt)*] {([$($kind:tt)*] [$($predicate:tt)?] $attrs:tt $offset:tt [$default:expr] $vis_field:vis $id:ident: $ty:ty) $($fields:tt)*}) => {
+        $crate::offset!(@default_fn $name $lifetimes $params $value $arguments [$($set)*
+            $(#[cfg $predicate])?
+            $crate::paste::paste!($value.[<set_ $id>]($default));
+        ] {$($fields)*});
+    };
+
+    (@default_fn $name:ident $lifetimes:tt $params:tt $value:ident $arguments:tt $sets:tt {$field:tt $($fields:tt)*}) => {
+        $crate::offset!(@default_fn $name $lifetimes $params $value $arguments $sets {$($fields)*});
+    };
+
+    (@default_value [$default:expr] $ty:ty) => {
+        $default
+    };
+
+    (@default_value [] $ty:ty) => {
+        const { unsafe { core::mem::MaybeUninit::<$ty>::zeroed().assume_init() } }
+    };
+
+    (@bytes_fns [bytes $($option:ident)*] $options:tt $name:ident [$($lifetime:lifetime),*] [$($param:ident $(: $bound:path)?),*] $struct_size:tt $current_offset:tt) => {
+        impl<$($lifetime,)* $($param $(: $bound)?),*> $name<$($lifetime,)* $($param),*> {
+            /// Copies the struct out of the start of `bytes`, or returns `None` if there are fewer
+            /// than `SIZE` bytes. Any bytes after the struct are ignored.
+            ///
+            /// # Safety
+            ///
+            /// The bytes must be a valid value for every field.
+            #[allow(dead_code)]
+            pub unsafe fn try_from_bytes_unchecked(bytes: &[u8]) -> Option<Self> {
+                if bytes.len() < Self::SIZE {
+                    return None;
+                }
+
+                Some(core::ptr::read_unaligned(bytes.as_ptr().cast::<Self>()))
+            }
+
+            $crate::offset!(@plain_bytes $options);
+        }
+
+        $crate::offset!(@as_bytes $name [$($lifetime),*] [$($param),*] $struct_size $current_offset);
+    };
+
+    (@bytes_fns [$other:ident $($option:ident)*] $($input:tt)*) => {
+        $crate::offset!(@bytes_fns [$($option)*] $($input)*);
+    };
+
+    (@bytes_fns [] $($input:tt)*) => {};
+
+    // The fields of plain structs are checked to be valid for any bytes, so copying them out of a
+    // slice is safe.
+    (@plain_bytes [plain $($option:ident)*]) => {
+        /// Copies the struct out of the start of `bytes`, or returns `None` if there are fewer than
+        /// `SIZE` bytes. Any bytes after the struct are ignored.
+        #[allow(dead_code)]
+        pub fn try_from_bytes(bytes: &[u8]) -> Option<Self> {
+            unsafe { Self::try_from_bytes_unchecked(bytes) }
+        }
+    };
+
+    (@plain_bytes [$other:ident $($option:ident)*]) => {
+        $crate::offset!(@plain_bytes [$($option)*]);
+    };
+
+    (@plain_bytes []) => {};
+
+    // The size of a struct with lifetimes can't be named through `Self` in an array length, so it's
+    // spelled out instead. The size of a struct with type parameters can't be used at all.
+    (@as_bytes $name:ident [$($lifetime:lifetime),*] [] [$($struct_size:expr)?] ($current_offset:expr)) => {
+        impl<$($lifetime),*> $name<$($lifetime),*> {
+            /// Returns the bytes of the struct, e.g. to write it back to memory or to a file.
+            #[allow(dead_code)]
+            pub fn as_bytes(&self) -> &[u8; $crate::offset!(@total_size [$($struct_size)?] ($current_offset))] {
+                unsafe { &*(self as *const Self).cast() }
+            }
+
+            /// Returns the bytes of the struct for writing.
+            ///
+            /// # Safety
+            ///
+            /// The bytes written must be a valid value for every field.
+            #[allow(dead_code)]
+            pub unsafe fn as_bytes_mut(&mut self) -> &mut [u8; $crate::offset!(@total_size [$($struct_size)?] ($current_offset))] {
+                &mut *(self as *mut Self).cast()
+            }
+        }
+    };
+
+    (@as_bytes $name:ident $lifetimes:tt [$($params:tt)+] $struct_size:tt $current_offset:tt) => {};
+
+    (@total_size [$struct_size:expr] $current_offset:tt) => {
+        $struct_size
+    };
+
+    (@total_size [] ($current_offset:expr)) => {
+        $current_offset
+    };
+
+    // The fields of private structs keep to themselves, only their accessors have the visibility
+    // the fields were declared with.
+    (@struct [private $($option:ident)*] {[$(#[$attr:meta])*] $vis:vis struct $name:ident [$($struct_size:expr)?] [$($lifetime:lifetime),*] [$($param:ident $(: $bound:path)?),*]} ($current_offset:expr) {$($(#[$($field_attr:tt)*])* [$($vis_field:tt)*] $field:tt: $ty:ty,)*}) => {
+        $crate::paste::paste! {
+            #[repr(C, packed)]
+            $(#[$attr])* $vis struct $name<$($lifetime,)* $($param $(: $bound)?),*> {
+                $($(#[$($field_attr)*])* $field: $ty,)*
+                $(_remaining_padding: [u8; usize::saturating_sub($struct_size, $current_offset)],)?
+            }
+        }
+    };
+
+    (@struct [$other:ident $($option:ident)*] $($input:tt)*) => {
+        $crate::offset!(@struct [$($option)*] $($input)*);
+    };
+
+    (@struct [] {[$(#[$attr:meta])*] $vis:vis struct $name:ident [$($struct_size:expr)?] [$($lifetime:lifetime),*] [$($param:ident $(: $bound:path)?),*]} ($current_offset:expr) {$($(#[$($field_attr:tt)*])* [$($vis_field:tt)*] $field:tt: $ty:ty,)*}) => {
+        $crate::paste::paste! {
+            #[repr(C, packed)]
+            $(#[$attr])* $vis struct $name<$($lifetime,)* $($param $(: $bound)?),*> {
+                $($(#[$($field_attr)*])* $($vis_field)* $field: $ty,)*
+                $(_remaining_padding: [u8; usize::saturating_sub($struct_size, $current_offset)],)?
+            }
+        }
+    };
+
+    (@emit {[$($option:ident)*] [$(#[$attr:meta])*] $vis:vis union $name:ident $union_size:tt [] []} ($current_offset:expr) -> {$($body:tt)*} {$(([field] [$($predicate:tt)?] [$($attrs:tt)*] [$($offset:tt)*] $default:tt $vis_field:vis $id:ident: $ty:ty))*}) => {
+        $crate::paste::paste! {
+            #[repr(C, packed)]
+            $(#[$attr])* $vis union $name {
+                $($(#[cfg $predicate])? $($attrs)* $id: [<$name _ $id>],)*
+            }
+
+            impl $name {
+                /// The size of the union in bytes.
+                pub const SIZE: usize = core::mem::size_of::<Self>();
+
+                $crate::offset!(@fields_const [] $(([field] [$($predicate)?] [$($offset)*] $id: $ty))*);
+                $($crate::offset!(@offset_const [field] [$($predicate)?] [$($offset)*] $vis_field $id);)*
+            }
+        }
+
+        $crate::offset!(@union_accessors [$($option)*] $name {$(([field] [$($predicate)?] [$($attrs)*] [$($offset)*] $default $vis_field $id: $ty))*});
+        $($crate::offset!(@union_variant $name $union_size [$($predicate)?] ($($offset)*) $id: $ty);)*
+    };
+
+    // The variants of plain unions are valid for any bytes, so reading them is safe. Otherwise the
+    // bytes may have been written as another variant, which the caller has to rule out.
+    (@union_accessors [plain $($option:ident)*] $name:ident {$(([field] [$($predicate:tt)?] [$($attrs:tt)*] $offset:tt $default:tt $vis_field:vis $id:ident: $ty:ty))*}) => {
+        impl $name {
+            $(
+                #[doc = concat!("Reads the `", stringify!($id), "` variant.")]
+                $(#[cfg $predicate])?
+                $($attrs)*
+                $vis_field fn $id(&self) -> $ty {
+                    unsafe { core::ptr::read_unaligned(core::ptr::addr_of!(self.$id.value)) }
+                }
+            )*
+        }
+
+        $crate::offset!(@plain $name [] [] {$(([field] [$($predicate)?] [$($attrs)*] $offset $default $vis_field $id: $ty))*});
+    };
+
+    (@union_accessors [$other:ident $($option:ident)*] $name:ident $fields:tt) => {
+        $crate::offset!(@union_accessors [$($option)*] $name $fields);
+    };
+
+    (@union_accessors [] $name:ident {$(([field] [$($predicate:tt)?] [$($attrs:tt)*] $offset:tt $default:tt $vis_field:vis $id:ident: $ty:ty))*}) => {
+        impl $name {
+            $(
+                #[doc = concat!("Reads the `", stringify!($id), "` variant.")]
+                ///
+                /// # Safety
+                ///
+                /// The bytes of the variant have to be a valid value of its type, e.g. because
+                /// it was the variant written last.
+                $(#[cfg $predicate])?
+                $($attrs)*
+                $vis_field unsafe fn $id(&self) -> $ty {
+                    unsafe { core::ptr::read_unaligned(core::ptr::addr_of!(self.$id.value)) }
+                }
+            )*
+        }
+    };
+
+    // Every variant of a union is its own packed struct, so each one gets padded up to its offset.
+    (@union_variant $name:ident [$($union_size:expr)?] [$($predicate:tt)?] ($offset:expr) $id:ident: $ty:ty) => {
+        $crate::paste::paste! {
+            $(#[cfg $predicate])?
+            #[doc(hidden)]
+            #[allow(non_camel_case_types)]
+            #[repr(C, packed)]
+            #[derive(Clone, Copy)]
+            struct [<$name _ $id>] {
+                _pad: [u8; $offset],
+                value: $ty,
+                $(_remaining_padding: [u8; $union_size - ($offset + core::mem::size_of::<$ty>())],)?
+            }
+        }
+    };
+
+    // The accessors of volatile structs access the memory with `read_volatile` and `write_volatile`,
+    // all others with `read_unaligned` and `write_unaligned`.
+    (@accessors [volatile $($option:ident)*] $($input:tt)*) => {
+        $crate::offset!(@accessors_with volatile $($input)*);
+    };
+
+    (@accessors [$other:ident $($option:ident)*] $($input:tt)*) => {
+        $crate::offset!(@accessors [$($option)*] $($input)*);
+    };
+
+    (@accessors [] $($input:tt)*) => {
+        $crate::offset!(@accessors_with unaligned $($input)*);
+    };
+
+    (@accessors_with $access:ident $name:ident [$($lifetime:lifetime),*] [$($param:ident $(: $bound:path)?),*] {$(([$($kind:tt)*] $predicate:tt $attrs:tt [$($offset:tt)*] $default:tt $vis_field:vis $id:ident: $ty:ty))*}) => {
+        impl<$($lifetime,)* $($param $(: $bound)?),*> $name<$($lifetime,)* $($param),*> {
+            $crate::offset!(@fields_const [] $(([$($kind)*] $predicate [$($offset)*] $id: $ty))*);
+            $crate::offset!(@layout_const $name [] $(([$($kind)*] $predicate [$($offset)*] $id: $ty))*);
+            $($crate::offset!(@offset_const [$($kind)*] $predicate [$($offset)*] $vis_field $id);)*
+            $($crate::offset!(@accessor $access [$($kind)*] $predicate $attrs [$($offset)*] $vis_field $id: $ty);)*
+            $crate::offset!(@validate_scan $name {$(([$($kind)*] $predicate $attrs $id))*} $(([$($kind)*] $predicate $attrs $id))*);
+        }
+    };
+
+    // `validate` is only generated for structs that declare invariants, so it can't clash with a
+    // field or method of that name otherwise.
+    (@validate_scan $name:ident $fields:tt ($kind:tt $predicate:tt [#[cfg_attr(any(), validate($($invariant:tt)*))] $($attrs:tt)*] $id:ident) $($rest:tt)*) => {
+        $crate::offset!(@validate $name $fields);
+    };
+
+    (@validate_scan $name:ident $fields:tt ($kind:tt $predicate:tt [#[$($attr:tt)*] $($attrs:tt)*] $id:ident) $($rest:tt)*) => {
+        $crate::offset!(@validate_scan $name $fields ($kind $predicate [$($attrs)*] $id) $($rest)*);
+    };
+
+    (@validate_scan $name:ident $fields:tt ($kind:tt $predicate:tt [] $id:ident) $($rest:tt)*) => {
+        $crate::offset!(@validate_scan $name $fields $($rest)*);
+    };
+
+    (@validate_scan $name:ident $fields:tt) => {};
+
+    (@validate $name:ident {$(($kind:tt $predicate:tt $attrs:tt $id:ident))*}) => {
+        /// Checks the invariants declared with `#[validate(...)]` on the fields, returning the first
+        /// one that doesn't hold.
+        #[allow(dead_code)]
+        pub fn validate(&self) -> core::result::Result<(), $crate::LayoutError> {
+            $($crate::offset!(@invariants self $name $kind $predicate $id $attrs);)*
+            Ok(())
+        }
+    };
+
+    (@invariants $this:ident $name:ident $kind:tt [$($predicate:tt)?] $id:ident [#[cfg_attr(any(), validate($($invariant:tt)*))] $($attrs:tt)*]) => {
+        $(#[cfg $predicate])?
+        {
+            let $id = $crate::offset!(@read $this $kind $id);
+            if !($($invariant)*) {
+                return Err($crate::LayoutError {
+                    struct_name: stringify!($name),
+                    field: stringify!($id),
+                    invariant: stringify!($($invariant)*),
+                });
+            }
+        }
+        $crate::offset!(@invariants $this $name $kind [$($predicate)?] $id [$($attrs)*]);
+    };
+
+    (@invariants $this:ident $name:ident $kind:tt $predicate:tt $id:ident [#[$($attr:tt)*] $($attrs:tt)*]) => {
+        $crate::offset!(@invariants $this $name $kind $predicate $id [$($attrs)*]);
+    };
+
+    (@invariants $this:ident $name:ident $kind:tt $predicate:tt $id:ident []) => {};
+
+    // A struct with type parameters can't have a total size and ends with the field of generic size,
+    // whose size isn't known up front.
+    (@layout_size $struct_size:tt $current_offset:tt []) => {
+        $crate::offset!(@total_size $struct_size $current_offset)
+    };
+
+    (@layout_size $struct_size:tt $current_offset:tt [$($param:ident),+]) => {
+        core::mem::size_of::<Self>()
+    };
+
+    // Only the fields stored in the struct have an offset of their own, the others are covered by
+    // padding.
+    (@layout_offsets [$($info:tt)*]) => {
+        #[doc(hidden)]
+        pub const __LAYOUT_OFFSETS: &'static [(&'static str, usize, usize)] = &[$($info)*];
+    };
+
+    (@layout_offsets $info:tt ([overlap] $($field:tt)*) $($rest:tt)*) => {
+        $crate::offset!(@layout_offsets $info $($rest)*);
+    };
+
+    (@layout_offsets [$($info:tt)*] ([$kind:ident] [$($predicate:tt)?] $attrs:tt [$($offset:tt)*] $default:tt $vis_field:vis $id:ident: $ty:ty) $($rest:tt)*) => {
+        $crate::offset!(@layout_offsets [$($info)* $(#[cfg $predicate])? (stringify!($id), $($offset)*, core::mem::offset_of!(Self, $id)),] $($rest)*);
+    };
+
+    (@layout_offsets $info:tt $field:tt $($rest:tt)*) => {
+        $crate::offset!(@layout_offsets $info $($rest)*);
+    };
+
+    (@fields_const [$($info:tt)*]) => {
+        /// The name, offset and size of every field except reserved regions.
+        pub const FIELDS: &'static [$crate::FieldInfo] = &[$($info)*];
+    };
+
+    (@fields_const $info:tt ([reserved] $predicate:tt $offset:tt $id:ident: $ty:ty) $($rest:tt)*) => {
+        $crate::offset!(@fields_const $info $($rest)*);
+    };
+
+    (@fields_const [$($info:tt)*] ([$($kind:tt)*] [$($predicate:tt)?] [$($offset:tt)*] $id:ident: $ty:ty) $($rest:tt)*) => {
+        $crate::offset!(@fields_const [$($info)* $(#[cfg $predicate])? $crate::FieldInfo {
+            name: stringify!($id),
+            offset: $($offset)*,
+            size: $crate::offset!(@field_size [$($kind)*] $ty),
+        },] $($rest)*);
+    };
+
+    (@layout_const $name:ident [$($descriptors:tt)*]) => {
+        /// The name and size of the struct, and the name, offset, size and type of every field
+        /// except reserved regions.
+        pub const LAYOUT: $crate::Layout = $crate::Layout {
+            name: stringify!($name),
+            size: Self::SIZE,
+            fields: &[$($descriptors)*],
+        };
+    };
+
+    (@layout_const $name:ident $descriptors:tt ([reserved] $predicate:tt $offset:tt $id:ident: $ty:ty) $($rest:tt)*) => {
+        $crate::offset!(@layout_const $name $descriptors $($rest)*);
+    };
+
+    (@layout_const $name:ident [$($descriptors:tt)*] ([$($kind:tt)*] [$($predicate:tt)?] [$($offset:tt)*] $id:ident: $ty:ty) $($rest:tt)*) => {
+        $crate::offset!(@layout_const $name [$($descriptors)* $(#[cfg $predicate])? $crate::FieldDescriptor {
+            name: stringify!($id),
+            offset: $($offset)*,
+            size: $crate::offset!(@field_size [$($kind)*] $ty),
+            type_name: stringify!($ty),
+        },] $($rest)*);
+    };
+
+    // `describe` lists the reserved regions next to `FIELDS`, so they aren't mistaken for padding.
+    (@describe_fn $name:ident [$($reserved:tt)*]) => {
+        /// Writes a table of the fields, reserved regions and padding of the struct ordered by
+        /// offset, e.g. to log the layout a binary was built with.
+        #[allow(dead_code)]
+        pub fn describe(writer: &mut impl core::fmt::Write) -> core::fmt::Result {
+            $crate::describe_layout(writer, stringify!($name), Self::SIZE, Self::FIELDS, &[$($reserved)*])
+        }
+    };
+
+    (@describe_fn $name:ident [$($reserved:tt)*] ([reserved] [$($predicate:tt)?] [$($offset:tt)*] $id:ident: $ty:ty) $($rest:tt)*) => {
+        $crate::offset!(@describe_fn $name [$($reserved)* $(#[cfg $predicate])? $crate::FieldInfo {
+            name: stringify!($id),
+            offset: $($offset)*,
+            size: core::mem::size_of::<$ty>(),
+        },] $($rest)*);
+    };
+
+    (@describe_fn $name:ident $reserved:tt $field:tt $($rest:tt)*) => {
+        $crate::offset!(@describe_fn $name $reserved $($rest)*);
+    };
+
+    (@c_declaration_fn $name:ident [$($fields:tt)*]) => {
+        /// Writes an equivalent C declaration of the struct, packed and with char arrays for the
+        /// padding, e.g. to share the layout with a driver written in C.
+        #[allow(dead_code)]
+        pub fn c_declaration(writer: &mut impl core::fmt::Write) -> core::fmt::Result {
+            $crate::write_c_declaration(writer, stringify!($name), Self::SIZE, &[$($fields)*])
+        }
+    };
+
+    (@c_declaration_fn $name:ident [$($fields:tt)*] ([$kind:ident $($arguments:tt)*] [$($predicate:tt)?] [$($offset:tt)*] $id:ident: $ty:ty) $($rest:tt)*) => {
+        $crate::offset!(@c_declaration_fn $name [$($fields)* $(#[cfg $predicate])? (stringify!($kind), $crate::FieldInfo {
+            name: stringify!($id),
+            offset: $($offset)*,
+            size: $crate::offset!(@field_size [$kind $($arguments)*] $ty),
+        }, stringify!($ty)),] $($rest)*);
+    };
+
+    (@field_size [stride $elem:tt $count:tt $stride:tt] $ty:ty) => {
+        ($count - 1) * ($stride) + core::mem::size_of::<$elem>()
+    };
+
+    (@field_size [flexible $elem:tt] $ty:ty) => {
+        0
+    };
+
+    (@field_size $kind:tt $ty:ty) => {
+        core::mem::size_of::<$ty>()
+    };
+
+    (@offset_const [reserved] $predicate:tt $offset:tt $vis_field:vis $id:ident) => {};
+
+    (@offset_const $kind:tt [$($predicate:tt)?] [$($offset:tt)*] $vis_field:vis $id:ident) => {
+        $crate::paste::paste! {
+            #[doc = concat!("The offset of `", stringify!($id), "` in bytes.")]
+            #[allow(dead_code)]
+            $(#[cfg $predicate])?
+            $vis_field const [<OFFSET_ $id:upper>]: usize = $($offset)*;
+        }
+    };
+
+    // Fields of packed structs can't be borrowed, so the accessors copy the value in and out instead.
+    // Only `Copy` values can be read that way, anything else would be owned twice.
+    (@accessor $access:ident [field] [$($predicate:tt)?] [$($attrs:tt)*] [$($offset:tt)*] $vis_field:vis $id:ident: $ty:ty) => {
+        $crate::paste::paste! {
+            $(#[cfg $predicate])?
+            $($attrs)*
+            #[allow(dead_code)]
+            $vis_field fn $id(&self) -> $ty where for<'copy> $ty: Copy {
+                unsafe { $crate::offset!(@load $access core::ptr::addr_of!(self.$id)) }
+            }
+
+            $(#[cfg $predicate])?
+            $($attrs)*
+            #[allow(dead_code, clippy::not_unsafe_ptr_arg_deref)]
+            $vis_field fn [<set_ $id>](&mut self, value: $ty) {
+                unsafe { $crate::offset!(@store $access core::ptr::addr_of_mut!(self.$id), value) }
+            }
+        }
+    };
+
+    // Atomic operations need an aligned reference, which is only taken after checking the address.
+    (@accessor $access:ident [atomic] [$($predicate:tt)?] [$($attrs:tt)*] [$($offset:tt)*] $vis_field:vis $id:ident: $ty:ty) => {
+        $crate::paste::paste! {
+            $(#[cfg $predicate])?
+            $($attrs)*
+            #[allow(dead_code)]
+            $vis_field fn [<load_ $id>](&self, ordering: core::sync::atomic::Ordering) -> $ty {
+                <$ty as $crate::Atomic>::load(unsafe { $crate::offset!(@atomic_ref core::ptr::addr_of!(self.$id)) }, ordering)
+            }
+
+            $(#[cfg $predicate])?
+            $($attrs)*
+            #[allow(dead_code)]
+            $vis_field fn [<store_ $id>](&self, value: $ty, ordering: core::sync::atomic::Ordering) {
+                <$ty as $crate::Atomic>::store(unsafe { $crate::offset!(@atomic_ref core::ptr::addr_of!(self.$id)) }, value, ordering)
+            }
+        }
+    };
+
+    (@atomic_ref $pointer:expr) => {{
+        let pointer = $pointer;
+        assert!(pointer.is_aligned(), "atomic access to an unaligned field");
+        &*pointer
+    }};
+
+    (@accessor $access:ident [reserved] [$($predicate:tt)?] [$($attrs:tt)*] [$($offset:tt)*] $vis_field:vis $id:ident: $ty:ty) => {};
+
+    (@accessor $access:ident [overlap] [$($predicate:tt)?] [$($attrs:tt)*] [$($offset:tt)*] $vis_field:vis $id:ident: $ty:ty) => {
+        $crate::paste::paste! {
+            $(#[cfg $predicate])?
+            $($attrs)*
+            $vis_field fn $id(&self) -> $ty where for<'copy> $ty: Copy {
+                unsafe { $crate::offset!(@load $access (self as *const Self).cast::<u8>().add($($offset)*).cast::<$ty>()) }
+            }
+
+            $(#[cfg $predicate])?
+            $($attrs)*
+            $vis_field fn [<set_ $id>](&mut self, value: $ty) {
+                unsafe { $crate::offset!(@store $access (self as *mut Self).cast::<u8>().add($($offset)*).cast::<$ty>(), value) }
+            }
+        }
+    };
+
+    (@accessor $access:ident [bits $low:tt $high:tt] [$($predicate:tt)?] [$($attrs:tt)*] [$($offset:tt)*] $vis_field:vis $id:ident: $ty:ty) => {
+        $crate::paste::paste! {
+            $(#[cfg $predicate])?
+            $($attrs)*
+            $vis_field fn $id(&self) -> $ty {
+                const _: () = assert!(
+                    <$ty>::MIN == 0 && $low < $high && $high <= <$ty>::BITS,
+                    concat!("bit range `", stringify!($low), "..", stringify!($high), "` of `", stringify!($id), "` doesn't fit in `", stringify!($ty), "`")
+                );
+                let storage = unsafe { $crate::offset!(@load $access (self as *const Self).cast::<u8>().add($($offset)*).cast::<$ty>()) };
+                (storage >> $low) & (<$ty>::MAX >> (<$ty>::BITS - ($high - $low)))
+            }
+
+            $(#[cfg $predicate])?
+            $($attrs)*
+            $vis_field fn [<set_ $id>](&mut self, value: $ty) {
+                let mask = (<$ty>::MAX >> (<$ty>::BITS - ($high - $low))) << $low;
+                unsafe {
+                    let storage = (self as *mut Self).cast::<u8>().add($($offset)*).cast::<$ty>();
+                    $crate::offset!(@store $access storage, ($crate::offset!(@load $access storage) & !mask) | ((value << $low) & mask));
+                }
+            }
+        }
+    };
+
+    (@accessor $access:ident [stride $elem:tt $count:tt $stride:tt] [$($predicate:tt)?] [$($attrs:tt)*] [$($offset:tt)*] $vis_field:vis $id:ident: $ty:ty) => {
+        $crate::paste::paste! {
+            $(#[cfg $predicate])?
+            $($attrs)*
+            $vis_field fn $id(&self, index: usize) -> $elem where for<'copy> $elem: Copy {
+                const _: () = assert!(
+                    $stride >= core::mem::size_of::<$elem>(),
+                    concat!("stride of `", stringify!($id), "` is smaller than `", stringify!($elem), "`")
+                );
+                assert!(index < $count, "index out of bounds");
+                unsafe { $crate::offset!(@load $access (self as *const Self).cast::<u8>().add(($($offset)*) + index * ($stride)).cast::<$elem>()) }
+            }
+
+            $(#[cfg $predicate])?
+            $($attrs)*
+            $vis_field fn [<set_ $id>](&mut self, index: usize, value: $elem) {
+                assert!(index < $count, "index out of bounds");
+                unsafe { $crate::offset!(@store $access (self as *mut Self).cast::<u8>().add(($($offset)*) + index * ($stride)).cast::<$elem>(), value) }
+            }
+        }
+    };
+
+    // The copy is never dropped, so fields that own resources aren't released by reading them.
+    (@accessor $access:ident [flexible $elem:tt] [$($predicate:tt)?] [$($attrs:tt)*] [$($offset:tt)*] $vis_field:vis $id:ident: $ty:ty) => {
+        $crate::paste::paste! {
+            /// Returns the first `count` elements of the trailing array.
+            ///
+            /// # Safety
+            ///
+            /// The struct must be followed by at least `count` elements in the same allocation.
+            $(#[cfg $predicate])?
+            $($attrs)*
+            $vis_field unsafe fn $id(&self, count: usize) -> *const [$elem] {
+                core::ptr::slice_from_raw_parts((self as *const Self).cast::<u8>().add($($offset)*).cast::<$elem>(), count)
+            }
+
+            /// Returns the first `count` elements of the trailing array for writing.
+            ///
+            /// # Safety
+            ///
+            /// The struct must be followed by at least `count` elements in the same allocation.
+            $(#[cfg $predicate])?
+            $($attrs)*
+            $vis_field unsafe fn [<$id _mut>](&mut self, count: usize) -> *mut [$elem] {
+                core::ptr::slice_from_raw_parts_mut((self as *mut Self).cast::<u8>().add($($offset)*).cast::<$elem>(), count)
+            }
+        }
+    };
+
+    (@load unaligned $pointer:expr) => {
+        core::ptr::read_unaligned($pointer)
+    };
+
+    // Volatile accesses have to be aligned, which the fields of packed structs aren't guaranteed to
+    // be, so the alignment is checked on every access.
+    (@load volatile $pointer:expr) => {{
+        let pointer = $pointer;
+        assert!(pointer.is_aligned(), "volatile access to an unaligned field");
+        core::ptr::read_volatile(pointer)
+    }};
+
+    (@store unaligned $pointer:expr, $value:expr) => {
+        core::ptr::write_unaligned($pointer, $value)
+    };
+
+    (@store volatile $pointer:expr, $value:expr) => {{
+        let pointer = $pointer;
+        assert!(pointer.is_aligned(), "volatile access to an unaligned field");
+        core::ptr::write_volatile(pointer, $value)
+    }};
+
+    (@debug_field $debug:ident $this:ident $truncate:tt [reserved] $predicate:tt $offset:tt $id:ident: $ty:ty) => {};
+
+    (@debug_field $debug:ident $this:ident $truncate:tt [flexible $elem:tt] $predicate:tt $offset:tt $id:ident: $ty:ty) => {};
+
+    (@debug_field $debug:ident $this:ident [truncate] [field] [$($predicate:tt)?] [$($offset:tt)*] $id:ident: $ty:ty) => {
+        $(#[cfg $predicate])?
+        {
+            if core::mem::size_of::<$ty>() > $crate::DebugTruncated::LIMIT {
+                $debug.field(
+                    stringify!($id),
+                    &$crate::DebugTruncated(unsafe {
+                        core::slice::from_raw_parts(($this as *const Self).cast::<u8>().add($($offset)*), core::mem::size_of::<$ty>())
+                    }),
+                );
+            } else {
+                $debug.field(stringify!($id), &$crate::offset!(@read $this [field] $id));
+            }
+        }
+    };
+
+    (@debug_field $debug:ident $this:ident $truncate:tt [$($kind:tt)*] [$($predicate:tt)?] $offset:tt $id:ident: $ty:ty) => {
+        $(#[cfg $predicate])?
+        $debug.field(stringify!($id), &$crate::offset!(@read $this [$($kind)*] $id));
+    };
+
+    // The alternate output shows the offset of every field and its bytes as they are in memory,
+    // except for bitfields and atomic fields, which show the value their getter reads.
+    (@debug_hex $debug:ident $this:ident $truncate:tt [reserved] $predicate:tt $offset:tt $id:ident: $ty:ty) => {};
+
+    (@debug_hex $debug:ident $this:ident $truncate:tt [flexible $elem:tt] $predicate:tt $offset:tt $id:ident: $ty:ty) => {};
+
+    (@debug_hex $debug:ident $this:ident $truncate:tt [bits $low:tt $high:tt] [$($predicate:tt)?] [$($offset:tt)*] $id:ident: $ty:ty) => {
+        $(#[cfg $predicate])?
+        {
+            let value = $this.$id();
+            $debug.entry(
+                &format_args!("{} @ {:#x}:{}..{}", stringify!($id), $($offset)*, $low, $high),
+                &$crate::HexBytes(unsafe { core::slice::from_raw_parts((&value as *const $ty).cast::<u8>(), core::mem::size_of::<$ty>()) }),
+            );
+        }
+    };
+
+    (@debug_hex $debug:ident $this:ident $truncate:tt [atomic] [$($predicate:tt)?] [$($offset:tt)*] $id:ident: $ty:ty) => {
+        $(#[cfg $predicate])?
+        {
+            let value = $crate::offset!(@read $this [atomic] $id);
+            $debug.entry(
+                &format_args!("{} @ {:#x}", stringify!($id), $($offset)*),
+                &$crate::HexBytes(unsafe { core::slice::from_raw_parts((&value as *const $ty).cast::<u8>(), core::mem::size_of::<$ty>()) }),
+            );
+        }
+    };
+
+    (@debug_hex $debug:ident $this:ident $truncate:tt [$($kind:tt)*] [$($predicate:tt)?] [$($offset:tt)*] $id:ident: $ty:ty) => {
+        $(#[cfg $predicate])?
+        $debug.entry(
+            &format_args!("{} @ {:#x}", stringify!($id), $($offset)*),
+            &$crate::offset!(@hex_bytes $truncate unsafe {
+                core::slice::from_raw_parts(($this as *const Self).cast::<u8>().add($($offset)*), $crate::offset!(@field_size [$($kind)*] $ty))
+            }),
+        );
+    };
+
+    (@hex_bytes [] $bytes:expr) => {
+        $crate::HexBytes($bytes)
+    };
+
+    (@hex_bytes [truncate] $bytes:expr) => {
+        $crate::DebugTruncated($bytes)
+    };
+
+    (@read $this:ident [reserved] $id:ident) => {
+        $crate::offset!(@read $this [field] $id)
+    };
+
+    (@read $this:ident [field] $id:ident) => {
+        *core::mem::ManuallyDrop::new(unsafe { core::ptr::read_unaligned(core::ptr::addr_of!($this.$id)) })
+    };
+
+    // An unaligned atomic field can't be accessed atomically anywhere, so it's read like any other
+    // field instead of panicking, e.g. when a copy of the struct is formatted.
+    (@read $this:ident [atomic] $id:ident) => {{
+        let pointer = core::ptr::addr_of!($this.$id);
+        if pointer.is_aligned() {
+            $crate::paste::paste!($this.[<load_ $id>](core::sync::atomic::Ordering::Relaxed))
+        } else {
+            $crate::Atomic::into_inner(unsafe { pointer.read_unaligned() })
+        }
+    }};
+
+    (@read $this:ident [overlap] $id:ident) => {
+        $this.$id()
+    };
+
+    (@read $this:ident [bits $low:tt $high:tt] $id:ident) => {
+        $this.$id()
+    };
+
+    (@read $this:ident [stride $elem:tt $count:tt $stride:tt] $id:ident) => {
+        {
+            let elements: [$elem; $count] = core::array::from_fn(|index| $this.$id(index));
+            elements
+        }
+    };
+
+    (@options [$($option:ident)*] $vis:vis $name:ident $lifetimes:tt $params:tt $fields:tt) => {
+        $($crate::offset!(@$option $vis $name $lifetimes $params $fields);)*
+    };
+
+    // Debug is implemented for the `debug` option, `debug_padding` additionally shows the bytes
+    // that aren't covered by any field, and `debug_truncate` shortens large fields. The options
+    // are collected into flags first, since any of them implies `debug`.
+    (@debug_impl [debug $($option:ident)*] $padding:tt $truncate:tt $debug:tt $($input:tt)*) => {
+        $crate::offset!(@debug_impl [$($option)*] $padding $truncate [debug] $($input)*);
+    };
+
+    (@debug_impl [debug_padding $($option:ident)*] $padding:tt $truncate:tt $debug:tt $($input:tt)*) => {
+        $crate::offset!(@debug_impl [$($option)*] [padding] $truncate [debug] $($input)*);
+    };
+
+    (@debug_impl [debug_truncate $($option:ident)*] $padding:tt $truncate:tt $debug:tt $($input:tt)*) => {
+        $crate::offset!(@debug_impl [$($option)*] $padding [truncate] [debug] $($input)*);
+    };
+
+    (@debug_impl [$other:ident $($option:ident)*] $($input:tt)*) => {
+        $crate::offset!(@debug_impl [$($option)*] $($input)*);
+    };
+
+    (@debug_impl [] $padding:tt $truncate:tt [debug] $($input:tt)*) => {
+        $crate::offset!(@debug_fmt $padding $truncate $($input)*);
+    };
+
+    (@debug_impl [] $padding:tt $truncate:tt [] $($input:tt)*) => {};
+
+    (@debug_fmt [$($padding:ident)?] $truncate:tt $name:ident [$($lifetime:lifetime),*] [$($param:ident $(: $bound:path)?),*] {$(([$($kind:tt)*] [$($predicate:tt)?] $attrs:tt [$($offset:tt)*] $default:tt $vis_field:vis $id:ident: $ty:ty))*}) => {
+        impl<$($lifetime,)* $($param $(: $bound)?),*> core::fmt::Debug for $name<$($lifetime,)* $($param),*> where $($param: core::fmt::Debug),* {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                if f.alternate() {
+                    write!(f, "{} ", stringify!($name))?;
+                    let mut debug = f.debug_map();
+                    $($crate::offset!(@debug_hex debug self $truncate [$($kind)*] [$($predicate)?] [$($offset)*] $id: $ty);)*
+                    $(debug.entry(&format_args!("{}", stringify!($padding)), &$crate::offset!(@padding_bytes self));)?
+                    return debug.finish();
+                }
+
+                let mut debug = f.debug_struct(stringify!($name));
+                $($crate::offset!(@debug_field debug self $truncate [$($kind)*] [$($predicate)?] [$($offset)*] $id: $ty);)*
+                $(debug.field(stringify!($padding), &$crate::offset!(@padding_bytes self));)?
+                debug.finish()
+            }
+        }
+
+    };
+
+    // A name declared twice otherwise only shows up as conflicts between the items generated for
+    // it. Fields whose cfg predicates exclude each other may share a name.
+    (@unique $name:ident {$(($kind:tt [$($predicate:tt)?] $attrs:tt $offset:tt $default:tt $vis_field:vis $id:ident: $ty:ty))*}) => {
+        const _: () = {
+            const COUNT: usize = <[&str]>::len(&[$(stringify!($id)),*]);
+            let mut names: [&str; COUNT] = [""; COUNT];
+            #[allow(unused_mut)]
+            let mut count = 0;
+            $(
+                $(#[cfg $predicate])?
+                {
+                    let mut index = 0;
+                    while index < count {
+                        if $crate::CheckMessage::same(names[index], stringify!($id)) {
+                            panic!(concat!("`", stringify!($name), "::", stringify!($id), "` is declared more than once"));
+                        }
+                        index += 1;
+                    }
+                    names[count] = stringify!($id);
+                    count += 1;
+                }
+            )*
+            let _ = (names, count);
+        };
+    };
+
+    // A field reaching past the total size otherwise only shows up as an overflow in the length of
+    // the remaining padding. The field reaching the furthest is named, so the message tells by how
+    // much the size has to grow.
+    (@fits $header:tt $name:ident [$struct_size:expr] [] {$(([$($kind:tt)*] [$($predicate:tt)?] $attrs:tt [$($offset:tt)*] $default:tt $vis_field:vis $id:ident: $ty:ty))*}) => {
+        const _: () = {
+            let size = $struct_size;
+            let mut last = ("", 0);
+            $(
+                $(#[cfg $predicate])?
+                {
+                    let end = ($($offset)*) + $crate::offset!(@extent $header [$($kind)*] $id: $ty);
+                    if end > last.1 {
+                        last = (stringify!($id), end);
+                    }
+                }
+            )*
+            if last.1 > size {
+                let message = $crate::CheckMessage::EMPTY
+                    .str("the total size ")
+                    .hex(size)
+                    .str(concat!(" of `", stringify!($name), "` is "))
+                    .hex(last.1 - size)
+                    .str(concat!(" bytes short of the end of `", stringify!($name), "::"))
+                    .str(last.0)
+                    .str("` at ")
+                    .hex(last.1);
+                panic!("{}", message.as_str());
+            }
+        };
+    };
+
+    (@fits $header:tt $name:ident $struct_size:tt $params:tt $fields:tt) => {};
+
+    // Fields have to be declared in the order they are placed in. Checking that up front gives a
+    // clearer error than the padding in front of a field declared too late, whose length overflows.
+    // Overlapping fields and bitfields alias other fields, so they can be declared anywhere.
+    (@order $name:ident {$(([$($kind:tt)*] [$($predicate:tt)?] $attrs:tt [$($offset:tt)*] $default:tt $vis_field:vis $id:ident: $ty:ty))*}) => {
+        #[allow(unused_mut, unused_assignments)]
+        const _: () = {
+            let mut previous: Option<(&str, usize)> = None;
+            $(
+                $(#[cfg $predicate])?
+                {
+                    let offset = $($offset)*;
+                    $crate::offset!(@order_check [$($kind)*] $name $id offset previous);
+                }
+            )*
+        };
+    };
+
+    (@order_check [overlap] $($input:tt)*) => {};
+
+    (@order_check [bits $low:tt $high:tt] $($input:tt)*) => {};
+
+    (@order_check $kind:tt $name:ident $id:ident $offset:ident $previous:ident) => {
+        if let Some((previous_id, previous_offset)) = $previous {
+            if $offset < previous_offset {
+                let message = $crate::CheckMessage::EMPTY
+                    .str(concat!("`", stringify!($name), "::", stringify!($id), "` at "))
+                    .hex($offset)
+                    .str(concat!(" is declared after `", stringify!($name), "::"))
+                    .str(previous_id)
+                    .str("` at ")
+                    .hex(previous_offset)
+                    .str(", fields have to be declared in ascending order");
+                panic!("{}", message.as_str());
+            }
+        }
+        $previous = Some((stringify!($id), $offset));
+    };
+
+    (@padding_bytes $this:ident) => {
+        $crate::DebugPadding(
+            unsafe { core::slice::from_raw_parts(($this as *const Self).cast::<u8>(), core::mem::size_of::<Self>()) },
+            Self::FIELDS,
+        )
+    };
+
+    // The values are written with their Debug implementation, since arrays and pointers don't
+    // implement Display.
+    (@display $vis:vis $name:ident [$($lifetime:lifetime),*] [$($param:ident $(: $bound:path)?),*] {$(([$($kind:tt)*] [$($predicate:tt)?] $attrs:tt [$($offset:tt)*] $default:tt $vis_field:vis $id:ident: $ty:ty))*}) => {
+        impl<$($lifetime,)* $($param $(: $bound)?),*> core::fmt::Display for $name<$($lifetime,)* $($param),*> where $($param: core::fmt::Debug),* {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                #[allow(unused_mut, unused_assignments)]
+                let mut separator = "";
+                $($crate::offset!(@display_field f separator self [$($kind)*] [$($predicate)?] $id);)*
+                let _ = separator;
+                Ok(())
+            }
+        }
+    };
+
+    (@display_field $f:ident $separator:ident $this:ident [reserved] $predicate:tt $id:ident) => {};
+
+    (@display_field $f:ident $separator:ident $this:ident [flexible $elem:tt] $predicate:tt $id:ident) => {};
+
+    (@display_field $f:ident $separator:ident $this:ident [$($kind:tt)*] [$($predicate:tt)?] $id:ident) => {
+        $(#[cfg $predicate])?
+        {
+            write!($f, "{}{}={:?}", $separator, stringify!($id), $crate::offset!(@read $this [$($kind)*] $id))?;
+            $separator = " ";
+        }
+    };
+
+    // Only the fields are compared, the padding between them may differ between equal structs.
+    (@partial_eq $vis:vis $name:ident [$($lifetime:lifetime),*] [$($param:ident $(: $bound:path)?),*] {$(([$($kind:tt)*] [$($predicate:tt)?] $attrs:tt [$($offset:tt)*] $default:tt $vis_field:vis $id:ident: $ty:ty))*}) => {
+        impl<$($lifetime,)* $($param $(: $bound)?),*> core::cmp::PartialEq for $name<$($lifetime,)* $($param),*> where $($param: core::cmp::PartialEq),* {
+            fn eq(&self, other: &Self) -> bool {
+                $($crate::offset!(@eq_field self other [$($kind)*] [$($predicate)?] $id);)*
+                true
+            }
+        }
+    };
+
+    (@eq $vis:vis $name:ident [$($lifetime:lifetime),*] [$($param:ident $(: $bound:path)?),*] $fields:tt) => {
+        impl<$($lifetime,)* $($param $(: $bound)?),*> core::cmp::Eq for $name<$($lifetime,)* $($param),*> where $($param: core::cmp::Eq),* {}
+    };
+
+    (@hash $vis:vis $name:ident [$($lifetime:lifetime),*] [$($param:ident $(: $bound:path)?),*] {$(([$($kind:tt)*] [$($predicate:tt)?] $attrs:tt [$($offset:tt)*] $default:tt $vis_field:vis $id:ident: $ty:ty))*}) => {
+        impl<$($lifetime,)* $($param $(: $bound)?),*> core::hash::Hash for $name<$($lifetime,)* $($param),*> where $($param: core::hash::Hash),* {
+            fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+                $($crate::offset!(@hash_field self state [$($kind)*] [$($predicate)?] $id);)*
+            }
+        }
+    };
+
+    (@hash_field $this:ident $state:ident [reserved] $predicate:tt $id:ident) => {};
+
+    (@hash_field $this:ident $state:ident [flexible $elem:tt] $predicate:tt $id:ident) => {};
+
+    (@hash_field $this:ident $state:ident [$($kind:tt)*] [$($predicate:tt)?] $id:ident) => {
+        $(#[cfg $predicate])?
+        core::hash::Hash::hash(&$crate::offset!(@read $this [$($kind)*] $id), $state);
+    };
+
+    (@eq_field $this:ident $other:ident [reserved] $predicate:tt $id:ident) => {};
+
+    (@eq_field $this:ident $other:ident [flexible $elem:tt] $predicate:tt $id:ident) => {};
+
+    (@eq_field $this:ident $other:ident [$($kind:tt)*] [$($predicate:tt)?] $id:ident) => {
+        $(#[cfg $predicate])?
+        if $crate::offset!(@read $this [$($kind)*] $id) != $crate::offset!(@read $other [$($kind)*] $id) {
+            return false;
+        }
+    };
+
+    (@field $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [overlap $($modifier:ident)*] [field] [$($offset:tt)*] $vis_field:vis $id:ident: $ty:ty $(= $default:expr)?; $($next:tt)*) => {
+        $crate::offset!(@field $header ($current_offset) $previous -> {$($body)*} {$($fields)*} [$($predicate)?] [$($attrs)*] [$($modifier)*] [overlap] [$($offset)*] $vis_field $id: $ty $(= $default)?; $($next)*);
+    };
+
+    (@field $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [atomic $($modifier:ident)*] [field] [$($offset:tt)*] $vis_field:vis $id:ident: $ty:ty $(= $default:expr)?; $($next:tt)*) => {
+        $crate::offset!(@field $header ($current_offset) $previous -> {$($body)*} {$($fields)*} [$($predicate)?] [$($attrs)*] [$($modifier)*] [atomic] [$($offset)*] $vis_field $id: $ty $(= $default)?; $($next)*);
+    };
+
+    (@field $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [field] [$($offset:tt)*] $vis_field:vis $id:ident: $ty:ty $(= $default:expr)?; $($next:tt)*) => {
+        $crate::offset!(@after $header $previous [$($offset)*] $id);
+        $crate::offset!(@guard $header ($(if !cfg! $predicate { $current_offset } else)? {
+            ($($offset)*) + $crate::offset!(@size_of $header $id: $ty)
+        }) [[$($predicate)?] [$($modifier)*] [$($offset)*] $id: $ty] -> {
+            $($body)*
+            $(#[cfg $predicate])? [] [<_pad $id>]: [u8; ($($offset)*) - ($current_offset)],
+            $(#[cfg $predicate])? $($attrs)* [$vis_field] $id: $ty,
+        } {$($fields)* ([field] [$($predicate)?] [$($attrs)*] [$($offset)*] [$($default)?] $vis_field $id: $ty)} [] [] [] [] $($next)*);
+    };
+
+    // Atomic fields are stored as the atomic type of the same size, so they can be changed through
+    // shared references.
+    (@field $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [atomic] [$($offset:tt)*] $vis_field:vis $id:ident: $ty:ty $(= $default:expr)?; $($next:tt)*) => {
+        $crate::offset!(@after $header $previous [$($offset)*] $id);
+        $crate::offset!(@guard $header ($(if !cfg! $predicate { $current_offset } else)? {
+            ($($offset)*) + $crate::offset!(@size_of $header $id: $ty)
+        }) [[$($predicate)?] [$($modifier)*] [$($offset)*] $id: $ty] -> {
+            $($body)*
+            $(#[cfg $predicate])? [] [<_pad $id>]: [u8; ($($offset)*) - ($current_offset)],
+            $(#[cfg $predicate])? $($attrs)* [$vis_field] $id: <$ty as $crate::Atomic>::Atomic,
+        } {$($fields)* ([atomic] [$($predicate)?] [$($attrs)*] [$($offset)*] [$($default)?] $vis_field $id: $ty)} [] [] [] [] $($next)*);
+    };
+
+    // Reserved regions are private fields that are left out of the accessors and Debug output.
+    // They are recorded with a `reserved` modifier so the next field is checked against them.
+    (@field $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [reserved] [$($offset:tt)*] $id:ident: $ty:ty; $($next:tt)*) => {
+        $crate::offset!(@after $header $previous [$($offset)*] $id);
+        $crate::offset_checker!(@reserved_start $header [$($predicate)?] ($current_offset) [$($offset)*] $id);
+        $crate::offset!(@guard $header ($(if !cfg! $predicate { $current_offset } else)? {
+            ($($offset)*) + $crate::offset!(@size_of $header $id: $ty)
+        }) [[$($predicate)?] [$($modifier)* reserved] [$($offset)*] $id: $ty] -> {
+            $($body)*
+            $(#[cfg $predicate])? [] [<_pad $id>]: [u8; ($($offset)*) - ($current_offset)],
+            $(#[cfg $predicate])? $($attrs)* [] $id: $ty,
+        } {$($fields)* ([reserved] [$($predicate)?] [$($attrs)*] [$($offset)*] [] $id: $ty)} [] [] [] [] $($next)*);
+    };
+
+    // The length of an `auto` field is a constant that is defined once the offset of the next
+    // field is known, see the `auto` arms of `@after` and `@last_auto`.
+    (@auto {$options:tt $struct_attrs:tt $vis:vis $kind:ident $name:ident $size:tt $lifetimes:tt $params:tt} ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [$($offset:tt)*] $vis_field:vis $id:ident; $($next:tt)*) => {
+        $crate::offset!(@field {$options $struct_attrs $vis $kind $name $size $lifetimes $params} ($current_offset) $previous -> {$($body)*} {$($fields)*} [$($predicate)?] [$($attrs)*] [auto $($modifier)*] [field] [$($offset)*] $vis_field $id: [u8; $crate::paste::paste!([<__ $name _ $id _auto_len>])]; $($next)*);
+    };
+
+    // Overlapping fields may alias the fields around them, so like bitfields they are covered by
+    // padding and only reachable through their accessors.
+    (@field $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [overlap] [$($offset:tt)*] $vis_field:vis $id:ident: $ty:ty $(= $default:expr)?; $($next:tt)*) => {
+        $crate::offset!(@after $header $previous [$($offset)*] $id);
+        $crate::offset!(@guard $header ($(if !cfg! $predicate { $current_offset } else)? {
+            let current = $current_offset;
+            let end = ($($offset)*) + $crate::offset!(@size_of $header $id: $ty);
+            if end > current { end } else { current }
+        }) [[$($predicate)?] [$($modifier)*] [$($offset)*] $id: $ty] -> {
+            $($body)*
+            $(#[cfg $predicate])? [] [<_pad $id>]: [u8; {
+                let current = $current_offset;
+                (($($offset)*) + $crate::offset!(@size_of $header $id: $ty)).saturating_sub(current)
+            }],
+        } {$($fields)* ([overlap] [$($predicate)?] [$($attrs)*] [$($offset)*] [$($default)?] $vis_field $id: $ty)} [] [] [] [] $($next)*);
+    };
+
+    // Bitfields don't get a field of their own, the storage they live in is covered by padding
+    // instead. That way several bitfields can share the same storage offset.
+    (@field $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [bits $low:tt $high:tt] [$($offset:tt)*] $vis_field:vis $id:ident: $ty:ty $(= $default:expr)?; $($next:tt)*) => {
+        $crate::offset!(@after $header $previous [$($offset)*] $id);
+        $crate::offset!(@guard $header ($(if !cfg! $predicate { $current_offset } else)? {
+            let current = $current_offset;
+            let end = ($($offset)*) + core::mem::size_of::<$ty>();
+            if end > current { end } else { current }
+        }) [[$($predicate)?] [$($modifier)*] [$($offset)*] $id: $ty] -> {
+            $($body)*
+            $(#[cfg $predicate])? [] [<_pad $id>]: [u8; {
+                let current = $current_offset;
+                (($($offset)*) + core::mem::size_of::<$ty>()).saturating_sub(current)
+            }],
+        } {$($fields)* ([bits $low $high] [$($predicate)?] [$($attrs)*] [$($offset)*] [$($default)?] $vis_field $id: $ty)} [] [] [] [] $($next)*);
+    };
+
+    // Strided arrays don't follow the natural layout of an array, so like bitfields they are
+    // covered by padding and only reachable through their indexed accessors.
+    (@field $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [stride $elem:tt $count:tt $stride:tt] [$($offset:tt)*] $vis_field:vis $id:ident: $ty:ty; $($next:tt)*) => {
+        $crate::offset!(@after $header $previous [$($offset)*] $id);
+        $crate::offset!(@guard $header ($(if !cfg! $predicate { $current_offset } else)? {
+            ($($offset)*) + ($count - 1) * ($stride) + core::mem::size_of::<$elem>()
+        }) [[$($predicate)?] [$($modifier)*] [$($offset)*] $id: $ty] -> {
+            $($body)*
+            $(#[cfg $predicate])? [] [<_pad $id>]: [u8; (($($offset)*) - ($current_offset)) + ($count - 1) * ($stride) + core::mem::size_of::<$elem>()],
+        } {$($fields)* ([stride $elem $count $stride] [$($predicate)?] [$($attrs)*] [$($offset)*] [] $vis_field $id: $ty)} [] [] [] [] $($next)*);
+    };
+
+    // A flexible array takes up no space in the struct, the struct ends where the array begins.
+    (@field $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [flexible $elem:tt] [$($offset:tt)*] $vis_field:vis $id:ident: $ty:ty;) => {
+        $crate::offset!(@after $header $previous [$($offset)*] $id);
+        $crate::offset!(@guard $header ($(if !cfg! $predicate { $current_offset } else)? {
+            $($offset)*
+        }) [[$($predicate)?] [$($modifier)*] [$($offset)*] $id: $ty] -> {
+            $($body)*
+            $(#[cfg $predicate])? [] [<_pad $id>]: [u8; ($($offset)*) - ($current_offset)],
+        } {$($fields)* ([flexible $elem] [$($predicate)?] [$($attrs)*] [$($offset)*] [] $vis_field $id: $ty)} [] [] [] []);
+    };
+
+    // The number of bytes a field spans from its offset.
+    (@extent $header:tt [bits $low:tt $high:tt] $id:ident: $ty:ty) => {
+        core::mem::size_of::<$ty>()
+    };
+
+    (@extent $header:tt [stride $elem:tt $count:tt $stride:tt] $id:ident: $ty:ty) => {
+        ($count - 1) * ($stride) + core::mem::size_of::<$elem>()
+    };
+
+    (@extent $header:tt [flexible $elem:tt] $id:ident: $ty:ty) => {
+        0
+    };
+
+    (@extent $header:tt $kind:tt $id:ident: $ty:ty) => {
+        $crate::offset!(@size_of $header $id: $ty)
+    };
+
+    // Lifetimes can't be used in constants, so the size of a field of a struct with lifetimes is
+    // taken by a function that is generic over them, letting the call infer the lifetimes instead.
+    (@size_of {$options:tt $attrs:tt $vis:vis $kind:ident $name:ident $size:tt [] $params:tt} $id:ident: $ty:ty) => {
+        core::mem::size_of::<$ty>()
+    };
+
+    (@size_of {$options:tt $attrs:tt $vis:vis $kind:ident $name:ident $size:tt [$($lifetime:lifetime),+] $params:tt} $id:ident: $ty:ty) => {
+        $crate::paste::paste!([<__size_of_ $name _ $id>]())
+    };
+
+    (@size_fn {$options:tt $attrs:tt $vis:vis $kind:ident $name:ident $size:tt [] $params:tt} $predicate:tt $id:ident: $ty:ty) => {};
+
+    (@size_fn {$options:tt $attrs:tt $vis:vis $kind:ident $name:ident $size:tt [$($lifetime:lifetime),+] $params:tt} [$($predicate:tt)?] $id:ident: $ty:ty) => {
+        $crate::paste::paste! {
+            $(#[cfg $predicate])?
+            #[doc(hidden)]
+            #[allow(non_snake_case)]
+            const fn [<__size_of_ $name _ $id>]<$($lifetime),+>() -> usize {
+                core::mem::size_of::<$ty>()
+            }
+
+            // The offsets following a conditional field still name its size when it's disabled.
+            $(
+                #[cfg(not $predicate)]
+                #[doc(hidden)]
+                #[allow(non_snake_case)]
+                const fn [<__size_of_ $name _ $id>]() -> usize {
+                    0
+                }
+            )?
+        }
+    };
+
+    (@auto_len {$options:tt $attrs:tt $vis:vis $kind:ident $name:ident $size:tt $lifetimes:tt $params:tt} [$($predicate:tt)?] $id:ident ($length:expr)) => {
+        $crate::paste::paste! {
+            $(#[cfg $predicate])?
+            #[doc(hidden)]
+            #[allow(non_upper_case_globals)]
+            const [<__ $name _ $id _auto_len>]: usize = $length;
+
+            $(
+                #[cfg(not $predicate)]
+                #[doc(hidden)]
+                #[allow(non_upper_case_globals)]
+                const [<__ $name _ $id _auto_len>]: usize = 0;
+            )?
+        }
+    };
+
+    // An `auto` field at the end of the struct spans until the total size.
+    (@last_auto {$options:tt $attrs:tt $vis:vis $kind:ident $name:ident [$struct_size:expr] $lifetimes:tt $params:tt} [$predicate:tt [auto $($modifier:ident)*] [$($offset:tt)*] $id:ident: $ty:ty]) => {
+        $crate::offset!(@auto_len {$options $attrs $vis $kind $name [$struct_size] $lifetimes $params} $predicate $id (($struct_size) - ($($offset)*)));
+    };
+
+    (@last_auto {$options:tt $attrs:tt $vis:vis $kind:ident $name:ident [] $lifetimes:tt $params:tt} [$predicate:tt [auto $($modifier:ident)*] $offset:tt $id:ident: $ty:ty]) => {
+        compile_error!(concat!("`", stringify!($id), "` is an `auto` field, so it has to be followed by another field or a total size"));
+    };
+
+    (@last_auto $header:tt $previous:tt) => {};
+
+    // The size of the last field is needed to pad the struct to its total size, or for the size of
+    // the struct in `as_bytes` otherwise. Neither applies to structs with type parameters.
+    (@last_size_fn {$options:tt $attrs:tt $vis:vis $kind:ident $name:ident $size:tt $lifetimes:tt []} [$predicate:tt $modifiers:tt $offset:tt $id:ident: $ty:ty]) => {
+        $crate::offset!(@size_fn {$options $attrs $vis $kind $name $size $lifetimes []} $predicate $id: $ty);
+    };
+
+    (@last_size_fn $header:tt $previous:tt) => {};
+
+    // Runs the checks the modifiers of the previous field need the offset of the next field for.
+    (@after $header:tt [] [$($offset:tt)*] $id:ident) => {};
+
+    (@after $header:tt [[$($predicate:tt)?] [auto $($modifier:ident)*] [$($previous_offset:tt)*] $previous_id:ident: $previous_ty:ty] [$($offset:tt)*] $id:ident) => {
+        $crate::offset!(@auto_len $header [$($predicate)?] $previous_id (($($offset)*) - ($($previous_offset)*)));
+        $crate::offset!(@after $header [[$($predicate)?] [$($modifier)*] [$($previous_offset)*] $previous_id: $previous_ty] [$($offset)*] $id);
+    };
+
+    (@after $header:tt [$predicate:tt [$($modifier:ident)*] $previous_offset:tt $previous_id:ident: $previous_ty:ty] $offset:tt $id:ident) => {
+        $crate::offset!(@size_fn $header $predicate $previous_id: $previous_ty);
+        $($crate::offset_checker!(@$modifier $header $predicate $previous_offset $previous_id: $previous_ty, $offset $id);)*
+    };
+
+    (@guard $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [] [] [] []) => {
+        $crate::offset!(@last_auto $header $previous);
+        $crate::offset!(@last_size_fn $header $previous);
+        $crate::offset!(@emit $header ($current_offset) -> {$($body)*} {$($fields)*});
+    };
+
+    // Every `#[cfg]` of a field is folded into a single predicate, which is used both for the
+    // field itself and to pick the end of the previous field when computing the next padding.
+    (@guard $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [] [$($attrs:tt)*] [] [] #[cfg $predicate:tt] $($next:tt)*) => {
+        $crate::offset!(@guard $header ($current_offset) $previous -> {$($body)*} {$($fields)*} [$predicate] [$($attrs)*] [] [] $($next)*);
+    };
+
+    (@guard $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [($($predicate:tt)*)] [$($attrs:tt)*] [] [] #[cfg($($next_predicate:tt)*)] $($next:tt)*) => {
+        $crate::offset!(@guard $header ($current_offset) $previous -> {$($body)*} {$($fields)*} [(all($($predicate)*, $($next_predicate)*))] [$($attrs)*] [] [] $($next)*);
+    };
+
+    // Invariants checked by `validate` are kept with the other attributes, behind a `cfg_attr`
+    // that never holds so they vanish from the field and its accessors.
+    (@guard $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [] [] #[validate($($invariant:tt)*)] $($next:tt)*) => {
+        $crate::offset!(@guard $header ($current_offset) $previous -> {$($body)*} {$($fields)*} [$($predicate)?] [$($attrs)* #[cfg_attr(any(), validate($($invariant)*))]] [] [] $($next)*);
+    };
+
+    // Any other attribute, including doc comments, ends up on the field and its accessors.
+    (@guard $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [] [] #[$($attr:tt)*] $($next:tt)*) => {
+        $crate::offset!(@guard $header ($current_offset) $previous -> {$($body)*} {$($fields)*} [$($predicate)?] [$($attrs)* #[$($attr)*]] [] [] $($next)*);
+    };
+
+    (@guard $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [] embed $($next:tt)*) => {
+        $crate::offset!(@guard $header ($current_offset) $previous -> {$($body)*} {$($fields)*} [$($predicate)?] [$($attrs)*] [$($modifier)* embed] [] $($next)*);
+    };
+
+    // `overlap` and `atomic` are kept in front of the other modifiers, where `@field` looks for them.
+    (@guard $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [] overlap $($next:tt)*) => {
+        $crate::offset!(@guard $header ($current_offset) $previous -> {$($body)*} {$($fields)*} [$($predicate)?] [$($attrs)*] [overlap $($modifier)*] [] $($next)*);
+    };
+
+    (@guard $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [] atomic $($next:tt)*) => {
+        $crate::offset!(@guard $header ($current_offset) $previous -> {$($body)*} {$($fields)*} [$($predicate)?] [$($attrs)*] [atomic $($modifier)*] [] $($next)*);
+    };
+
+    // A relative offset starts at the end of the previous fields.
+    (@guard $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [] + $($next:tt)*) => {
+        $crate::offset!(@guard $header ($current_offset) $previous -> {$($body)*} {$($fields)*} [$($predicate)?] [$($attrs)*] [$($modifier)*] [($current_offset) +] $($next)*);
+    };
+
+    // Offset alternatives like `{ win10: 0x550, win11: 0x558 }` pick the first offset whose cfg
+    // predicate holds. This has to happen before the braces are mistaken for a plain offset.
+    (@guard $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [$($offset:tt)*] {$($alternative:meta: $alternative_offset:expr),+ $(,)?} $($next:tt)*) => {
+        $crate::offset!(@guard $header ($current_offset) $previous -> {$($body)*} {$($fields)*} [$($predicate)?] [$($attrs)*] [$($modifier)*] [$($offset)*] ({
+            $(if cfg!($alternative) { $alternative_offset } else)+ {
+                panic!("{}", concat!("none of the offsets in `", stringify!({$($alternative: $alternative_offset),+}), "` is enabled"))
+            }
+        }) $($next)*);
+    };
+
+    // An offset taken from an environment variable at build time, e.g. `env("EPROCESS_PEB")`, with
+    // an optional fallback for when it isn't set. It's replaced by the expression parsing it, which
+    // is then collected like any other offset.
+    (@guard $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [$($offset:tt)*] env($variable:literal $(,)?) $($next:tt)*) => {
+        $crate::offset!(@guard $header ($current_offset) $previous -> {$($body)*} {$($fields)*} [$($predicate)?] [$($attrs)*] [$($modifier)*] [$($offset)*] ($crate::env_offset($variable, env!($variable))) $($next)*);
+    };
+
+    (@guard $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [$($offset:tt)*] env($variable:literal, $fallback:expr $(,)?) $($next:tt)*) => {
+        $crate::offset!(@guard $header ($current_offset) $previous -> {$($body)*} {$($fields)*} [$($predicate)?] [$($attrs)*] [$($modifier)*] [$($offset)*] (match option_env!($variable) {
+            Some(value) => $crate::env_offset($variable, value),
+            None => $fallback,
+        }) $($next)*);
+    };
+
+    // A trailing `[T; ...]` array has no length of its own. These arms come before any arm that
+    // would try to parse it as a type, which would be a hard error.
+    (@guard $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [] $($path:ident)::+ pub $(($($restriction:tt)*))? $id:ident: [$elem:ty; ...] $(,)?) => {
+        $crate::offset!(@field $header ($current_offset) $previous -> {$($body)*} {$($fields)*} [$($predicate)?] [$($attrs)*] [$($modifier)*] [flexible $elem] [$($path)::+] pub $(($($restriction)*))? $id: [$elem; 0];);
+    };
+
+    (@guard $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [] $($path:ident)::+ $id:ident: [$elem:ty; ...] $(,)?) => {
+        $crate::offset!(@field $header ($current_offset) $previous -> {$($body)*} {$($fields)*} [$($predicate)?] [$($attrs)*] [$($modifier)*] [flexible $elem] [$($path)::+] $id: [$elem; 0];);
+    };
+
+    (@guard $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [$($offset:tt)*] $last:tt $vis_field:vis $id:ident: [$elem:ty; ...] $(,)?) => {
+        $crate::offset!(@field $header ($current_offset) $previous -> {$($body)*} {$($fields)*} [$($predicate)?] [$($attrs)*] [$($modifier)*] [flexible $elem] [$($offset)* $last] $vis_field $id: [$elem; 0];);
+    };
+
+    // An `auto` field is a byte array spanning until the next field, so it can't be parsed as the
+    // type it looks like either.
+    (@guard $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [] $($path:ident)::+ pub $(($($restriction:tt)*))? $id:ident: auto $(, $($next:tt)*)?) => {
+        $crate::offset!(@auto $header ($current_offset) $previous -> {$($body)*} {$($fields)*} [$($predicate)?] [$($attrs)*] [$($modifier)*] [$($path)::+] pub $(($($restriction)*))? $id; $($($next)*)?);
+    };
+
+    (@guard $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [] $($path:ident)::+ $id:ident: auto $(, $($next:tt)*)?) => {
+        $crate::offset!(@auto $header ($current_offset) $previous -> {$($body)*} {$($fields)*} [$($predicate)?] [$($attrs)*] [$($modifier)*] [$($path)::+] $id; $($($next)*)?);
+    };
+
+    (@guard $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [$($offset:tt)*] $last:tt $vis_field:vis $id:ident: auto $(, $($next:tt)*)?) => {
+        $crate::offset!(@auto $header ($current_offset) $previous -> {$($body)*} {$($fields)*} [$($predicate)?] [$($attrs)*] [$($modifier)*] [$($offset)* $last] $vis_field $id; $($($next)*)?);
+    };
+
+    // A `flags` field declares its flags type in place, which is generated next to the struct
+    // with the visibility of the field, and the field then has that type.
+    (@guard $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [] $($path:ident)::+ pub $(($($restriction:tt)*))? $id:ident: flags $flags:ident: $repr:ty {$($flag:tt)*} $(, $($next:tt)*)?) => {
+        $crate::offset!(@flags pub $(($($restriction)*))? $flags: $repr {$($flag)*});
+        $crate::offset!(@field $header ($current_offset) $previous -> {$($body)*} {$($fields)*} [$($predicate)?] [$($attrs)*] [$($modifier)*] [field] [$($path)::+] pub $(($($restriction)*))? $id: $flags; $($($next)*)?);
+    };
+
+    (@guard $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [] $($path:ident)::+ $id:ident: flags $flags:ident: $repr:ty {$($flag:tt)*} $(, $($next:tt)*)?) => {
+        $crate::offset!(@flags $flags: $repr {$($flag)*});
+        $crate::offset!(@field $header ($current_offset) $previous -> {$($body)*} {$($fields)*} [$($predicate)?] [$($attrs)*] [$($modifier)*] [field] [$($path)::+] $id: $flags; $($($next)*)?);
+    };
+
+    (@guard $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [$($offset:tt)*] $last:tt $vis_field:vis $id:ident: flags $flags:ident: $repr:ty {$($flag:tt)*} $(, $($next:tt)*)?) => {
+        $crate::offset!(@flags $vis_field $flags: $repr {$($flag)*});
+        $crate::offset!(@field $header ($current_offset) $previous -> {$($body)*} {$($fields)*} [$($predicate)?] [$($attrs)*] [$($modifier)*] [field] [$($offset)* $last] $vis_field $id: $flags; $($($next)*)?);
+    };
+
+    // Offsets taken from named constants, e.g. `offsets::EPROCESS_PEB`, are matched in one step.
+    // The visibility is spelled out since a `vis` fragment right after the path would be ambiguous.
+    (@guard $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [] $($path:ident)::+ pub $(($($restriction:tt)*))? $id:ident: $ty:ty $(= $default:expr)? $(, $($next:tt)*)?) => {
+        $crate::offset!(@field $header ($current_offset) $previous -> {$($body)*} {$($fields)*} [$($predicate)?] [$($attrs)*] [$($modifier)*] [field] [$($path)::+] pub $(($($restriction)*))? $id: $ty $(= $default)?; $($($next)*)?);
+    };
+
+    (@guard $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [] $($path:ident)::+ $id:ident: $ty:ty $(= $default:expr)? $(, $($next:tt)*)?) => {
+        $crate::offset!(@field $header ($current_offset) $previous -> {$($body)*} {$($fields)*} [$($predicate)?] [$($attrs)*] [$($modifier)*] [field] [$($path)::+] $id: $ty $(= $default)?; $($($next)*)?);
+    };
+
+    (@guard $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [$($offset:tt)*] $last:tt reserved $id:ident: $ty:ty $(, $($next:tt)*)?) => {
+        $crate::offset!(@field $header ($current_offset) $previous -> {$($body)*} {$($fields)*} [$($predicate)?] [$($attrs)*] [$($modifier)*] [reserved] [$($offset)* $last] $id: $ty; $($($next)*)?);
+    };
+
+    (@guard $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [$($offset:tt)*] $last:tt $vis_field:vis $id:ident: [$elem:ty; $count:expr] stride $stride:expr $(, $($next:tt)*)?) => {
+        $crate::offset!(@field $header ($current_offset) $previous -> {$($body)*} {$($fields)*} [$($predicate)?] [$($attrs)*] [$($modifier)*] [stride $elem $count $stride] [$($offset)* $last] $vis_field $id: [$elem; $count]; $($($next)*)?);
+    };
+
+    // A size in brackets after the type is the size the type is expected to have.
+    (@guard $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [$($offset:tt)*] $last:tt $vis_field:vis $id:ident: $ty:ty [$size:expr] $(= $default:expr)? $(, $($next:tt)*)?) => {
+        $crate::offset_checker!(@size $header [$($predicate)?] $id: $ty, $size);
+        $crate::offset!(@field $header ($current_offset) $previous -> {$($body)*} {$($fields)*} [$($predicate)?] [$($attrs)*] [$($modifier)*] [field] [$($offset)* $last] $vis_field $id: $ty $(= $default)?; $($($next)*)?);
+    };
+
+    (@guard $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [$($offset:tt)*] $last:tt $vis_field:vis $id:ident: $ty:ty $(= $default:expr)? $(, $($next:tt)*)?) => {
+        $crate::offset!(@field $header ($current_offset) $previous -> {$($body)*} {$($fields)*} [$($predicate)?] [$($attrs)*] [$($modifier)*] [field] [$($offset)* $last] $vis_field $id: $ty $(= $default)?; $($($next)*)?);
+    };
+
+    (@guard $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [$($offset:tt)*] $last:tt : $low:tt .. $high:tt $vis_field:vis $id:ident: $ty:ty $(= $default:expr)? $(, $($next:tt)*)?) => {
+        $crate::offset!(@field $header ($current_offset) $previous -> {$($body)*} {$($fields)*} [$($predicate)?] [$($attrs)*] [$($modifier)*] [bits $low $high] [$($offset)* $last] $vis_field $id: $ty $(= $default)?; $($($next)*)?);
+    };
+
+    // In the layouts of a dual struct, `a/b` picks the side for the pointer width being generated.
+    (@guard {[ptr32 $($option:ident)*] $($header:tt)*} ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [$($offset:tt)*] $left:tt / $right:tt $($next:tt)*) => {
+        $crate::offset!(@guard {[ptr32 $($option)*] $($header)*} ($current_offset) $previous -> {$($body)*} {$($fields)*} [$($predicate)?] [$($attrs)*] [$($modifier)*] [$($offset)*] $left $($next)*);
+    };
+
+    (@guard {[ptr64 $($option:ident)*] $($header:tt)*} ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [$($offset:tt)*] $left:tt / $right:tt $($next:tt)*) => {
+        $crate::offset!(@guard {[ptr64 $($option)*] $($header)*} ($current_offset) $previous -> {$($body)*} {$($fields)*} [$($predicate)?] [$($attrs)*] [$($modifier)*] [$($offset)*] $right $($next)*);
+    };
+
+    // An offset expression can't be captured as `$offset:expr` since fragments of that kind may
+    // not be followed by a visibility, so tokens are collected until the field declaration itself.
+    (@guard $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [$($offset:tt)*] $token:tt $($next:tt)*) => {
+        $crate::offset!(@guard $header ($current_offset) $previous -> {$($body)*} {$($fields)*} [$($predicate)?] [$($attrs)*] [$($modifier)*] [$($offset)* $token] $($next)*);
+    };
+
+    (@guard $header:tt ($current_offset:expr) $previous:tt -> {$($body:tt)*} {$($fields:tt)*} [$($predicate:tt)?] [$($attrs:tt)*] [$($modifier:ident)*] [$($offset:tt)*]) => {
+        compile_error!(concat!("expected a field declaration after `", stringify!($($modifier)* $($offset)*), "`"));
+    };
+
+    // `#[offset(...)]` attributes on the struct are options of the macro rather than attributes of
+    // the generated struct, so they are sorted out before the fields are munched.
+    (@attributes [$($option:ident)*] [$($attr:tt)*] [$($dual:ident)?] [#[offset(dual $(, $($more:tt)*)?)] $($rest:tt)*] $struct:tt $($input:tt)*) => {
+        $crate::offset!(@attributes [$($option)*] [$($attr)*] [dual] [#[offset($($($more)*)?)] $($rest)*] $struct $($input)*);
+    };
+
+    (@attributes [$($option:ident)*] [$($attr:tt)*] [$($dual:ident)?] [#[offset(wow64 $(, $($more:tt)*)?)] $($rest:tt)*] $struct:tt $($input:tt)*) => {
+        $crate::offset!(@attributes [$($option)*] [$($attr)*] [wow64] [#[offset($($($more)*)?)] $($rest)*] $struct $($input)*);
+    };
+
+    (@attributes [$($option:ident)*] [$($attr:tt)*] $dual:tt [#[offset($next_option:ident $(, $($more:tt)*)?)] $($rest:tt)*] $struct:tt $($input:tt)*) => {
+        $crate::offset!(@attributes [$($option)* $next_option] [$($attr)*] $dual [#[offset($($($more)*)?)] $($rest)*] $struct $($input)*);
+    };
+
+    (@attributes [$($option:ident)*] [$($attr:tt)*] $dual:tt [#[offset()] $($rest:tt)*] $struct:tt $($input:tt)*) => {
+        $crate::offset!(@attributes [$($option)*] [$($attr)*] $dual [$($rest)*] $struct $($input)*);
+    };
+
+    (@attributes [$($option:ident)*] [$($attr:tt)*] $dual:tt [#[$($next_attr:tt)*] $($rest:tt)*] $struct:tt $($input:tt)*) => {
+        $crate::offset!(@attributes [$($option)*] [$($attr)* #[$($next_attr)*]] $dual [$($rest)*] $struct $($input)*);
+    };
+
+    (@attributes [$($option:ident)*] [$($attr:tt)*] [] [] {$vis:vis struct $name:ident $size:tt $lifetimes:tt $params:tt $base:tt} $($input:tt)*) => {
+        $crate::offset!(@start {[$($option)*] [$($attr)*] $vis struct $name $size $lifetimes $params} $base $($input)*);
+    };
+
+    (@attributes $options:tt $attrs:tt [$dual:ident] [] {$vis:vis struct $name:ident [$size32:tt / $size64:tt] $lifetimes:tt $params:tt $base:tt} $($input:tt)*) => {
+        $crate::offset!(@dual ptr32 32 u32 $options $attrs {$vis struct $name [$size32] $lifetimes $params $base} $($input)*);
+        $crate::offset!(@dual ptr64 64 u64 $options $attrs {$vis struct $name [$size64] $lifetimes $params $base} $($input)*);
+        $crate::offset!(@dual_convert $dual $name $lifetimes $params);
+    };
+
+    (@attributes $options:tt $attrs:tt [$dual:ident] [] {$vis:vis struct $name:ident $size:tt $lifetimes:tt $params:tt $base:tt} $($input:tt)*) => {
+        $crate::offset!(@dual ptr32 32 u32 $options $attrs {$vis struct $name $size $lifetimes $params $base} $($input)*);
+        $crate::offset!(@dual ptr64 64 u64 $options $attrs {$vis struct $name $size $lifetimes $params $base} $($input)*);
+        $crate::offset!(@dual_convert $dual $name $lifetimes $params);
+    };
+
+    // The flags type of a `flags` field keeps bits that aren't declared, since the flags of
+    // reversed structures are rarely all known.
+    (@flags $vis:vis $flags:ident: $repr:ty {$($(#[$flag_attr:meta])* $flag:ident = $value:expr),* $(,)?}) => {
+        #[doc = concat!("The flags of a `", stringify!($flags), "` field.")]
+        #[repr(transparent)]
+        #[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+        $vis struct $flags($repr);
+
+        #[allow(dead_code)]
+        impl $flags {
+            $(
+                $(#[$flag_attr])*
+                pub const $flag: Self = Self($value);
+            )*
+
+            /// No flags set.
+            pub const fn empty() -> Self {
+                Self(0)
+            }
+
+            /// All declared flags set.
+            pub const fn all() -> Self {
+                Self(0 $(| $value)*)
+            }
+
+            /// The flags from `bits`, keeping bits that aren't declared.
+            pub const fn from_bits_retain(bits: $repr) -> Self {
+                Self(bits)
+            }
+
+            /// The flags from `bits`, or `None` if a bit isn't declared.
+            pub const fn from_bits(bits: $repr) -> Option<Self> {
+                if bits & !Self::all().0 == 0 {
+                    Some(Self(bits))
+                } else {
+                    None
+                }
+            }
+
+            /// The bits of the flags.
+            pub const fn bits(&self) -> $repr {
+                self.0
+            }
+
+            /// Whether no bit is set.
+            pub const fn is_empty(&self) -> bool {
+                self.0 == 0
+            }
+
+            /// Whether all flags of `other` are set.
+            pub const fn contains(&self, other: Self) -> bool {
+                self.0 & other.0 == other.0
+            }
+
+            /// Whether any flag of `other` is set.
+            pub const fn intersects(&self, other: Self) -> bool {
+                self.0 & other.0 != 0
+            }
+
+            /// Sets the flags of `other`.
+            pub fn insert(&mut self, other: Self) {
+                self.0 |= other.0;
+            }
+
+            /// Clears the flags of `other`.
+            pub fn remove(&mut self, other: Self) {
+                self.0 &= !other.0;
+            }
+
+            /// Sets or clears the flags of `other`.
+            pub fn set(&mut self, other: Self, value: bool) {
+                if value {
+                    self.insert(other);
+                } else {
+                    self.remove(other);
+                }
+            }
+        }
+
+        unsafe impl $crate::FfiSafe for $flags {}
+
+        impl core::ops::BitOr for $flags {
+            type Output = Self;
+
+            fn bitor(self, other: Self) -> Self {
+                Self(self.0 | other.0)
+            }
+        }
+
+        impl core::ops::BitAnd for $flags {
+            type Output = Self;
+
+            fn bitand(self, other: Self) -> Self {
+                Self(self.0 & other.0)
+            }
+        }
+
+        impl core::ops::BitXor for $flags {
+            type Output = Self;
+
+            fn bitxor(self, other: Self) -> Self {
+                Self(self.0 ^ other.0)
+            }
+        }
+
+        impl core::ops::Sub for $flags {
+            type Output = Self;
+
+            fn sub(self, other: Self) -> Self {
+                Self(self.0 & !other.0)
+            }
+        }
+
+        impl core::ops::Not for $flags {
+            type Output = Self;
+
+            fn not(self) -> Self {
+                Self(!self.0)
+            }
+        }
+
+        impl core::ops::BitOrAssign for $flags {
+            fn bitor_assign(&mut self, other: Self) {
+                self.0 |= other.0;
+            }
+        }
+
+        impl core::ops::BitAndAssign for $flags {
+            fn bitand_assign(&mut self, other: Self) {
+                self.0 &= other.0;
+            }
+        }
+
+        impl core::ops::SubAssign for $flags {
+            fn sub_assign(&mut self, other: Self) {
+                self.0 &= !other.0;
+            }
+        }
+
+        // The set flags are listed by name, followed by the bits that aren't declared in hex.
+        impl core::fmt::Debug for $flags {
+            #[allow(unused_assignments)]
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                write!(f, "{}(", stringify!($flags))?;
+                let mut remaining = self.0;
+                let mut separator = "";
+                $(
+                    if Self::$flag.0 != 0 && self.contains(Self::$flag) {
+                        write!(f, "{}{}", separator, stringify!($flag))?;
+                        remaining &= !Self::$flag.0;
+                        separator = " | ";
+                    }
+                )*
+                if remaining != 0 || separator.is_empty() {
+                    write!(f, "{}{:#x}", separator, remaining)?;
+                }
+                f.write_str(")")
+            }
+        }
+    };
+
+    // Each layout of a dual struct lives in its own module, so `Ptr` can mean a different type in
+    // each of them. The width is kept in front of the options, where `@guard` looks for it.
+    (@dual $width:ident $bits:tt $ptr:ty [$($option:ident)*] [$($attr:tt)*] {$vis:vis struct $name:ident $size:tt $lifetimes:tt $params:tt $base:tt} $($input:tt)*) => {
+        $crate::paste::paste! {
+            #[doc(hidden)]
+            #[allow(non_snake_case)]
+            mod [<__ $name $bits>] {
+                #[allow(unused_imports)]
+                use super::*;
+
+                /// Integer as large as a pointer of this layout.
+                pub type Ptr = $ptr;
+
+                $crate::offset!(@start {[$width $($option)*] [$($attr)*] pub struct [<$name $bits>] $size $lifetimes $params} $base $($input)*);
+            }
+
+            #[allow(unused_imports)]
+            $vis use [<__ $name $bits>]::[<$name $bits>];
+
+            $crate::offset!(@dual_reexport $vis [<__ $name $bits>] [<$name $bits>] [$($option)*]);
+        }
+    };
+
+    // With `wow64`, the 32-bit layout converts from and to the 64-bit one. Only fields of type
+    // `Ptr` and arrays of them may differ in size, which is checked up front.
+    (@dual_convert dual $($input:tt)*) => {};
+
+    (@dual_convert wow64 $name:ident [] []) => {
+        $crate::paste::paste! {
+            const _: () = if let Some(field) = $crate::dual_mismatch(&[<$name 32>]::LAYOUT, &[<$name 64>]::LAYOUT) {
+                let message = $crate::CheckMessage::EMPTY
+                    .str(concat!("`", stringify!($name), "::"))
+                    .str(field)
+                    .str("` differs in size between the layouts, only `Ptr` fields and arrays of them can be converted");
+                panic!("{}", message.as_str());
+            };
+
+            impl From<&[<$name 32>]> for [<$name 64>] {
+                /// Converts to the 64-bit layout, zero-extending the pointers.
+                fn from(value: &[<$name 32>]) -> Self {
+                    match unsafe { $crate::convert_dual(value, &[<$name 32>]::LAYOUT, &Self::LAYOUT) } {
+                        Ok(value) => value,
+                        Err(_) => unreachable!(),
+                    }
+                }
+            }
+
+            impl TryFrom<&[<$name 64>]> for [<$name 32>] {
+                type Error = core::num::TryFromIntError;
+
+                /// Converts to the 32-bit layout, failing if a pointer doesn't fit into 32 bits.
+                fn try_from(value: &[<$name 64>]) -> Result<Self, Self::Error> {
+                    unsafe { $crate::convert_dual(value, &[<$name 64>]::LAYOUT, &Self::LAYOUT) }
+                }
+            }
+        }
+    };
+
+    (@dual_convert wow64 $name:ident $lifetimes:tt $params:tt) => {
+        compile_error!(concat!("`", stringify!($name), "` can't convert between its layouts with generic parameters, use `#[offset(dual)]`"));
+    };
+
+    // Types generated next to the struct by its options are reexported along with it.
+    (@dual_reexport $vis:vis $module:ident $name:ident [remote $($option:ident)*]) => {
+        $crate::paste::paste! {
+            #[allow(unused_imports)]
+            $vis use $module::[<$name Remote>];
+        }
+    };
+
+    (@dual_reexport $vis:vis $module:ident $name:ident [$other:ident $($option:ident)*]) => {
+        $crate::offset!(@dual_reexport $vis $module $name [$($option)*]);
+    };
+
+    (@dual_reexport $vis:vis $module:ident $name:ident []) => {};
+
+    // The elements of a tuple struct are named `_0`, `_1` and so on, and then declared like any
+    // other field. Tokens are collected until they end in a visibility and a type, where the
+    // special kinds of fields have to be matched before their types are parsed.
+    // The names of the elements are made up by the macro, so an invariant couldn't refer to them.
+    (@tuple $names:tt $element:tt $fields:tt $prefix:tt #[validate $($invariant:tt)*] $($next:tt)*) => {
+        compile_error!("the elements of tuple structs can't have invariants, since they have no names to refer to");
+    };
+
+    // Attributes are moved over as a whole, their contents could be mistaken for a type.
+    (@tuple $names:tt [$($element:tt)*] $fields:tt $prefix:tt #[$($attr:tt)*] $($next:tt)*) => {
+        $crate::offset!(@tuple $names [$($element)* #[$($attr)*]] $fields $prefix $($next)*);
+    };
+
+    (@tuple [$name:ident $($names:ident)*] [$($element:tt)*] [$($fields:tt)*] $prefix:tt $last:tt $vis_field:vis [$elem:ty; ...] $(,)?) => {
+        $crate::offset!(@tuple [$($names)*] [] [$($fields)* $($element)* $last $vis_field $name: [$elem; ...]] $prefix);
+    };
+
+    (@tuple [$name:ident $($names:ident)*] [$($element:tt)*] [$($fields:tt)*] $prefix:tt $last:tt reserved $ty:ty $(, $($next:tt)*)?) => {
+        $crate::offset!(@tuple [$($names)*] [] [$($fields)* $($element)* $last reserved $name: $ty,] $prefix $($($next)*)?);
+    };
+
+    (@tuple [$name:ident $($names:ident)*] [$($element:tt)*] [$($fields:tt)*] $prefix:tt $last:tt $vis_field:vis auto $(, $($next:tt)*)?) => {
+        $crate::offset!(@tuple [$($names)*] [] [$($fields)* $($element)* $last $vis_field $name: auto,] $prefix $($($next)*)?);
+    };
+
+    (@tuple [$name:ident $($names:ident)*] [$($element:tt)*] [$($fields:tt)*] $prefix:tt $last:tt $vis_field:vis [$elem:ty; $count:expr] stride $stride:expr $(, $($next:tt)*)?) => {
+        $crate::offset!(@tuple [$($names)*] [] [$($fields)* $($element)* $last $vis_field $name: [$elem; $count] stride $stride,] $prefix $($($next)*)?);
+    };
+
+    (@tuple [$name:ident $($names:ident)*] [$($element:tt)*] [$($fields:tt)*] $prefix:tt $last:tt $vis_field:vis $ty:ty [$size:expr] $(= $default:expr)? $(, $($next:tt)*)?) => {
+        $crate::offset!(@tuple [$($names)*] [] [$($fields)* $($element)* $last $vis_field $name: $ty [$size] $(= $default)?,] $prefix $($($next)*)?);
+    };
+
+    (@tuple [$name:ident $($names:ident)*] [$($element:tt)*] [$($fields:tt)*] $prefix:tt $last:tt $vis_field:vis $ty:ty $(= $default:expr)? $(, $($next:tt)*)?) => {
+        $crate::offset!(@tuple [$($names)*] [] [$($fields)* $($element)* $last $vis_field $name: $ty $(= $default)?,] $prefix $($($next)*)?);
+    };
+
+    (@tuple [$($names:ident)+] [$($element:tt)*] $fields:tt $prefix:tt $token:tt $($next:tt)*) => {
+        $crate::offset!(@tuple [$($names)*] [$($element)* $token] $fields $prefix $($next)*);
+    };
+
+    (@tuple [$($names:ident)*] [] [$($fields:tt)*] [$($prefix:tt)*]) => {
+        $crate::offset!(@attributes $($prefix)* $($fields)*);
+    };
+
+    (@tuple [] $element:tt $fields:tt $prefix:tt $($next:tt)+) => {
+        compile_error!("tuple structs can have at most 32 fields");
+    };
+
+    (@tuple [$($names:ident)*] [$($element:tt)+] $fields:tt $prefix:tt) => {
+        compile_error!(concat!("expected a field type after `", stringify!($($element)*), "`"));
+    };
+
+    // A base struct is a private field at offset 0, which the `extends` option generates the
+    // conversions to the base for.
+    (@start {[$($option:ident)*] $($header:tt)*} [$base:ty] $($input:tt)*) => {
+        $crate::offset!(@start {[$($option)* extends] $($header)*} [] 0x0 base: $base, $($input)*);
+    };
+
+    (@start $header:tt [] $($input:tt)*) => {
+        $crate::offset!(@guard $header (0) [] -> {} {} [] [] [] [] $($input)*);
+    };
+
+    (@extends $vis:vis $name:ident [$($lifetime:lifetime),*] [$($param:ident $(: $bound:path)?),*] {([field] $predicate:tt $attrs:tt $offset:tt $default:tt $vis_field:vis base: $base:ty) $($fields:tt)*}) => {
+        impl<$($lifetime,)* $($param $(: $bound)?),*> $name<$($lifetime,)* $($param),*> {
+            /// Returns the base struct this struct extends.
+            pub fn as_base(&self) -> &$base {
+                &self.base
+            }
+
+            /// Returns the base struct this struct extends for writing.
+            pub fn as_base_mut(&mut self) -> &mut $base {
+                &mut self.base
+            }
+        }
+
+        impl<$($lifetime,)* $($param $(: $bound)?),*> core::ops::Deref for $name<$($lifetime,)* $($param),*> {
+            type Target = $base;
+
+            fn deref(&self) -> &$base {
+                &self.base
+            }
+        }
+
+        impl<$($lifetime,)* $($param $(: $bound)?),*> core::ops::DerefMut for $name<$($lifetime,)* $($param),*> {
+            fn deref_mut(&mut self) -> &mut $base {
+                &mut self.base
+            }
+        }
+    };
+
+    (@private $($input:tt)*) => {};
+
+    (@volatile $($input:tt)*) => {};
+
+    // The bytes of a strict struct have to be covered by its fields and reserved regions. Fields
+    // that alias others may be declared out of order, so every gap is searched for among all of
+    // them.
+    (@strict $vis:vis $name:ident [$($lifetime:lifetime),*] [] $fields:tt) => {
+        $crate::offset!(@strict_check {[] [] pub struct $name [] [$($lifetime),*] []} $name [$($lifetime),*] $fields);
+    };
+
+    (@strict $vis:vis $name:ident $lifetimes:tt [$($params:tt)+] $fields:tt) => {
+        compile_error!("the `strict` option doesn't support structs with type parameters");
+    };
+
+    (@strict_check $header:tt $name:ident [$($lifetime:lifetime),*] {$(([$($kind:tt)*] [$($predicate:tt)?] $attrs:tt [$($offset:tt)*] $default:tt $vis_field:vis $id:ident: $ty:ty))*}) => {
+        const _: () = {
+            const fn check<$($lifetime),*>() {
+                const COUNT: usize = <[&str]>::len(&[$(stringify!($id)),*]);
+                let mut starts: [usize; COUNT] = [0; COUNT];
+                let mut ends: [usize; COUNT] = [0; COUNT];
+                #[allow(unused_mut)]
+                let mut count = 0;
+                $(
+                    $(#[cfg $predicate])?
+                    {
+                        starts[count] = $($offset)*;
+                        ends[count] = starts[count] + $crate::offset!(@extent $header [$($kind)*] $id: $ty);
+                        count += 1;
+                    }
+                )*
+
+                let size = core::mem::size_of::<$name<$($lifetime),*>>();
+                let mut position = 0;
+                while position < size {
+                    let mut covered = position;
+                    let mut next_start = size;
+                    let mut index = 0;
+                    while index < count {
+                        if starts[index] <= position && position < ends[index] {
+                            if ends[index] > covered {
+                                covered = ends[index];
+                            }
+                        } else if starts[index] > position && starts[index] < next_start {
+                            next_start = starts[index];
+                        }
+                        index += 1;
+                    }
+
+                    if covered == position {
+                        let message = $crate::CheckMessage::EMPTY
+                            .str("the bytes ")
+                            .hex(position)
+                            .str("..")
+                            .hex(next_start)
+                            .str(concat!(" of strict `", stringify!($name), "` aren't covered by any field"));
+                        panic!("{}", message.as_str());
+                    }
+                    position = covered;
+                }
+            }
+
+            check();
+        };
+    };
+
+    // The alignments are taken in a function generic over the lifetimes, which can't be named in a
+    // constant, while type parameters would need concrete arguments.
+    (@aligned $vis:vis $name:ident [$($lifetime:lifetime),*] [] {$(([$($kind:tt)*] [$($predicate:tt)?] $attrs:tt [$($offset:tt)*] $default:tt $vis_field:vis $id:ident: $ty:ty))*}) => {
+        const _: () = {
+            const fn check<$($lifetime),*>() {
+                $(
+                    $(#[cfg $predicate])?
+                    $crate::offset!(@align_check [$($kind)*] $name $id ($($offset)*): $ty);
+                )*
+            }
+
+            check();
+        };
+    };
+
+    (@aligned $vis:vis $name:ident $lifetimes:tt [$($params:tt)+] $fields:tt) => {
+        compile_error!("the `aligned` option doesn't support structs with type parameters");
+    };
+
+    // A type has a niche if `Option` can use one of its bit patterns for `None` without growing,
+    // which means that some bytes read from memory aren't a valid value of it.
+    (@plain $vis:vis $name:ident [$($lifetime:lifetime),*] [] {$(([$($kind:tt)*] [$($predicate:tt)?] $attrs:tt $offset:tt $default:tt $vis_field:vis $id:ident: $ty:ty))*}) => {
+        const _: () = {
+            const fn check<$($lifetime),*>() {
+                $(
+                    $(#[cfg $predicate])?
+                    $crate::offset!(@plain_check [$($kind)*] $name $id: $ty);
+                )*
+            }
+
+            check();
+        };
+    };
+
+    (@plain $vis:vis $name:ident $lifetimes:tt [$($params:tt)+] $fields:tt) => {
+        compile_error!("the `plain` option doesn't support structs with type parameters");
+    };
+
+    // The field types are checked through a trait, since whether a type is `#[repr(C)]` can't be
+    // observed by a constant.
+    (@ffi $vis:vis $name:ident [$($lifetime:lifetime),*] [] {$(([$($kind:tt)*] [$($predicate:tt)?] $attrs:tt $offset:tt $default:tt $vis_field:vis $id:ident: $ty:ty))*}) => {
+        const _: () = {
+            #[allow(dead_code)]
+            fn check<$($lifetime),*>() {
+                $(
+                    $(#[cfg $predicate])?
+                    $crate::offset!(@ffi_check [$($kind)*] $ty);
+                )*
+            }
+        };
+
+        unsafe impl<$($lifetime),*> $crate::FfiSafe for $name<$($lifetime),*> {}
+    };
+
+    (@ffi $vis:vis $name:ident $lifetimes:tt [$($params:tt)+] $fields:tt) => {
+        compile_error!("the `ffi` option doesn't support structs with type parameters");
+    };
+
+    // Bitfields are stored in padding, which is made of bytes.
+    (@ffi_check [bits $low:tt $high:tt] $ty:ty) => {};
+
+    (@ffi_check [stride $elem:tt $count:tt $stride:tt] $ty:ty) => {
+        $crate::assert_ffi_safe::<$elem>();
+    };
+
+    (@ffi_check [flexible $elem:tt] $ty:ty) => {
+        $crate::assert_ffi_safe::<$elem>();
+    };
+
+    (@ffi_check $kind:tt $ty:ty) => {
+        $crate::assert_ffi_safe::<$ty>();
+    };
+
+    (@plain_check [stride $elem:tt $count:tt $stride:tt] $name:ident $id:ident: $ty:ty) => {
+        $crate::offset!(@plain_assert $name $id: $elem);
+    };
+
+    (@plain_check [flexible $elem:tt] $name:ident $id:ident: $ty:ty) => {
+        $crate::offset!(@plain_assert $name $id: $elem);
+    };
+
+    (@plain_check $kind:tt $name:ident $id:ident: $ty:ty) => {
+        $crate::offset!(@plain_assert $name $id: $ty);
+    };
+
+    (@plain_assert $name:ident $id:ident: $ty:ty) => {
+        assert!(
+            !core::mem::needs_drop::<$ty>(),
+            concat!("`", stringify!($name), "::", stringify!($id), "` is a `", stringify!($ty), "`, which needs to be dropped")
+        );
+        assert!(
+            core::mem::size_of::<Option<$ty>>() > core::mem::size_of::<$ty>(),
+            concat!("`", stringify!($name), "::", stringify!($id), "` is a `", stringify!($ty), "`, which has invalid bit patterns")
+        );
+    };
+
+    // Reserved regions aren't accessed, so they can be placed anywhere.
+    (@align_check [reserved] $($input:tt)*) => {};
+
+    (@align_check [atomic] $name:ident $id:ident ($offset:expr): $ty:ty) => {
+        $crate::offset!(@align_assert $name $id $offset, core::mem::align_of::<<$ty as $crate::Atomic>::Atomic>());
+    };
+
+    (@align_check [stride $elem:tt $count:tt $stride:tt] $name:ident $id:ident ($offset:expr): $ty:ty) => {
+        $crate::offset!(@align_assert $name $id $offset, core::mem::align_of::<$elem>());
+        if ($stride) % core::mem::align_of::<$elem>() != 0 {
+            let message = $crate::CheckMessage::EMPTY
+                .str(concat!("`", stringify!($name), "::", stringify!($id), "` has a stride of "))
+                .hex($stride)
+                .str(", which isn't aligned to ")
+                .hex(core::mem::align_of::<$elem>())
+                .str(" bytes");
+            panic!("{}", message.as_str());
+        }
+    };
+
+    (@align_check [flexible $elem:tt] $name:ident $id:ident ($offset:expr): $ty:ty) => {
+        $crate::offset!(@align_assert $name $id $offset, core::mem::align_of::<$elem>());
+    };
+
+    (@align_check $kind:tt $name:ident $id:ident ($offset:expr): $ty:ty) => {
+        $crate::offset!(@align_assert $name $id $offset, core::mem::align_of::<$ty>());
+    };
+
+    (@align_assert $name:ident $id:ident $offset:expr, $align:expr) => {
+        if ($offset) % ($align) != 0 {
+            let message = $crate::CheckMessage::EMPTY
+                .str(concat!("`", stringify!($name), "::", stringify!($id), "` at "))
+                .hex($offset)
+                .str(" isn't aligned to ")
+                .hex($align)
+                .str(" bytes");
+            panic!("{}", message.as_str());
+        }
+    };
+
+    // Packed fields can't be borrowed to clone them, so `Clone` copies the whole struct instead.
+    (@copy $vis:vis $name:ident [$($lifetime:lifetime),*] [$($param:ident $(: $bound:path)?),*] $fields:tt) => {
+        impl<$($lifetime,)* $($param $(: $bound)?),*> core::marker::Copy for $name<$($lifetime,)* $($param),*> where $($param: core::marker::Copy),* {}
+
+        impl<$($lifetime,)* $($param $(: $bound)?),*> core::clone::Clone for $name<$($lifetime,)* $($param),*> where $($param: core::marker::Copy),* {
+            fn clone(&self) -> Self {
+                *self
+            }
+        }
+    };
+
+    (@default $($input:tt)*) => {};
+
+    (@serialize $vis:vis $name:ident $lifetimes:tt $params:tt $fields:tt) => {
+        $crate::offset_serde!(@serialize $name $lifetimes $params $fields);
+    };
+
+    (@deserialize $vis:vis $name:ident $lifetimes:tt $params:tt $fields:tt) => {
+        $crate::offset_serde!(@deserialize $name $lifetimes $params $fields);
+    };
+
+    (@debug $($input:tt)*) => {};
+
+    (@new $($input:tt)*) => {};
+
+    (@bytes $($input:tt)*) => {};
+
+    (@from_ptr $vis:vis $name:ident [$($lifetime:lifetime),*] [$($param:ident $(: $bound:path)?),*] $fields:tt) => {
+        impl<$($lifetime,)* $($param $(: $bound)?),*> $name<$($lifetime,)* $($param),*> {
+            /// Views the memory at `pointer` as this struct.
+            ///
+            /// # Safety
+            ///
+            /// `pointer` must point to `SIZE` readable bytes holding a valid value of the struct,
+            /// which aren't written to for as long as the returned reference is used.
+            #[allow(dead_code)]
+            pub unsafe fn from_ptr<'ptr>(pointer: *const u8) -> &'ptr Self {
+                &*pointer.cast::<Self>()
+            }
+
+            /// Views the memory at `pointer` as this struct for writing.
+            ///
+            /// # Safety
+            ///
+            /// `pointer` must point to `SIZE` writable bytes holding a valid value of the struct,
+            /// which aren't accessed through other pointers for as long as the returned reference
+            /// is used.
+            #[allow(dead_code)]
+            pub unsafe fn from_mut_ptr<'ptr>(pointer: *mut u8) -> &'ptr mut Self {
+                &mut *pointer.cast::<Self>()
+            }
+        }
+    };
+
+    (@describe $vis:vis $name:ident [$($lifetime:lifetime),*] [$($param:ident $(: $bound:path)?),*] {$(([$($kind:tt)*] $predicate:tt $attrs:tt [$($offset:tt)*] $default:tt $vis_field:vis $id:ident: $ty:ty))*}) => {
+        impl<$($lifetime,)* $($param $(: $bound)?),*> $name<$($lifetime,)* $($param),*> {
+            $crate::offset!(@describe_fn $name [] $(([$($kind)*] $predicate [$($offset)*] $id: $ty))*);
+            $crate::offset_json!($name);
+        }
+    };
+
+    (@c_declaration $vis:vis $name:ident [$($lifetime:lifetime),*] [$($param:ident $(: $bound:path)?),*] {$(([$($kind:tt)*] $predicate:tt $attrs:tt [$($offset:tt)*] $default:tt $vis_field:vis $id:ident: $ty:ty))*}) => {
+        impl<$($lifetime,)* $($param $(: $bound)?),*> $name<$($lifetime,)* $($param),*> {
+            $crate::offset!(@c_declaration_fn $name [] $(([$($kind)*] $predicate [$($offset)*] $id: $ty))*);
+        }
+    };
+
+    (@defmt $vis:vis $name:ident $lifetimes:tt $params:tt $fields:tt) => {
+        $crate::offset_defmt!($name $lifetimes $params $fields);
+    };
+
+    (@ufmt $vis:vis $name:ident $lifetimes:tt $params:tt $fields:tt) => {
+        $crate::offset_ufmt!($name $lifetimes $params $fields);
+    };
+
+    (@zerocopy $($input:tt)*) => {};
+
+    (@arbitrary $vis:vis $name:ident $lifetimes:tt $params:tt $fields:tt) => {
+        $crate::offset_arbitrary!($name $lifetimes $params $fields);
+    };
+
+    (@kani $vis:vis $name:ident $lifetimes:tt $params:tt $fields:tt) => {
+        $crate::offset_kani!($name $lifetimes $params $fields);
+    };
+
+    (@rkyv $vis:vis $name:ident $lifetimes:tt $params:tt $fields:tt) => {
+        $crate::offset_rkyv!($name $lifetimes $params);
+    };
+
+    (@pod $vis:vis $name:ident $lifetimes:tt $params:tt $fields:tt) => {
+        $crate::offset_bytemuck!($name $lifetimes $params $fields);
+    };
+
+    (@debug_padding $($input:tt)*) => {};
+
+    (@debug_truncate $($input:tt)*) => {};
+
+    // Constants are validated once they're evaluated, so a struct with fields that can't be zero
+    // doesn't compile instead of producing an invalid value.
+    (@zeroed $vis:vis $name:ident [$($lifetime:lifetime),*] [$($param:ident $(: $bound:path)?),*] $fields:tt) => {
+        impl<$($lifetime,)* $($param $(: $bound)?),*> $name<$($lifetime,)* $($param),*> {
+            /// Returns the struct with every byte set to zero.
+            #[allow(dead_code)]
+            pub const fn new_zeroed() -> Self {
+                const { unsafe { core::mem::MaybeUninit::zeroed().assume_init() } }
+            }
+        }
+    };
+
+    // A builder starts out zeroed like `new_zeroed`, and the fields are set through their setters.
+    (@builder $vis:vis $name:ident [$($lifetime:lifetime),*] [$($param:ident $(: $bound:path)?),*] {$(([$($kind:tt)*] $predicate:tt $attrs:tt $offset:tt $default:tt $vis_field:vis $id:ident: $ty:ty))*}) => {
+        $crate::paste::paste! {
+            #[doc = concat!("Builds a [`", stringify!($name), "`] one field at a time, starting out with every byte set to zero.")]
+            #[allow(dead_code)]
+            $vis struct [<$name Builder>]<$($lifetime,)* $($param $(: $bound)?),*> {
+                value: $name<$($lifetime,)* $($param),*>,
+            }
+
+            impl<$($lifetime,)* $($param $(: $bound)?),*> [<$name Builder>]<$($lifetime,)* $($param),*> {
+                $($crate::offset!(@builder_setter [$($kind)*] $predicate $attrs $vis_field $id: $ty);)*
+
+                /// Returns the built struct.
+                #[allow(dead_code)]
+                pub fn build(self) -> $name<$($lifetime,)* $($param),*> {
+                    self.value
+                }
+            }
+
+            impl<$($lifetime,)* $($param $(: $bound)?),*> $name<$($lifetime,)* $($param),*> {
+                /// Returns a builder for the struct with every byte set to zero.
+                #[allow(dead_code)]
+                pub const fn builder() -> [<$name Builder>]<$($lifetime,)* $($param),*> {
+                    [<$name Builder>] { value: const { unsafe { core::mem::MaybeUninit::zeroed().assume_init() } } }
+                }
+            }
+        }
+    };
+
+    (@builder_setter [reserved] $predicate:tt $attrs:tt $vis_field:vis $id:ident: $ty:ty) => {};
+
+    (@builder_setter [flexible $elem:tt] $predicate:tt $attrs:tt $vis_field:vis $id:ident: $ty:ty) => {};
+
+    (@builder_setter [atomic] $predicate:tt $attrs:tt $vis_field:vis $id:ident: $ty:ty) => {};
+
+    (@builder_setter [stride $elem:tt $count:tt $stride:tt] [$($predicate:tt)?] [$($attrs:tt)*] $vis_field:vis $id:ident: $ty:ty) => {
+        $crate::paste::paste! {
+            $(#[cfg $predicate])?
+            $($attrs)*
+            #[allow(dead_code)]
+            $vis_field fn $id(mut self, index: usize, value: $elem) -> Self {
+                self.value.[<set_ $id>](index, value);
+                self
+            }
+        }
+    };
+
+    (@builder_setter $kind:tt [$($predicate:tt)?] [$($attrs:tt)*] $vis_field:vis $id:ident: $ty:ty) => {
+        $crate::paste::paste! {
+            $(#[cfg $predicate])?
+            $($attrs)*
+            #[allow(dead_code)]
+            $vis_field fn $id(mut self, value: $ty) -> Self {
+                self.value.[<set_ $id>](value);
+                self
+            }
+        }
+    };
+
+    // A remote view only holds the address of the struct, and every access goes through the
+    // reader, so the fields are read one at a time instead of copying the whole struct.
+    (@remote $vis:vis $name:ident [] [] {$(([$($kind:tt)*] $predicate:tt $attrs:tt $offset:tt $default:tt $vis_field:vis $id:ident: $ty:ty))*}) => {
+        $crate::paste::paste! {
+            #[doc = concat!("A [`", stringify!($name), "`] in memory read through a [`MemoryRead`](", stringify!($crate), "::MemoryRead), e.g. of another process.")]
+            #[derive(Clone, Copy, Debug)]
+            #[allow(dead_code)]
+            $vis struct [<$name Remote>]<R> {
+                reader: R,
+                address: u64,
+            }
+
+            impl<R: $crate::MemoryRead> [<$name Remote>]<R> {
+                #[doc = concat!("Creates a view of the `", stringify!($name), "` at `address`, read through `reader`.")]
+                ///
+                /// # Safety
+                ///
+                /// The fields read through the view have to hold valid values of their types.
+                #[allow(dead_code)]
+                pub unsafe fn new(reader: R, address: u64) -> Self {
+                    Self { reader, address }
+                }
+
+                /// Returns the address of the struct.
+                #[allow(dead_code)]
+                pub fn address(&self) -> u64 {
+                    self.address
+                }
+
+                /// Returns the reader the fields are read through.
+                #[allow(dead_code)]
+                pub fn reader(&self) -> &R {
+                    &self.reader
+                }
+
+                /// Reads the whole struct.
+                #[allow(dead_code)]
+                pub fn read(&self) -> Result<$name, R::Error> {
+                    unsafe { $crate::read_remote(&self.reader, self.address) }
+                }
+
+                /// Returns a view reading every byte of the struct at most once until the reader's
+                /// `refresh`, and keeping the written bytes until its `flush`.
+                #[allow(dead_code)]
+                pub fn cached(self) -> [<$name Remote>]<$crate::Cached<R, { $name::SIZE }>> {
+                    [<$name Remote>] { reader: $crate::Cached::new(self.reader, self.address), address: self.address }
+                }
+
+                $($crate::offset!(@remote_getter [$($kind)*] $predicate $attrs $offset $vis_field $id: $ty);)*
+            }
+
+            impl<R: $crate::MemoryWrite> [<$name Remote>]<R> {
+                /// Writes the whole struct.
+                #[allow(dead_code)]
+                pub fn write(&self, value: &$name) -> Result<(), R::Error> {
+                    $crate::write_remote(&self.reader, self.address, value)
+                }
+
+                $($crate::offset!(@remote_setter [$($kind)*] $predicate $attrs $offset $vis_field $id: $ty);)*
+            }
+        }
+    };
+
+    (@remote $vis:vis $name:ident $lifetimes:tt $params:tt $fields:tt) => {
+        compile_error!("the `remote` option doesn't support structs with generic parameters");
+    };
+
+    (@remote_getter [reserved] $predicate:tt $attrs:tt $offset:tt $vis_field:vis $id:ident: $ty:ty) => {};
+
+    (@remote_getter [flexible $elem:tt] $predicate:tt $attrs:tt $offset:tt $vis_field:vis $id:ident: $ty:ty) => {};
+
+    (@remote_getter [bits $low:tt $high:tt] [$($predicate:tt)?] [$($attrs:tt)*] [$($offset:tt)*] $vis_field:vis $id:ident: $ty:ty) => {
+        $(#[cfg $predicate])?
+        $($attrs)*
+        #[allow(dead_code)]
+        $vis_field fn $id(&self) -> Result<$ty, R::Error> {
+            let storage: $ty = unsafe { $crate::read_remote(&self.reader, self.address + ($($offset)*) as u64)? };
+            Ok((storage >> $low) & (<$ty>::MAX >> (<$ty>::BITS - ($high - $low))))
+        }
+    };
+
+    (@remote_getter [stride $elem:tt $count:tt $stride:tt] [$($predicate:tt)?] [$($attrs:tt)*] [$($offset:tt)*] $vis_field:vis $id:ident: $ty:ty) => {
+        $(#[cfg $predicate])?
+        $($attrs)*
+        #[allow(dead_code)]
+        $vis_field fn $id(&self, index: usize) -> Result<$elem, R::Error> where for<'copy> $elem: Copy {
+            assert!(index < $count, "index out of bounds");
+            unsafe { $crate::read_remote(&self.reader, self.address + (($($offset)*) + index * ($stride)) as u64) }
+        }
+    };
+
+    // Atomic fields are read like any other, since the reader can't access them atomically.
+    (@remote_getter $kind:tt [$($predicate:tt)?] [$($attrs:tt)*] [$($offset:tt)*] $vis_field:vis $id:ident: $ty:ty) => {
+        $(#[cfg $predicate])?
+        $($attrs)*
+        #[allow(dead_code)]
+        $vis_field fn $id(&self) -> Result<$ty, R::Error> where for<'copy> $ty: Copy {
+            unsafe { $crate::read_remote(&self.reader, self.address + ($($offset)*) as u64) }
+        }
+    };
+
+    (@remote_setter [reserved] $predicate:tt $attrs:tt $offset:tt $vis_field:vis $id:ident: $ty:ty) => {};
+
+    (@remote_setter [flexible $elem:tt] $predicate:tt $attrs:tt $offset:tt $vis_field:vis $id:ident: $ty:ty) => {};
+
+    (@remote_setter [bits $low:tt $high:tt] [$($predicate:tt)?] [$($attrs:tt)*] [$($offset:tt)*] $vis_field:vis $id:ident: $ty:ty) => {
+        $crate::paste::paste! {
+            $(#[cfg $predicate])?
+            $($attrs)*
+            #[allow(dead_code)]
+            $vis_field fn [<set_ $id>](&self, value: $ty) -> Result<(), R::Error> {
+                let mask = (<$ty>::MAX >> (<$ty>::BITS - ($high - $low))) << $low;
+                let address = self.address + ($($offset)*) as u64;
+                let storage: $ty = unsafe { $crate::read_remote(&self.reader, address)? };
+                $crate::write_remote(&self.reader, address, &((storage & !mask) | ((value << $low) & mask)))
+            }
+        }
+    };
+
+    (@remote_setter [stride $elem:tt $count:tt $stride:tt] [$($predicate:tt)?] [$($attrs:tt)*] [$($offset:tt)*] $vis_field:vis $id:ident: $ty:ty) => {
+        $crate::paste::paste! {
+            $(#[cfg $predicate])?
+            $($attrs)*
+            #[allow(dead_code)]
+            $vis_field fn [<set_ $id>](&self, index: usize, value: $elem) -> Result<(), R::Error> {
+                assert!(index < $count, "index out of bounds");
+                $crate::write_remote(&self.reader, self.address + (($($offset)*) + index * ($stride)) as u64, &value)
+            }
+        }
+    };
+
+    (@remote_setter $kind:tt [$($predicate:tt)?] [$($attrs:tt)*] [$($offset:tt)*] $vis_field:vis $id:ident: $ty:ty) => {
+        $crate::paste::paste! {
+            $(#[cfg $predicate])?
+            $($attrs)*
+            #[allow(dead_code)]
+            $vis_field fn [<set_ $id>](&self, value: $ty) -> Result<(), R::Error> {
+                $crate::write_remote(&self.reader, self.address + ($($offset)*) as u64, &value)
+            }
+        }
+    };
+
+    (@ptr32 $($input:tt)*) => {};
+
+    (@target32 $vis:vis $name:ident $lifetimes:tt $params:tt $fields:tt) => {
+        #[cfg(not(target_pointer_width = "32"))]
+        compile_error!(concat!("`", stringify!($name), "` is laid out for targets with 32-bit pointers"));
+    };
+
+    (@target64 $vis:vis $name:ident $lifetimes:tt $params:tt $fields:tt) => {
+        #[cfg(not(target_pointer_width = "64"))]
+        compile_error!(concat!("`", stringify!($name), "` is laid out for targets with 64-bit pointers"));
+    };
+
+    (@ptr64 $($input:tt)*) => {};
+
+    ($(#[$($attr:tt)*])* $vis:vis struct $struct_name:ident $(<$($lifetime:lifetime),* $(,)? $($($param:ident $(: $bound:path)?),+ $(,)?)?>)? $(extends $base:ty)? $([$($struct_size:tt)*])? {$($input:tt)*}) => {
+        $crate::offset!(@attributes [] [] [] [$(#[$($attr)*])*] {$vis struct $struct_name [$($($struct_size)*)?] [$($($lifetime),*)?] [$($($($param $(: $bound)?),+)?)?] [$($base)?]} $($input)*);
+    };
+
+    ($(#[$($attr:tt)*])* $vis:vis struct $struct_name:ident $(<$($lifetime:lifetime),* $(,)? $($($param:ident $(: $bound:path)?),+ $(,)?)?>)? $(extends $base:ident)? $([$($struct_size:tt)*])? ($($input:tt)*) $(;)?) => {
+        $crate::offset!(@tuple [_0 _1 _2 _3 _4 _5 _6 _7 _8 _9 _10 _11 _12 _13 _14 _15 _16 _17 _18 _19 _20 _21 _22 _23 _24 _25 _26 _27 _28 _29 _30 _31] [] [] [[] [] [] [$(#[$($attr)*])*] {$vis struct $struct_name [$($($struct_size)*)?] [$($($lifetime),*)?] [$($($($param $(: $bound)?),+)?)?] [$($base)?]}] $($input)*);
+    };
+}
+
+#[macro_export]
+/// Creates a struct with fields at specific offsets and a custom Debug implementation.
+///
+/// This macro works the same as the `offset!` macro but also implements the Debug trait
+/// in a way that hides padding fields. This gives you cleaner debug output that shows
+/// only the actual data fields, not the padding.
+///
+/// # Alternate Output
+///
+/// Formatting with `{:#?}` shows the offset of every field next to its value in hex, e.g.
+/// `field3 @ 0x8: 0xdeadbeef`, for comparing live memory against expectations. Fields of 1, 2,
+/// 4, 8 or 16 bytes are shown as a number in the byte order of the target, all others as their
+/// bytes in memory order.
+///
+/// # Padding
+///
+/// With `#[offset(debug_padding)]` the output also contains a `padding` entry, which maps every
+/// range of bytes that isn't covered by a field, e.g. `0x4..0x8`, to the bytes it holds.
+///
+/// # Truncation
+///
+/// With `#[offset(debug_truncate)]` fields larger than 16 bytes are shown as their first 16 bytes
+/// in hex followed by their length, e.g. `[00 01 .. 0f] ... (0x200 bytes)`, in both outputs, so
+/// large buffers don't flood the logs. The `defmt` and `ufmt` implementations still show them in
+/// full.
+///
+/// # defmt
+///
+/// With the `defmt` feature, `#[offset(defmt)]` also implements `defmt::Format`, printing the
+/// same fields as the Debug implementation, e.g. `Example { field1: 1, field2: 2 }`. Type
+/// parameters have to implement `defmt::Format`, and the `defmt` version 1 the implementation is
+/// for is the one logged with, e.g. by `defmt::info!`.
+///
+/// ```
+/// # #[cfg(feature = "defmt")] {
+/// use offsetter::offset_debug;
+///
+/// offset_debug!(
+///     #[offset(defmt)]
+///     pub struct Sample {
+///         0x0 pub id: u16,
+///         0x4 pub value: f32
+///     }
+/// );
+///
+/// fn assert_format<T: defmt::Format>() {}
+/// assert_format::<Sample>();
+/// # }
+/// ```
+///
+/// # ufmt
+///
+/// With the `ufmt` feature, `#[offset(ufmt)]` also implements `ufmt::uDebug` with the same fields,
+/// for targets where `core::fmt` is too large. Type parameters have to implement `ufmt::uDebug`,
+/// and the implementation is for `ufmt` 0.2.
+///
+/// ```
+/// # #[cfg(feature = "ufmt")] {
+/// use offsetter::offset_debug;
+///
+/// offset_debug!(
+///     #[offset(ufmt, new)]
+///     pub struct Sample {
+///         0x0 pub id: u16,
+///         0x4 pub value: i32
+///     }
+/// );
+///
+/// let mut text = String::new();
+/// ufmt::uwrite!(text, "{:?}", Sample::new(7, -3)).unwrap();
+/// assert_eq!(text, "Sample { id: 7, value: -3 }");
+/// # }
+/// ```
+///
+/// # Optional Total Size
+///
+/// You can optionally specify the total size of the struct by adding a size value in
+/// square brackets after the struct name. This will ensure the struct has exactly that
+/// size by adding padding at the end.
+///
+/// # Examples
+///
+/// Basic usage:
+///
+/// ```rust
+/// # use offsetter::offset_debug;
+/// offset_debug!(
+///     pub struct Example {
+///         0x0 pub field1: u32,
+///         0x4 pub field2: u16,
+///         0x8 pub field3: u64
+///     }
+/// );
+///
+/// // Debug output will be something like:
+/// // Example { field1: 1, field2: 2, field3: 3 }
+/// // (no padding fields shown)
+/// ```
+///
+/// Showing offsets and hex values:
+///
+/// ```rust
+/// # use offsetter::offset_debug;
+/// offset_debug!(
+///     #[offset(new)]
+///     pub struct Example {
+///         0x0 pub field1: u32,
+///         0x4 pub field2: [u8; 3],
+///         0x8 pub field3: u32
+///     }
+/// );
+///
+/// let example = Example::new(1, [1, 2, 3], 0xdeadbeef);
+/// assert_eq!(
+///     format!("{:#?}", example),
+///     "Example {\n    field1 @ 0x0: 0x1,\n    field2 @ 0x4: [01 02 03],\n    field3 @ 0x8: 0xdeadbeef,\n}"
+/// );
+/// ```
+///
+/// Showing the bytes between the fields:
+///
+/// ```rust
+/// # use offsetter::offset_debug;
+/// offset_debug!(
+///     #[offset(debug_padding, new)]
+///     pub struct Example[0x8] {
+///         0x0 pub field1: u16,
+///         0x4 pub field2: u16
+///     }
+/// );
+///
+/// assert_eq!(
+///     format!("{:?}", Example::new(1, 2)),
+///     "Example { field1: 1, field2: 2, padding: {0x2..0x4: [00 00], 0x6..0x8: [00 00]} }"
+/// );
+/// ```
+///
+/// Shortening large buffers:
+///
+/// ```rust
+/// # use offsetter::offset_debug;
+/// offset_debug!(
+///     #[offset(debug_truncate, new)]
+///     pub struct Packet {
+///         0x0 pub length: u16,
+///         0x2 pub payload: [u8; 0x200]
+///     }
+/// );
+///
+/// let packet = Packet::new(0x200, core::array::from_fn(|index| index as u8));
+/// assert_eq!(
+///     format!("{:?}", packet),
+///     "Packet { length: 512, payload: [00 01 02 03 04 05 06 07 08 09 0a 0b 0c 0d 0e 0f] ... (0x200 bytes) }"
+/// );
+/// ```
+///
+/// With explicit total size:
+///
+/// ```rust
+/// # use offsetter::offset_debug;
+/// offset_debug!(
+///     pub struct KernelStructure[0x100] {
+///         0x00 pub header: u32,
+///         0x08 pub pointer: *mut u8,
+///         0x10 pub flags: u32
+///         // The struct will be padded to exactly 0x100 bytes
+///         // Debug output will not show the padding
+///     }
+/// );
+/// ```
+///
+/// Real-world example for Windows kernel structures:
+///
+/// ```rust
+/// # use offsetter::offset_debug;
+/// # struct DEVICE_OBJECT;
+/// offset_debug!(
+///     pub struct DRIVER_OBJECT[0x150] {
+///         0x0  pub type_: u16,
+///         0x2  pub size: u16,
+///         0x8  pub device_object: *mut DEVICE_OBJECT,
+///         0x10 pub flags: u32,
+///         0x18 pub driver_start: usize,
+///         // More fields...
+///     }
+/// );
+/// ```
+macro_rules! offset_debug {
+    ($(#[$($attr:tt)*])* $vis:vis struct $struct_name:ident $(<$($lifetime:lifetime),* $(,)? $($($param:ident $(: $bound:path)?),+ $(,)?)?>)? $(extends $base:ty)? $([$($struct_size:tt)*])? {$($input:tt)*}) => {
+        $crate::offset!(@attributes [debug] [] [] [$(#[$($attr)*])*] {$vis struct $struct_name [$($($struct_size)*)?] [$($($lifetime),*)?] [$($($($param $(: $bound)?),+)?)?] [$($base)?]} $($input)*);
+    };
+
+    ($(#[$($attr:tt)*])* $vis:vis struct $struct_name:ident $(<$($lifetime:lifetime),* $(,)? $($($param:ident $(: $bound:path)?),+ $(,)?)?>)? $(extends $base:ident)? $([$($struct_size:tt)*])? ($($input:tt)*) $(;)?) => {
+        $crate::offset!(@tuple [_0 _1 _2 _3 _4 _5 _6 _7 _8 _9 _10 _11 _12 _13 _14 _15 _16 _17 _18 _19 _20 _21 _22 _23 _24 _25 _26 _27 _28 _29 _30 _31] [] [] [[debug] [] [] [$(#[$($attr)*])*] {$vis struct $struct_name [$($($struct_size)*)?] [$($($lifetime),*)?] [$($($($param $(: $bound)?),+)?)?] [$($base)?]}] $($input)*);
+    };
+}
+
+#[macro_export]
+/// Creates a struct with fields at specific offsets that are only reachable through accessors.
+///
+/// This macro works the same as the `offset!` macro, except that the fields of the struct are
+/// private and the visibility written in front of a field only applies to its getter and
+/// setter. Code outside the module then can't take references to the packed fields, which is
+/// undefined behavior whenever a field isn't aligned. The same can be done with
+/// `#[offset(private)]` on a struct of the `offset!` macro.
+///
+/// # Examples
+///
+/// ```rust
+/// # use offsetter::offset_accessors;
+/// mod structures {
+///     # use offsetter::offset_accessors;
+///     offset_accessors!(
+///         pub struct Example {
+///             0x0 pub field1: u8,
+///             0x1 pub field2: u64
+///         }
+///     );
+/// }
+///
+/// let mut example: structures::Example = unsafe { core::mem::zeroed() };
+/// example.set_field2(2);
+/// assert_eq!(example.field2(), 2);
+/// ```
+///
+/// Taking a reference to the field outside of the module doesn't compile:
+///
+/// ```rust,compile_fail,E0616
+/// # use offsetter::offset_accessors;
+/// mod structures {
+///     # use offsetter::offset_accessors;
+///     offset_accessors!(
+///         pub struct Example {
+///             0x0 pub field1: u8,
+///             0x1 pub field2: u64
+///         }
+///     );
+/// }
+///
+/// let example: structures::Example = unsafe { core::mem::zeroed() };
+/// let field2 = &example.field2;
+/// ```
+macro_rules! offset_accessors {
+    ($(#[$($attr:tt)*])* $vis:vis struct $struct_name:ident $(<$($lifetime:lifetime),* $(,)? $($($param:ident $(: $bound:path)?),+ $(,)?)?>)? $(extends $base:ty)? $([$($struct_size:tt)*])? {$($input:tt)*}) => {
+        $crate::offset!(@attributes [private] [] [] [$(#[$($attr)*])*] {$vis struct $struct_name [$($($struct_size)*)?] [$($($lifetime),*)?] [$($($($param $(: $bound)?),+)?)?] [$($base)?]} $($input)*);
+    };
+
+    ($(#[$($attr:tt)*])* $vis:vis struct $struct_name:ident $(<$($lifetime:lifetime),* $(,)? $($($param:ident $(: $bound:path)?),+ $(,)?)?>)? $(extends $base:ident)? $([$($struct_size:tt)*])? ($($input:tt)*) $(;)?) => {
+        $crate::offset!(@tuple [_0 _1 _2 _3 _4 _5 _6 _7 _8 _9 _10 _11 _12 _13 _14 _15 _16 _17 _18 _19 _20 _21 _22 _23 _24 _25 _26 _27 _28 _29 _30 _31] [] [] [[private] [] [] [$(#[$($attr)*])*] {$vis struct $struct_name [$($($struct_size)*)?] [$($($lifetime),*)?] [$($($($param $(: $bound)?),+)?)?] [$($base)?]}] $($input)*);
+    };
+}
+
+#[macro_export]
+/// Creates a union whose variants are placed at specific offsets within a shared region.
+///
+/// Each variant is declared like a field of the `offset!` macro. Variants are wrapped in their
+/// own padded struct so that they start at the requested offset, and a read accessor is
+/// generated for every variant since the packed variants can't be referenced directly.
+/// Variant types must be `Copy`. The accessors are unsafe, since the bytes of a variant have to be
+/// a valid value of its type, which is only guaranteed for plain data that is valid for any bit
+/// pattern.
+///
+/// # Plain Unions
+///
+/// `#[offset(plain)]` checks every variant like it checks the fields of an `offset!` struct, so a
+/// variant whose type needs to be dropped or has invalid bit patterns is a compile error. Since
+/// any bytes are then a valid value of every variant, the accessors of plain unions are safe.
+///
+/// # Optional Total Size
+///
+/// You can optionally specify the size of the union by adding a size value in square brackets
+/// after the union name. Every variant is then padded to exactly that size, so a variant that
+/// doesn't fit is a compile error.
+///
+/// # Examples
+///
+/// ```rust
+/// # use offsetter::offset_union;
+/// offset_union!(
+///     pub union LARGE_INTEGER[0x8] {
+///         0x0 pub quad_part: u64,
+///         0x0 pub low_part: u32,
+///         0x4 pub high_part: u32
+///     }
+/// );
+///
+/// let value: LARGE_INTEGER = unsafe { core::mem::transmute(0x1122334455667788u64) };
+/// assert_eq!(unsafe { value.low_part() }, 0x55667788);
+/// assert_eq!(unsafe { value.high_part() }, 0x11223344);
+/// assert_eq!(core::mem::size_of::<LARGE_INTEGER>(), 0x8);
+/// ```
+///
+/// A plain union with safe accessors:
+///
+/// ```rust
+/// # use offsetter::offset_union;
+/// offset_union!(
+///     #[offset(plain)]
+///     pub union ULARGE_INTEGER[0x8] {
+///         0x0 pub quad_part: u64,
+///         0x0 pub low_part: u32,
+///         0x4 pub high_part: u32
+///     }
+/// );
+///
+/// let value: ULARGE_INTEGER = unsafe { core::mem::transmute(0x1122334455667788u64) };
+/// assert_eq!(value.quad_part(), 0x1122334455667788);
+/// assert_eq!(value.low_part(), 0x55667788);
+/// assert_eq!(value.high_part(), 0x11223344);
+/// ```
+///
+/// A plain union can't have variants such as `bool`:
+///
+/// ```rust,compile_fail,E0080
+/// # use offsetter::offset_union;
+/// offset_union!(
+///     #[offset(plain)]
+///     pub union Value[0x4] {
+///         0x0 pub raw: u32,
+///         0x0 pub flag: bool
+///     }
+/// );
+/// ```
+macro_rules! offset_union {
+    ($(#[$($attr:tt)*])* $vis:vis union $union_name:ident $([$union_size:expr])? {$($input:tt)*}) => {
+        $crate::offset_union!(@attributes [] [] [$(#[$($attr)*])*] {$vis union $union_name [$($union_size)?]} $($input)*);
+    };
+
+    // Unions only take the `plain` option, the other options are about structs.
+    (@attributes [$($option:ident)*] [$($attr:tt)*] [#[offset(plain $(, $($more:tt)*)?)] $($rest:tt)*] $union:tt $($input:tt)*) => {
+        $crate::offset_union!(@attributes [$($option)* plain] [$($attr)*] [#[offset($($($more)*)?)] $($rest)*] $union $($input)*);
+    };
+
+    (@attributes $options:tt $attrs:tt [#[offset()] $($rest:tt)*] $union:tt $($input:tt)*) => {
+        $crate::offset_union!(@attributes $options $attrs [$($rest)*] $union $($input)*);
+    };
+
+    (@attributes $options:tt $attrs:tt [#[offset($option:ident $($more:tt)*)] $($rest:tt)*] $union:tt $($input:tt)*) => {
+        compile_error!(concat!("the `", stringify!($option), "` option isn't supported by unions"));
+    };
+
+    (@attributes $options:tt [$($attr:tt)*] [#[$($next_attr:tt)*] $($rest:tt)*] $union:tt $($input:tt)*) => {
+        $crate::offset_union!(@attributes $options [$($attr)* #[$($next_attr)*]] [$($rest)*] $union $($input)*);
+    };
+
+    (@attributes $options:tt [$($attr:tt)*] [] {$vis:vis union $union_name:ident $union_size:tt} $($input:tt)*) => {
+        $crate::offset!(@guard {$options [$($attr)*] $vis union $union_name $union_size [] []} (0) [] -> {} {} [] [] [] [] $($input)*);
+    };
+}
+
+#[macro_export]
+/// Creates a view of a struct whose field offsets are only known at runtime.
+///
+/// Fields are declared without offsets. Instead, a `FooOffsets` struct with a `usize` for every
+/// field is generated next to `Foo`, and the offsets are set once with `Foo::set_offsets`, e.g.
+/// after detecting the version of a game or of the binary being analyzed, whose layouts change
+/// with every patch. `Foo` itself only holds the address of the struct, and every field is read
+/// and written through a getter and a `set_` setter computing the address from the offsets.
+///
+/// `from_ptr` creates a view of the struct at an address, and `offsets` returns the offsets that
+/// were set, if any. Accessing a field before the offsets were set panics. Field types must be
+/// `Copy`, and the fields are accessed with unaligned reads and writes.
+///
+/// `FooOffsets::from_json` reads the offsets from a layout in the JSON format of
+/// `verify_layout!`, e.g. one shipped next to the tool for every supported version. It returns an
+/// [`OffsetsError`] if a field is missing, or doesn't fit within the `size` the layout gives the
+/// struct. `FooOffsets::patch` applies a delta layout in the same format that only lists the
+/// fields that moved, so supporting a new build means shipping a few lines next to the base
+/// layout.
+///
+/// Offsets that are set again replace the previous ones as a whole, so `offsets` never mixes two
+/// of them. With the `std` feature, `Foo::watch_offsets` reads them from a layout file, and again
+/// whenever it changes, see the `watch` module.
+///
+/// # Examples
+///
+/// ```rust
+/// # use offsetter::offset_dynamic;
+/// offset_dynamic!(
+///     pub struct Player {
+///         pub health: u32,
+///         pub position: [f32; 3],
+///     }
+/// );
+///
+/// unsafe { Player::set_offsets(PlayerOffsets { health: 0x10, position: 0x40 }) };
+///
+/// let mut memory = [0u8; 0x50];
+/// memory[0x10..0x14].copy_from_slice(&100u32.to_ne_bytes());
+/// let mut player = unsafe { Player::from_ptr(memory.as_mut_ptr()) };
+/// assert_eq!(player.health(), 100);
+///
+/// player.set_position([1.0, 2.0, 3.0]);
+/// assert_eq!(player.position(), [1.0, 2.0, 3.0]);
+/// assert_eq!(Player::offsets().unwrap().position, 0x40);
+/// ```
+///
+/// Reading the offsets from a layout:
+///
+/// ```rust
+/// # use offsetter::{offset_dynamic, OffsetsError};
+/// offset_dynamic!(
+///     pub struct Player {
+///         pub health: u32,
+///         pub position: [f32; 3],
+///     }
+/// );
+///
+/// let offsets = PlayerOffsets::from_json(r#"{
+///     "Player": { "size": 80, "fields": { "Health": { "offset": 16 }, "Position": { "offset": 64 } } }
+/// }"#);
+/// assert_eq!(offsets, Ok(PlayerOffsets { health: 0x10, position: 0x40 }));
+///
+/// let offsets = PlayerOffsets::from_json(r#"{
+///     "Player": { "size": 72, "fields": { "Health": { "offset": 16 }, "Position": { "offset": 64 } } }
+/// }"#);
+/// assert_eq!(
+///     offsets.unwrap_err().to_string(),
+///     "`Player::position` at 0x40 doesn't fit in the 0x48 bytes of `Player`"
+/// );
+///
+/// let base = PlayerOffsets { health: 0x10, position: 0x40 };
+/// let offsets = base.patch(r#"{ "Player": { "fields": { "Position": { "offset": 72 } } } }"#);
+/// assert_eq!(offsets, Ok(PlayerOffsets { health: 0x10, position: 0x48 }));
+/// ```
+macro_rules! offset_dynamic {
+    ($(#[$attr:meta])* $vis:vis struct $name:ident {$($(#[$field_attr:meta])* $vis_field:vis $id:ident: $ty:ty),* $(,)?}) => {
+        $crate::paste::paste! {
+            $(#[$attr])*
+            #[derive(Clone, Copy, Debug)]
+            $vis struct $name {
+                base: *mut u8,
+            }
+
+            #[doc = concat!("The offsets of the fields of [`", stringify!($name), "`], see its `set_offsets`.")]
+            #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+            $vis struct [<$name Offsets>] {
+                $($(#[$field_attr])* pub $id: usize,)*
+            }
+
+            impl [<$name Offsets>] {
+                /// Reads the offsets from a layout in the JSON format of `verify_layout!`, checking
+                /// that every field has an offset and fits within the size of the struct, if the
+                /// layout gives one.
+                #[allow(dead_code)]
+                pub fn from_json(layout: &str) -> Result<Self, $crate::OffsetsError> {
+                    const COUNT: usize = <[&str]>::len(&[$(stringify!($id)),*]);
+                    let mut offsets = [0; COUNT];
+                    $crate::read_offsets(
+                        layout,
+                        stringify!($name),
+                        &[$((stringify!($id), core::mem::size_of::<$ty>())),*],
+                        &mut offsets,
+                        true,
+                    )?;
+
+                    let [$($id),*] = offsets;
+                    Ok(Self { $($id),* })
+                }
+
+                /// Applies a delta layout, in the format of `from_json` but listing only the fields
+                /// that moved, and returns the offsets with those fields replaced. If the delta
+                /// gives a size, every field has to fit within it.
+                #[allow(dead_code)]
+                pub fn patch(self, delta: &str) -> Result<Self, $crate::OffsetsError> {
+                    let mut offsets = [$(self.$id),*];
+                    $crate::read_offsets(
+                        delta,
+                        stringify!($name),
+                        &[$((stringify!($id), core::mem::size_of::<$ty>())),*],
+                        &mut offsets,
+                        false,
+                    )?;
+
+                    let [$($id),*] = offsets;
+                    Ok(Self { $($id),* })
+                }
+            }
+
+            // The offsets are stored field by field behind a sequence number, which is odd while
+            // they're being replaced and 0 until they're first set, so a reader of the whole table
+            // can retry until it saw a single version of it.
+            #[doc(hidden)]
+            #[allow(non_snake_case)]
+            mod [<__ $name _offsets>] {
+                pub struct Table {
+                    $(pub $id: core::sync::atomic::AtomicUsize,)*
+                    pub sequence: core::sync::atomic::AtomicUsize,
+                }
+
+                pub static TABLE: Table = Table {
+                    $($id: core::sync::atomic::AtomicUsize::new(0),)*
+                    sequence: core::sync::atomic::AtomicUsize::new(0),
+                };
+            }
+
+            impl $name {
+                /// Sets the offsets of the fields for every view of the struct, replacing the ones
+                /// set before as a whole.
+                ///
+                /// # Safety
+                ///
+                /// Every field has to fit at its offset within the structs viewed through
+                /// `from_ptr`, for as long as the views are used.
+                #[allow(dead_code)]
+                pub unsafe fn set_offsets(offsets: [<$name Offsets>]) {
+                    let table = &[<__ $name _offsets>]::TABLE;
+                    let mut sequence = table.sequence.load(core::sync::atomic::Ordering::Relaxed);
+                    loop {
+                        if sequence % 2 == 1 {
+                            core::hint::spin_loop();
+                            sequence = table.sequence.load(core::sync::atomic::Ordering::Relaxed);
+                            continue;
+                        }
+
+                        match table.sequence.compare_exchange_weak(sequence, sequence + 1, core::sync::atomic::Ordering::Acquire, core::sync::atomic::Ordering::Relaxed) {
+                            Ok(_) => break,
+                            Err(current) => sequence = current,
+                        }
+                    }
+
+                    $(table.$id.store(offsets.$id, core::sync::atomic::Ordering::Relaxed);)*
+                    table.sequence.store(sequence + 2, core::sync::atomic::Ordering::Release);
+                }
+
+                /// Returns the offsets of the fields, or `None` if they haven't been set yet.
+                #[allow(dead_code)]
+                pub fn offsets() -> Option<[<$name Offsets>]> {
+                    let table = &[<__ $name _offsets>]::TABLE;
+                    loop {
+                        let sequence = table.sequence.load(core::sync::atomic::Ordering::Acquire);
+                        if sequence == 0 {
+                            return None;
+                        }
+                        if sequence % 2 == 1 {
+                            core::hint::spin_loop();
+                            continue;
+                        }
+
+                        let offsets = [<$name Offsets>] {
+                            $($id: table.$id.load(core::sync::atomic::Ordering::Relaxed),)*
+                        };
+                        core::sync::atomic::fence(core::sync::atomic::Ordering::Acquire);
+                        if table.sequence.load(core::sync::atomic::Ordering::Relaxed) == sequence {
+                            return Some(offsets);
+                        }
+                    }
+                }
+
+                /// Views the struct at `pointer`.
+                ///
+                /// # Safety
+                ///
+                /// `pointer` must point to a struct whose fields are at the offsets set through
+                /// `set_offsets`, which stays readable and writable for as long as the view is used.
+                #[allow(dead_code)]
+                pub unsafe fn from_ptr(pointer: *mut u8) -> Self {
+                    Self { base: pointer }
+                }
+
+                /// Returns the address of the struct.
+                #[allow(dead_code)]
+                pub fn as_ptr(&self) -> *mut u8 {
+                    self.base
+                }
+
+                #[allow(dead_code)]
+                fn __offset(offset: &core::sync::atomic::AtomicUsize) -> usize {
+                    assert!(
+                        [<__ $name _offsets>]::TABLE.sequence.load(core::sync::atomic::Ordering::Acquire) >= 2,
+                        concat!("the offsets of `", stringify!($name), "` haven't been set")
+                    );
+                    offset.load(core::sync::atomic::Ordering::Relaxed)
+                }
+
+                $(
+                    $(#[$field_attr])*
+                    #[allow(dead_code)]
+                    $vis_field fn $id(&self) -> $ty {
+                        let offset = Self::__offset(&[<__ $name _offsets>]::TABLE.$id);
+                        *core::mem::ManuallyDrop::new(unsafe { self.base.add(offset).cast::<$ty>().read_unaligned() })
+                    }
+
+                    $(#[$field_attr])*
+                    #[allow(dead_code)]
+                    $vis_field fn [<set_ $id>](&mut self, value: $ty) {
+                        let offset = Self::__offset(&[<__ $name _offsets>]::TABLE.$id);
+                        unsafe { self.base.add(offset).cast::<$ty>().write_unaligned(value) }
+                    }
+                )*
+            }
+        }
+
+        $crate::offset_watch!($name);
+    };
+}
+
+#[cfg(feature = "std")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! offset_watch {
+    ($name:ident) => {
+        $crate::paste::paste! {
+            impl $name {
+                /// Sets the offsets from the layout in the file at `path`, like
+                #[doc = concat!("`", stringify!($name), "Offsets::from_json`, and sets them again whenever the file changes,")]
+                /// checking every `interval`, until the returned watcher is dropped. A layout that
+                /// can't be read keeps the offsets that were set before, and its error is passed to
+                /// `on_error`.
+                ///
+                /// # Safety
+                ///
+                /// Every layout the file holds has to satisfy the requirements of `set_offsets`.
+                #[allow(dead_code)]
+                pub unsafe fn watch_offsets(
+                    path: impl Into<$crate::watch::PathBuf>,
+                    interval: core::time::Duration,
+                    mut on_error: impl FnMut($crate::OffsetsError) + Send + 'static,
+                ) -> $crate::watch::io::Result<$crate::watch::Watcher> {
+                    $crate::watch::Watcher::spawn(path, interval, move |layout| match [<$name Offsets>]::from_json(layout) {
+                        Ok(offsets) => unsafe { Self::set_offsets(offsets) },
+                        Err(error) => on_error(error),
+                    })
+                }
+            }
+        }
+    };
+}
+
+#[cfg(not(feature = "std"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! offset_watch {
+    ($name:ident) => {};
+}
+
+#[macro_export]
+/// Declares several versions of the same struct, e.g. one per build of the binary it's taken
+/// from, together with a trait for the fields they share and an enum over the versions.
+///
+/// Every version is declared like a struct of the `offset!` macro, with its version after an `@`,
+/// e.g. `EPROCESS@22H2`, and named after both like the layouts of dual structs, e.g.
+/// `EPROCESS22H2`. The trait lists the shared fields with their types, and is implemented for
+/// every version by calling its getters, so code written against the trait works with any of
+/// them. A version lacking one of the shared fields is a compile error. The enum holds a reference to one of the versions, picked at runtime, e.g. after
+/// detecting the build, and implements the trait by dispatching to it. Its variants are named
+/// after the versions, and it's created from a reference to one of them with `From`.
+///
+/// A version can list the Windows builds it's taken from as a pattern after its name, e.g.
+/// `EPROCESS@22H2(22621..=22631)`. The enum's `layout` then returns the `LAYOUT` of the version
+/// matching an [`OsBuild`](crate::OsBuild), and its `from_ptr` views a struct as that version. With
+/// the "std" feature on Windows, `current` returns the layout for the running build.
+///
+/// # Examples
+///
+/// ```rust
+/// # use offsetter::{offset_versions, OsBuild};
+/// offset_versions!(
+///     /// The fields of `EPROCESS` used by the tool.
+///     pub trait ProcessFields for pub enum Process {
+///         unique_process_id: usize,
+///         peb: u64,
+///     }
+///
+///     #[offset(from_ptr)]
+///     pub struct EPROCESS@22H2(22621..=22631) [0x880] {
+///         0x440 pub unique_process_id: usize,
+///         0x550 pub peb: u64,
+///     }
+///
+///     #[offset(from_ptr)]
+///     pub struct EPROCESS@24H2(26100..) [0x880] {
+///         0x1d0 pub unique_process_id: usize,
+///         0x2e0 pub peb: u64,
+///     }
+/// );
+///
+/// fn open(build: u32, address: *const u8) -> Process<'static> {
+///     match build {
+///         22621 => Process::from(unsafe { EPROCESS22H2::from_ptr(address) }),
+///         _ => Process::from(unsafe { EPROCESS24H2::from_ptr(address) }),
+///     }
+/// }
+///
+/// let mut memory = Box::new([0u8; 0x880]);
+/// memory[0x1d0..0x1d8].copy_from_slice(&4usize.to_ne_bytes());
+/// memory[0x440..0x448].copy_from_slice(&8usize.to_ne_bytes());
+/// let memory = Box::leak(memory);
+///
+/// assert_eq!(open(26100, memory.as_ptr()).unique_process_id(), 4);
+/// assert_eq!(open(22621, memory.as_ptr()).unique_process_id(), 8);
+/// assert!(matches!(open(22621, memory.as_ptr()), Process::EPROCESS22H2(_)));
+///
+/// assert_eq!(Process::layout(OsBuild(22631)).unwrap().field("peb").unwrap().offset, 0x550);
+/// assert!(Process::layout(OsBuild(19045)).is_none());
+/// let process = unsafe { Process::from_ptr(OsBuild(26100), memory.as_ptr()) }.unwrap();
+/// assert_eq!(process.unique_process_id(), 4);
+/// ```
+macro_rules! offset_versions {
+    ($(#[$trait_attr:meta])* $trait_vis:vis trait $trait_name:ident for $enum_vis:vis enum $enum_name:ident $getters:tt
+     $($(#[$($attr:tt)*])* $vis:vis struct $name:ident @ $version:tt $(($builds:pat))? $([$($size:tt)*])? {$($input:tt)*})+) => {
+        $crate::offset_versions!(@trait [$(#[$trait_attr])*] $trait_vis $trait_name $getters);
+
+        $crate::paste::paste! {
+            $(
+                $crate::offset!($(#[$($attr)*])* $vis struct [<$name $version>] $([$($size)*])? {$($input)*});
+                $crate::offset_versions!(@impl $trait_name [<$name $version>] $getters);
+            )+
+
+            #[doc = concat!("One of the versions implementing [`", stringify!($trait_name), "`], picked at runtime.")]
+            #[derive(Clone, Copy)]
+            $enum_vis enum $enum_name<'a> {
+                $(
+                    #[doc = concat!("Version `", stringify!($version), "`.")]
+                    [<$name $version>](&'a [<$name $version>]),
+                )+
+            }
+
+            $(
+                impl<'a> From<&'a [<$name $version>]> for $enum_name<'a> {
+                    fn from(value: &'a [<$name $version>]) -> Self {
+                        Self::[<$name $version>](value)
+                    }
+                }
+            )+
+
+            impl<'a> $enum_name<'a> {
+                /// The layout of the version declared for `build`, if any.
+                pub fn layout(build: $crate::OsBuild) -> Option<$crate::Layout> {
+                    $($(if matches!(build.0, $builds) {
+                        return Some([<$name $version>]::LAYOUT);
+                    })?)+
+                    None
+                }
+
+                /// Views the struct at `pointer` as the version declared for `build`, if any.
+                ///
+                /// # Safety
+                ///
+                /// `pointer` must point to a valid value of the version, which isn't written to for as
+                /// long as the returned view is used.
+                pub unsafe fn from_ptr(build: $crate::OsBuild, pointer: *const u8) -> Option<Self> {
+                    $($(if matches!(build.0, $builds) {
+                        return Some(Self::from(unsafe { &*pointer.cast::<[<$name $version>]>() }));
+                    })?)+
+                    None
+                }
+
+                $crate::offset_os_build!();
+            }
+
+            $crate::offset_versions!(@dispatch $trait_name $enum_name [$([<$name $version>])+] $getters);
+        }
+    };
+
+    // The shared fields are passed around as a whole, since they repeat independently of the
+    // versions and can't be expanded within their repetition.
+    (@trait [$($trait_attr:tt)*] $trait_vis:vis $trait_name:ident {$($(#[$getter_attr:meta])* $getter:ident: $getter_ty:ty),* $(,)?}) => {
+        $($trait_attr)*
+        $trait_vis trait $trait_name {
+            $($(#[$getter_attr])* fn $getter(&self) -> $getter_ty;)*
+        }
+    };
+
+    // A getter missing from a version would resolve to the method of the trait itself and
+    // recurse, so the offset constant of the field is looked up first to fail to compile instead.
+    (@impl $trait_name:ident $name:ident {$($(#[$getter_attr:meta])* $getter:ident: $getter_ty:ty),* $(,)?}) => {
+        $crate::paste::paste! {
+            impl $trait_name for $name {
+                $(
+                    fn $getter(&self) -> $getter_ty {
+                        let _ = $name::[<OFFSET_ $getter:upper>];
+                        $name::$getter(self)
+                    }
+                )*
+            }
+        }
+    };
+
+    (@dispatch $trait_name:ident $enum_name:ident $variants:tt {$($(#[$getter_attr:meta])* $getter:ident: $getter_ty:ty),* $(,)?}) => {
+        impl $trait_name for $enum_name<'_> {
+            $($crate::offset_versions!(@dispatch_getter $variants $getter: $getter_ty);)*
+        }
+    };
+
+    (@dispatch_getter [$($variant:ident)+] $getter:ident: $getter_ty:ty) => {
+        fn $getter(&self) -> $getter_ty {
+            match self {
+                $(Self::$variant(value) => value.$getter(),)+
+            }
+        }
+    };
+}
+
+#[cfg(all(feature = "std", windows))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! offset_os_build {
+    () => {
+        /// The layout of the version declared for the build of the running Windows, if any.
+        pub fn current() -> Option<$crate::Layout> {
+            Self::layout($crate::OsBuild::current())
+        }
+    };
+}
+
+#[cfg(not(all(feature = "std", windows)))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! offset_os_build {
+    () => {};
+}
+
 #[macro_export]
-/// Creates a struct with fields placed at specific memory offsets.
-///
-/// This macro allows you to define a struct with precise memory layout by specifying
-/// the byte offset of each field. The macro automatically inserts padding between fields
-/// to ensure proper alignment.
+/// Declares the vtable of a reversed C++ class, with a struct of its function pointers and a
+/// wrapper over a pointer to an object that calls them.
 ///
-/// # Optional Total Size
+/// Every slot is declared with its index in the vtable and the signature of its function without
+/// the object pointer, which is passed first as `*mut c_void`. Slots use the C calling convention
+/// unless they're declared with another, e.g. `3 pub extern "thiscall" fn tick(delta: f32);` for
+/// 32-bit MSVC binaries. The vtable struct is declared with `offset!`, with every slot at its index
+/// times the size of a pointer, so the slots have to be declared in ascending order and the ones
+/// in between are padding. The wrapper is created with `from_ptr` from a pointer to an object
+/// starting with its vtable pointer, and has an unsafe method per slot calling its function.
 ///
-/// You can optionally specify the total size of the struct by adding a size value in
-/// square brackets after the struct name. This will ensure the struct has exactly that
-/// size by adding padding at the end if necessary.
+/// For an object declared with `offset!`, naming the field holding its vtable pointer instead of
+/// a wrapper, e.g. `pub struct ActorVtable for Actor.vtable`, generates the methods on the object
+/// itself. Both also get `virtual_function::<F>(index)`, which returns the function in any slot,
+/// like [`virtual_function`](crate::virtual_function) does for any object.
 ///
 /// # Examples
 ///
-/// Basic usage:
+/// ```rust
+/// # use offsetter::vtable;
+/// use core::ffi::c_void;
+///
+/// vtable!(
+///     /// The virtual functions of `Actor`.
+///     pub struct ActorVtable for pub struct Actor {
+///         /// Applies damage and returns the health left.
+///         1 pub fn damage(amount: u32) -> u32;
+///         2 pub fn health() -> u32;
+///     }
+/// );
+///
+/// #[repr(C)]
+/// struct Native {
+///     vtable: *const [usize; 3],
+///     health: u32,
+/// }
+///
+/// extern "C" fn damage(this: *mut c_void, amount: u32) -> u32 {
+///     let native = unsafe { &mut *this.cast::<Native>() };
+///     native.health -= amount;
+///     native.health
+/// }
+///
+/// extern "C" fn health(this: *mut c_void) -> u32 {
+///     unsafe { (*this.cast::<Native>()).health }
+/// }
+///
+/// let vtable = [0, damage as *const () as usize, health as *const () as usize];
+/// let mut native = Native { vtable: &vtable, health: 100 };
+/// let actor = unsafe { Actor::from_ptr((&mut native as *mut Native).cast()) };
+/// assert_eq!(unsafe { actor.damage(30) }, 70);
+/// assert_eq!(unsafe { actor.health() }, 70);
+/// assert_eq!(ActorVtable::OFFSET_HEALTH, 2 * core::mem::size_of::<usize>());
+/// ```
+///
+/// Calling through the vtable pointer field of a struct:
+///
+/// ```rust
+/// # use offsetter::{offset, vtable};
+/// use core::ffi::c_void;
+///
+/// offset!(
+///     #[offset(from_ptr)]
+///     pub struct Actor {
+///         0x0 pub vtable: *const usize,
+///         0x8 pub health: u32
+///     }
+/// );
+///
+/// vtable!(
+///     pub struct ActorVtable for Actor.vtable {
+///         1 pub fn damage(amount: u32) -> u32;
+///     }
+/// );
+///
+/// extern "C" fn damage(this: *mut c_void, amount: u32) -> u32 {
+///     let actor = unsafe { &mut *this.cast::<Actor>() };
+///     actor.health -= amount;
+///     actor.health
+/// }
+///
+/// extern "C" fn health(this: *mut c_void) -> u32 {
+///     unsafe { (*this.cast::<Actor>()).health }
+/// }
+///
+/// let vtable = [0, damage as *const () as usize, health as *const () as usize];
+/// let mut memory = [0u8; 0x10];
+/// memory[0x0..0x8].copy_from_slice(&(vtable.as_ptr() as usize).to_ne_bytes());
+/// memory[0x8..0xc].copy_from_slice(&100u32.to_ne_bytes());
+/// let actor = unsafe { Actor::from_ptr(memory.as_mut_ptr()) };
+/// assert_eq!(unsafe { actor.damage(30) }, 70);
+///
+/// let health: unsafe extern "C" fn(*mut c_void) -> u32 = unsafe { actor.virtual_function(2) };
+/// assert_eq!(unsafe { health((actor as *const Actor).cast_mut().cast()) }, 70);
+/// ```
+macro_rules! vtable {
+    ($(#[$attr:meta])* $vis:vis struct $name:ident for $(#[$object_attr:meta])* $object_vis:vis struct $object:ident {$($slots:tt)*}) => {
+        $crate::vtable!(@slots [$(#[$attr])* $vis struct $name] [$(#[$object_attr])* $object_vis struct $object] [] $($slots)*);
+    };
+
+    ($(#[$attr:meta])* $vis:vis struct $name:ident for $object:ident . $field:ident {$($slots:tt)*}) => {
+        $crate::vtable!(@slots [$(#[$attr])* $vis struct $name] [$object . $field] [] $($slots)*);
+    };
+
+    // Slots are collected with their calling convention, which defaults to the C one.
+    (@slots $vtable:tt $object:tt [$($slot:tt)*] $(#[$slot_attr:meta])* $index:literal $slot_vis:vis fn $($rest:tt)*) => {
+        $crate::vtable!(@slots $vtable $object [$($slot)*] $(#[$slot_attr])* $index $slot_vis extern "C" fn $($rest)*);
+    };
+
+    (@slots $vtable:tt $object:tt [$($slot:tt)*] $(#[$slot_attr:meta])* $index:literal $slot_vis:vis extern $abi:literal fn $id:ident($($arg:ident: $arg_ty:ty),* $(,)?) $(-> $ret:ty)?; $($rest:tt)*) => {
+        $crate::vtable!(@slots $vtable $object [$($slot)* ([$(#[$slot_attr])*] $index [$abi] $slot_vis $id [$($arg: $arg_ty),*] [$($ret)?])] $($rest)*);
+    };
+
+    (@slots [$(#[$attr:meta])* $vis:vis struct $name:ident] $object:tt [$(([$($slot_attr:tt)*] $index:literal [$abi:literal] $slot_vis:vis $id:ident [$($arg:ident: $arg_ty:ty),*] [$($ret:ty)?]))*]) => {
+        $crate::offset!(
+            $(#[$attr])*
+            $vis struct $name {
+                $(
+                    $($slot_attr)*
+                    ($index * core::mem::size_of::<usize>()) $slot_vis $id: unsafe extern $abi fn(*mut core::ffi::c_void $(, $arg_ty)*) $(-> $ret)?
+                ),*
+            }
+        );
+
+        $crate::vtable!(@object $name $object [$(([$($slot_attr)*] $index [$abi] $slot_vis $id [$($arg: $arg_ty),*] [$($ret)?]))*]);
+    };
+
+    (@object $name:ident [$(#[$object_attr:meta])* $object_vis:vis struct $object:ident] $slots:tt) => {
+        $(#[$object_attr])*
+        #[derive(Clone, Copy)]
+        $object_vis struct $object {
+            pointer: *mut u8,
+        }
+
+        impl $object {
+            /// Wraps the object at `pointer`, which starts with a pointer to its vtable.
+            ///
+            /// # Safety
+            ///
+            /// The object has to stay alive while the wrapper is used, and its vtable has to hold
+            /// functions with the declared signatures at the declared slots.
+            #[allow(dead_code)]
+            pub unsafe fn from_ptr(pointer: *mut u8) -> Self {
+                Self { pointer }
+            }
+
+            /// The pointer to the object.
+            #[allow(dead_code)]
+            pub fn as_ptr(&self) -> *mut u8 {
+                self.pointer
+            }
+
+            /// The vtable of the object.
+            #[allow(dead_code)]
+            pub fn vtable(&self) -> &$name {
+                unsafe { &*self.pointer.cast::<*const $name>().read() }
+            }
+
+            $crate::vtable!(@methods [wrapper] $slots);
+        }
+    };
+
+    (@object $name:ident [$object:ident . $field:ident] $slots:tt) => {
+        impl $object {
+            $crate::vtable!(@methods [field $object $field] $slots);
+        }
+    };
+
+    // `self` is passed to `@this` from the methods declaring it, since it's hygienic.
+    (@methods $kind:tt [$(([$($slot_attr:tt)*] $index:literal [$abi:literal] $slot_vis:vis $id:ident [$($arg:ident: $arg_ty:ty),*] [$($ret:ty)?]))*]) => {
+        /// Returns the function in slot `index` of the vtable as an `F`, e.g. an
+        /// `unsafe extern "C" fn(*mut c_void) -> u32`, to call slots that aren't declared.
+        ///
+        /// # Safety
+        ///
+        /// The slot has to hold a function of type `F`.
+        #[allow(dead_code)]
+        pub unsafe fn virtual_function<F: Copy>(&self, index: usize) -> F {
+            let (object, offset) = $crate::vtable!(@this $kind self);
+            unsafe { $crate::virtual_function(object, offset, index) }
+        }
+
+        $(
+            $($slot_attr)*
+            ///
+            /// # Safety
+            ///
+            /// The arguments have to satisfy the requirements of the function in the slot.
+            #[allow(dead_code)]
+            $slot_vis unsafe fn $id(&self $(, $arg: $arg_ty)*) $(-> $ret)? {
+                let (object, offset) = $crate::vtable!(@this $kind self);
+                let function: unsafe extern $abi fn(*mut core::ffi::c_void $(, $arg_ty)*) $(-> $ret)? =
+                    unsafe { $crate::virtual_function(object, offset, $index) };
+                unsafe { function(object.cast_mut().cast() $(, $arg)*) }
+            }
+        )*
+    };
+
+    (@this [wrapper] $this:tt) => {
+        ($this.pointer.cast_const(), 0)
+    };
+
+    (@this [field $object:ident $field:ident] $this:tt) => {
+        ($this as *const $object as *const u8, $crate::paste::paste!($object::[<OFFSET_ $field:upper>]))
+    };
+}
+
+#[macro_export]
+/// Returns the offset of a field of a struct declared with these macros.
+///
+/// This is the field's `OFFSET_` constant, so it's usable in constant expressions and also
+/// works for fields that are only reachable through accessors, such as bitfields.
+///
+/// # Examples
 ///
 /// ```rust
+/// # use offsetter::{field_offset, offset};
 /// offset!(
 ///     pub struct Example {
 ///         0x0 pub field1: u32,
-///         0x4 pub field2: u16,
-///         0x8 pub field3: u64
+///         0x8 pub field2: u64,
+///         0x10:0..4 pub flags: u8
 ///     }
 /// );
+///
+/// const FIELD2: usize = field_offset!(Example, field2);
+/// assert_eq!(FIELD2, 0x8);
+/// assert_eq!(field_offset!(Example, flags), 0x10);
 /// ```
+macro_rules! field_offset {
+    ($struct_name:ty, $field:ident $(,)?) => {
+        $crate::paste::paste!(<$struct_name>::[<OFFSET_ $field:upper>])
+    };
+}
+
+#[macro_export]
+/// Returns a pointer to the struct containing the field `pointer` points to, like
+/// `CONTAINING_RECORD` in the Windows headers.
 ///
-/// With explicit total size:
+/// The pointer may be `*const` or `*mut`, and the returned pointer is of the same kind. It's
+/// computed with wrapping arithmetic, so the macro itself is safe, and it's up to the caller to
+/// only dereference it if the field really is part of such a struct.
+///
+/// # Examples
 ///
 /// ```rust
+/// # use offsetter::{container_of, offset};
 /// offset!(
-///     pub struct ExampleWithSize[0x20] {
-///         0x0 pub field1: u32,
-///         0x4 pub field2: u16,
-///         0x8 pub field3: u64
-///         // The struct will be padded to exactly 0x20 bytes
+///     #[offset(new)]
+///     pub struct LIST_ENTRY {
+///         0x0 pub flink: *mut LIST_ENTRY,
+///         0x8 pub blink: *mut LIST_ENTRY
+///     }
+/// );
+///
+/// offset!(
+///     #[offset(new)]
+///     pub struct DRIVER_ENTRY {
+///         0x0 pub id: u32,
+///         0x10 pub links: LIST_ENTRY
+///     }
+/// );
+///
+/// let mut entry = DRIVER_ENTRY::new(7, LIST_ENTRY::new(core::ptr::null_mut(), core::ptr::null_mut()));
+/// let links = core::ptr::addr_of_mut!(entry.links);
+/// let driver = container_of!(links, DRIVER_ENTRY, links);
+/// assert_eq!(unsafe { (*driver).id() }, 7);
+/// ```
+macro_rules! container_of {
+    ($pointer:expr, $struct_name:ty, $field:ident $(,)?) => {
+        ($pointer).cast::<u8>().wrapping_sub($crate::field_offset!($struct_name, $field)).cast::<$struct_name>()
+    };
+}
+
+#[macro_export]
+/// Walks a circular linked list like `LIST_ENTRY` in Windows, whose links are the `field` of
+/// structs declared with these macros, e.g. `list_entries!(head, DRIVER_ENTRY.links)`.
+///
+/// `head` points to the head of the list, and the result is a [`ListEntries`] iterator yielding a
+/// `*const DRIVER_ENTRY` for every struct in the list, found from its links like with
+/// `container_of!`. Since the list is read through raw pointers, the macro has to be used within
+/// an `unsafe` block. With a [`MemoryRead`] as the first argument, e.g.
+/// `list_entries!(process, head, DRIVER_ENTRY.links)`, `head` is an address and the result is a
+/// [`RemoteListEntries`] iterator yielding the address of every struct, e.g. to view it with a
+/// remote proxy. A corrupted list may never end, so walks of untrusted memory are best limited
+/// with `take`.
+///
+/// # Examples
+///
+/// ```rust
+/// # use offsetter::{list_entries, offset};
+/// offset!(
+///     #[offset(copy, new)]
+///     pub struct LIST_ENTRY {
+///         0x0 pub flink: *mut LIST_ENTRY,
+///         0x8 pub blink: *mut LIST_ENTRY
+///     }
+/// );
+///
+/// offset!(
+///     #[offset(new)]
+///     pub struct DRIVER_ENTRY {
+///         0x0 pub id: u32,
+///         0x10 pub links: LIST_ENTRY
 ///     }
 /// );
+///
+/// let null = core::ptr::null_mut();
+/// let mut head = LIST_ENTRY::new(null, null);
+/// let mut first = DRIVER_ENTRY::new(1, LIST_ENTRY::new(null, null));
+/// let mut second = DRIVER_ENTRY::new(2, LIST_ENTRY::new(null, null));
+/// let head_links: *mut LIST_ENTRY = &mut head;
+/// let first_links = core::ptr::addr_of_mut!(first.links);
+/// let second_links = core::ptr::addr_of_mut!(second.links);
+/// head.set_flink(first_links);
+/// unsafe {
+///     (*first_links).set_flink(second_links);
+///     (*second_links).set_flink(head_links);
+/// }
+///
+/// let ids: Vec<u32> = unsafe { list_entries!(head_links, DRIVER_ENTRY.links).map(|entry| (*entry).id()).collect() };
+/// assert_eq!(ids, [1, 2]);
 /// ```
 ///
-/// For platform compatibility (e.g., Windows driver structures):
+/// Walking a list in a memory dump:
 ///
 /// ```rust
+/// # use offsetter::{list_entries, offset, MemoryRead};
+/// struct Dump(Vec<u8>);
+///
+/// impl MemoryRead for Dump {
+///     type Error = ();
+///
+///     fn read(&self, address: u64, buffer: &mut [u8]) -> Result<(), ()> {
+///         let bytes = self.0.get(address as usize..address as usize + buffer.len()).ok_or(())?;
+///         buffer.copy_from_slice(bytes);
+///         Ok(())
+///     }
+/// }
+///
 /// offset!(
-///     pub struct DEVICE_OBJECT[0x150] {
-///         0x0 pub type_: u16,
-///         0x2 pub size: u16,
-///         0x8 pub next: *mut DEVICE_OBJECT,
-///         // More fields...
+///     pub struct DRIVER_ENTRY {
+///         0x0 pub id: u32,
+///         0x10 pub links: [u64; 2]
+///     }
+/// );
+///
+/// // The head at 0x8 links to the entry at 0x20, whose links at 0x30 lead back to the head.
+/// let mut dump = Dump(vec![0; 0x40]);
+/// dump.0[0x8..0x10].copy_from_slice(&0x30u64.to_le_bytes());
+/// dump.0[0x30..0x38].copy_from_slice(&0x8u64.to_le_bytes());
+///
+/// let entries: Result<Vec<u64>, ()> = list_entries!(dump, 0x8, DRIVER_ENTRY.links).collect();
+/// assert_eq!(entries, Ok(vec![0x20]));
+/// ```
+macro_rules! list_entries {
+    ($reader:expr, $head:expr, $($struct_name:ident)::+ . $field:ident $(,)?) => {
+        $crate::RemoteListEntries::new(&$reader, $head, $crate::field_offset!($($struct_name)::+, $field) as u64)
+    };
+
+    ($head:expr, $($struct_name:ident)::+ . $field:ident $(,)?) => {
+        $crate::ListEntries::<$($struct_name)::+>::new(($head).cast::<u8>(), $crate::field_offset!($($struct_name)::+, $field))
+    };
+}
+
+#[macro_export]
+/// Follows a chain of pointers through the fields of structs declared with these macros, e.g.
+/// `pointer_path!(world, UWorld.game_state => AGameState.players => u32)`.
+///
+/// Starting at the address `base`, every `Struct.field` reads the field of the struct at the
+/// current address, and `=>` follows its value to the next struct. The path ends either in a
+/// field, whose value is returned, or in a type, which the last field points to. Field values
+/// are turned into addresses with `as`, so they can be pointers as well as integers. The result
+/// is `None` if any address along the way is null.
+///
+/// The addresses are dereferenced without further checks, so the macro has to be used within an
+/// `unsafe` block. With a [`MemoryRead`] as the first argument, e.g.
+/// `pointer_path!(process, base, ...)`, the fields are read through it instead, and the result is
+/// a `Result<Option<T>, R::Error>`.
+///
+/// # Examples
+///
+/// ```rust
+/// # use offsetter::{offset, pointer_path};
+/// offset!(
+///     #[offset(new)]
+///     pub struct Settings {
+///         0x10 pub volume: *const u32
+///     }
+/// );
+///
+/// offset!(
+///     #[offset(new)]
+///     pub struct Game {
+///         0x8 pub settings: *const Settings,
+///         0x18 pub paused: u8
+///     }
+/// );
+///
+/// let volume = 80u32;
+/// let settings = Settings::new(&volume);
+/// let game = Game::new(&settings, 0);
+///
+/// let base = &game as *const Game;
+/// assert_eq!(unsafe { pointer_path!(base, Game.settings => Settings.volume => u32) }, Some(80));
+/// assert_eq!(unsafe { pointer_path!(base, Game.paused) }, Some(0));
+///
+/// let empty = Game::new(core::ptr::null(), 0);
+/// let base = &empty as *const Game;
+/// assert_eq!(unsafe { pointer_path!(base, Game.settings => Settings.volume => u32) }, None);
+/// ```
+///
+/// Reading through a [`MemoryRead`]:
+///
+/// ```rust
+/// # use offsetter::{offset, pointer_path, MemoryRead};
+/// struct Dump(Vec<u8>);
+///
+/// impl MemoryRead for Dump {
+///     type Error = ();
+///
+///     fn read(&self, address: u64, buffer: &mut [u8]) -> Result<(), ()> {
+///         let bytes = self.0.get(address as usize..address as usize + buffer.len()).ok_or(())?;
+///         buffer.copy_from_slice(bytes);
+///         Ok(())
+///     }
+/// }
+///
+/// offset!(
+///     pub struct Node {
+///         0x0 pub value: u32,
+///         0x8 pub next: u64
+///     }
+/// );
+///
+/// let mut dump = Dump(vec![0; 0x40]);
+/// dump.0[0x18..0x20].copy_from_slice(&0x20u64.to_le_bytes());
+/// dump.0[0x20..0x24].copy_from_slice(&7u32.to_le_bytes());
+///
+/// assert_eq!(unsafe { pointer_path!(dump, 0x10, Node.next => Node.value) }, Ok(Some(7)));
+/// assert_eq!(unsafe { pointer_path!(dump, 0x20, Node.next => Node.value) }, Ok(None));
+/// assert_eq!(unsafe { pointer_path!(dump, 0x3c, Node.next => Node.value) }, Err(()));
+/// ```
+macro_rules! pointer_path {
+    // Every step nests the rest of the path within the match on the current address.
+    (@local $address:expr; $($struct_name:ident)::+ . $field:ident => $($path:tt)+) => {
+        match ($address) as usize {
+            0 => None,
+            address => $crate::pointer_path!(@local (*(address as *const $($struct_name)::+)).$field(); $($path)+),
+        }
+    };
+
+    (@local $address:expr; $($struct_name:ident)::+ . $field:ident) => {
+        match ($address) as usize {
+            0 => None,
+            address => Some((*(address as *const $($struct_name)::+)).$field()),
+        }
+    };
+
+    (@local $address:expr; $target:ty) => {
+        match ($address) as usize {
+            0 => None,
+            address => Some((address as *const $target).read_unaligned()),
+        }
+    };
+
+    // The type of a field is taken from its getter, since it can't be named here.
+    (@remote $reader:ident $address:expr; $($struct_name:ident)::+ . $field:ident => $($path:tt)+) => {
+        match ($address) as u64 {
+            0 => Ok(None),
+            address => match $crate::pointer_path!(@read $reader address $($struct_name)::+ . $field) {
+                Ok(value) => $crate::pointer_path!(@remote $reader value; $($path)+),
+                Err(error) => Err(error),
+            },
+        }
+    };
+
+    (@remote $reader:ident $address:expr; $($struct_name:ident)::+ . $field:ident) => {
+        match ($address) as u64 {
+            0 => Ok(None),
+            address => $crate::pointer_path!(@read $reader address $($struct_name)::+ . $field).map(Some),
+        }
+    };
+
+    (@remote $reader:ident $address:expr; $target:ty) => {
+        match ($address) as u64 {
+            0 => Ok(None),
+            address => $crate::read_remote::<$target, _>($reader, address).map(Some),
+        }
+    };
+
+    (@read $reader:ident $address:ident $($struct_name:ident)::+ . $field:ident) => {
+        $crate::paste::paste! {
+            $crate::read_field($reader, $address + <$($struct_name)::+>::[<OFFSET_ $field:upper>] as u64, <$($struct_name)::+>::$field)
+        }
+    };
+
+    ($reader:expr, $base:expr, $($path:tt)+) => {{
+        let reader = &$reader;
+        $crate::pointer_path!(@remote reader $base; $($path)+)
+    }};
+
+    ($base:expr, $($path:tt)+) => {
+        $crate::pointer_path!(@local $base; $($path)+)
+    };
+}
+
+#[macro_export]
+/// Includes the `offset!` definitions a build script generated from a layout file with
+/// `offsetter::layout::build`, see the `layout` module.
+///
+/// The argument is the name of the layout file without its extension, e.g.
+/// `include_layout!("eprocess")` for the structs of `layouts/eprocess.layout`. Macros can't read
+/// files themselves, so the file is converted by the build script, which needs the `std` feature
+/// of offsetter in its build dependencies.
+///
+/// ```ignore
+/// // build.rs
+/// fn main() -> std::io::Result<()> {
+///     offsetter::layout::build("layouts/eprocess.layout")
+/// }
+///
+/// // src/lib.rs
+/// offsetter::include_layout!("eprocess");
+/// ```
+macro_rules! include_layout {
+    ($name:literal $(,)?) => {
+        include!(concat!(env!("OUT_DIR"), "/", $name, ".rs"));
+    };
+}
+
+#[macro_export]
+/// Compares the fields of two structs declared with these macros, e.g. the definitions of a
+/// structure for two builds of Windows.
+///
+/// The result is a [`LayoutDiff`] iterating over a [`FieldChange`] for every field that was
+/// added, removed, or moved to a different offset or size between the two, based on their
+/// `FIELDS` constants. Fields are matched by name, so a renamed field shows up as removed and
+/// added.
+///
+/// # Examples
+///
+/// ```rust
+/// # use offsetter::{layout_diff, offset, FieldChange, FieldInfo};
+/// offset!(
+///     pub struct KPROCESS_19041 {
+///         0x0 pub header: u64,
+///         0x28 pub directory_table_base: u64,
+///         0x30 pub thread_list_head: [u64; 2]
+///     }
+/// );
+///
+/// offset!(
+///     pub struct KPROCESS_22621 {
+///         0x0 pub header: u64,
+///         0x28 pub directory_table_base: u64,
+///         0x38 pub thread_list_head: [u64; 2],
+///         0x48 pub secure_state: u64
+///     }
+/// );
+///
+/// let changes: Vec<FieldChange> = layout_diff!(KPROCESS_19041, KPROCESS_22621).collect();
+/// assert_eq!(
+///     changes,
+///     [
+///         FieldChange::Moved {
+///             old: FieldInfo { name: "thread_list_head", offset: 0x30, size: 0x10 },
+///             new: FieldInfo { name: "thread_list_head", offset: 0x38, size: 0x10 },
+///         },
+///         FieldChange::Added(FieldInfo { name: "secure_state", offset: 0x48, size: 0x8 }),
+///     ]
+/// );
+/// assert_eq!(changes[0].to_string(), "`thread_list_head` moved from 0x30..0x40 to 0x38..0x48");
+/// ```
+macro_rules! layout_diff {
+    ($old:ty, $new:ty $(,)?) => {
+        $crate::LayoutDiff::new(<$old>::FIELDS, <$new>::FIELDS)
+    };
+}
+
+#[cfg(feature = "registry")]
+#[macro_export]
+/// Adds the layouts of structs declared with these macros to the registry of the `registry`
+/// module, where they can be listed and looked up by name, e.g.
+/// `register_layouts!(EPROCESS, KTHREAD, Wrapper<u64>)`. Registering the same invocation again
+/// does nothing.
+macro_rules! register_layouts {
+    ($($ty:ty),* $(,)?) => {{
+        $({
+            static REGISTRATION: $crate::registry::Registration = $crate::registry::Registration::new(&<$ty>::LAYOUT);
+            REGISTRATION.register();
+        })*
+    }};
+}
+
+#[macro_export]
+/// Fails the build unless the fields of a struct declared with these macros match a layout
+/// exported as JSON, e.g. from a symbol tool, so hand-written definitions stay in sync with the
+/// reverse-engineering database.
+///
+/// The layout is given as a string, usually `include_str!` of the exported file. It's an object
+/// with a member for the struct, either at its top level or in `user_types` like in Volatility's
+/// symbol tables, which may have a `size` and has a `fields` object with a member for every field.
+/// Those may have an `offset` and a `size`, as decimal integers:
+///
+/// ```json
+/// {
+///     "_EPROCESS": {
+///         "size": 2624,
+///         "fields": {
+///             "UniqueProcessId": { "offset": 1088, "size": 8 },
+///             "Peb": { "offset": 1360 }
+///         }
+///     }
+/// }
+/// ```
+///
+/// Every field of the struct has to be in the layout, while the layout may describe more fields
+/// than the struct declares. Names are compared ignoring case and underscores, so `_EPROCESS` and
+/// `UniqueProcessId` match `EPROCESS` and `unique_process_id`, and the name of the struct in the
+/// layout can be given after it, e.g. `verify_layout!(KPROCESS_19041, "_KPROCESS", ...)`. If the
+/// layout has a `size`, the struct has to be declared with that total size. A mismatch is a
+/// compile error naming the field, e.g. `` `EPROCESS::peb` is at 0x548, but the layout puts it
+/// at 0x550 ``.
+///
+/// # Examples
+///
+/// ```rust
+/// # use offsetter::{offset, verify_layout};
+/// offset!(
+///     pub struct EPROCESS[0xA40] {
+///         0x440 pub unique_process_id: usize,
+///         0x550 pub peb: *mut u8
+///     }
+/// );
+///
+/// verify_layout!(EPROCESS, r#"{
+///     "_EPROCESS": {
+///         "size": 2624,
+///         "fields": {
+///             "UniqueProcessId": { "offset": 1088, "size": 8 },
+///             "Peb": { "offset": 1360 }
+///         }
+///     }
+/// }"#);
+/// ```
+///
+/// A definition written for another build doesn't compile:
+///
+/// ```rust,compile_fail,E0080
+/// # use offsetter::{offset, verify_layout};
+/// offset!(
+///     pub struct KPROCESS_19041 {
+///         0x0 pub header: u64,
+///         0x38 pub thread_list_head: [u64; 2]
+///     }
+/// );
+///
+/// verify_layout!(KPROCESS_19041, "_KPROCESS", r#"{
+///     "_KPROCESS": { "fields": { "Header": { "offset": 0 }, "ThreadListHead": { "offset": 48 } } }
+/// }"#);
+/// ```
+macro_rules! verify_layout {
+    ($struct_name:ident, $key:literal, $layout:expr $(,)?) => {
+        const _: () = $crate::verify_layout(stringify!($struct_name), $key, $struct_name::SIZE, $struct_name::FIELDS, $layout);
+    };
+
+    ($struct_name:ident, $layout:expr $(,)?) => {
+        const _: () = $crate::verify_layout(stringify!($struct_name), stringify!($struct_name), $struct_name::SIZE, $struct_name::FIELDS, $layout);
+    };
+}
+
+#[macro_export]
+/// Generates tests verifying the layout of a struct declared with these macros.
+///
+/// `offset_tests!(Example)` expands to three `#[test]` functions, named after the struct in snake
+/// case, which assert that
+///
+/// - every field stored in the struct is at its declared offset,
+/// - the struct is as large as its declared total size, or ends at its last field otherwise,
+/// - the struct has an alignment of 1, so it can be viewed at any address.
+///
+/// Unlike the `checked` feature, the tests don't need a nightly compiler and can run in a test
+/// module of the crate using the struct even when the feature is off. Structs with type
+/// parameters are tested with concrete arguments, e.g. `offset_tests!(Wrapper<u64>)`.
+///
+/// # Examples
+///
+/// ```rust
+/// # use offsetter::{offset, offset_tests};
+/// offset!(
+///     pub struct Example[0x10] {
+///         0x0 pub field1: u32,
+///         0x8 pub field2: u64
 ///     }
 /// );
+///
+/// #[cfg(test)]
+/// mod tests {
+///     use super::*;
+///
+///     offset_tests!(Example);
+/// }
 /// ```
-macro_rules! offset {
-    (@guard_with_size ($current_offset:expr, $struct_size:expr) -> {$(#[$attr:meta])* $vis:vis struct $name:ident $(($amount:expr, $vis_field:vis $id:ident: $ty:ty))*}) => {
+macro_rules! offset_tests {
+    ($name:ident $(<$($argument:ty),+ $(,)?>)?) => {
         $crate::paste::paste! {
-            #[repr(C, packed)]
-            $(#[$attr])* $vis struct $name {
-                $([<_pad $id>]: [u8;$amount], $vis_field $id: $ty,)*
-                _remaining_padding: [u8; $struct_size - $current_offset]
+            #[test]
+            fn [<$name:snake _field_offsets>]() {
+                for &(field, declared, actual) in $name$(::<$($argument),+>)?::__LAYOUT_OFFSETS {
+                    assert_eq!(actual, declared, "`{}::{}` is at offset {:#x} instead of {:#x}", stringify!($name), field, actual, declared);
+                }
+            }
+
+            #[test]
+            fn [<$name:snake _size>]() {
+                let actual = core::mem::size_of::<$name$(<$($argument),+>)?>();
+                let declared = $name$(::<$($argument),+>)?::__LAYOUT_SIZE;
+                assert_eq!(actual, declared, "`{}` is {:#x} bytes large instead of {:#x}", stringify!($name), actual, declared);
+            }
+
+            #[test]
+            fn [<$name:snake _alignment>]() {
+                let alignment = core::mem::align_of::<$name$(<$($argument),+>)?>();
+                assert_eq!(alignment, 1, "`{}` is aligned to {} bytes instead of being packed", stringify!($name), alignment);
             }
         }
     };
+}
 
-    (@guard ($current_offset:expr) -> {$(#[$attr:meta])* $vis:vis struct $name:ident $(($amount:expr, $vis_field:vis $id:ident: $ty:ty))*}) => {
-        $crate::paste::paste! {
-            #[repr(C, packed)]
-            $(#[$attr])* $vis struct $name { $([<_pad $id>]: [u8;$amount], $vis_field $id: $ty),* }
+// With the `json` feature, structs also get a `layout_json` method next to `describe`.
+#[cfg(feature = "json")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! offset_json {
+    ($name:ident) => {
+        /// Writes the size of the struct and the name, offset and size of every field except
+        /// reserved regions as JSON, in the format `verify_layout!` reads, e.g. for external tools
+        /// to check the layouts a binary was built with.
+        #[allow(dead_code)]
+        pub fn layout_json(writer: &mut impl core::fmt::Write) -> core::fmt::Result {
+            $crate::write_layout_json(writer, stringify!($name), Self::SIZE, Self::FIELDS)
         }
     };
+}
+
+#[cfg(not(feature = "json"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! offset_json {
+    ($($input:tt)*) => {};
+}
+
+// With the `defmt` feature, the `defmt` option implements `defmt::Format` in the same shape as the
+// Debug implementation. The macros of defmt refer to `defmt::` relative to where they're used, so
+// the implementation brings offsetter's `defmt` into scope.
+#[cfg(feature = "defmt")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! offset_defmt {
+    ($name:ident [$($lifetime:lifetime),*] [$($param:ident $(: $bound:path)?),*] {$(([$($kind:tt)*] [$($predicate:tt)?] $attrs:tt [$($offset:tt)*] $default:tt $vis_field:vis $id:ident: $ty:ty))*}) => {
+        impl<$($lifetime,)* $($param $(: $bound)?),*> $crate::__private::defmt::Format for $name<$($lifetime,)* $($param),*> where $($param: $crate::__private::defmt::Format),* {
+            fn format(&self, f: $crate::__private::defmt::Formatter) {
+                use $crate::__private::defmt;
 
-    (@guard ($current_offset:expr, $offset:literal $vis_field:vis $id:ident: $ty:ty $(,)?) -> {$($output:tt)*}) => {
-        offset!(@guard ($offset + core::mem::size_of::<$ty>()) -> {$($output)* ($offset - ($current_offset), $vis_field $id: $ty)});
+                defmt::write!(f, "{=str} {{", stringify!($name));
+                #[allow(unused_mut, unused_assignments)]
+                let mut separator = " ";
+                $($crate::offset_defmt!(@field f separator self [$($kind)*] [$($predicate)?] $id);)*
+                let _ = separator;
+                defmt::write!(f, " }}");
+            }
+        }
     };
 
-    (@guard ($current_offset:expr, $offset:literal $vis_field:vis $id:ident: $ty:ty, $($next:tt)+) -> {$($output:tt)*}) => {
-        offset!(@guard ($offset + core::mem::size_of::<$ty>(), $($next)+) -> {$($output)* ($offset - ($current_offset), $vis_field $id: $ty)});
+    (@field $f:ident $separator:ident $this:ident [reserved] $predicate:tt $id:ident) => {};
+
+    (@field $f:ident $separator:ident $this:ident [flexible $elem:tt] $predicate:tt $id:ident) => {};
+
+    (@field $f:ident $separator:ident $this:ident [$($kind:tt)*] [$($predicate:tt)?] $id:ident) => {
+        $(#[cfg $predicate])?
+        {
+            defmt::write!($f, "{=str}{=str}: {}", $separator, stringify!($id), $crate::offset!(@read $this [$($kind)*] $id));
+            $separator = ", ";
+        }
     };
+}
 
-    (@guard_with_size ($current_offset:expr, $struct_size:expr, $offset:literal $vis_field:vis $id:ident: $ty:ty $(,)?) -> {$($output:tt)*}) => {
-        offset!(@guard_with_size ($offset + core::mem::size_of::<$ty>(), $struct_size) -> {$($output)* ($offset - ($current_offset), $vis_field $id: $ty)});
+#[cfg(not(feature = "defmt"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! offset_defmt {
+    ($($input:tt)*) => {
+        compile_error!("the `defmt` option requires the `defmt` feature of offsetter");
     };
+}
 
-    (@guard_with_size ($current_offset:expr, $struct_size:expr, $offset:literal $vis_field:vis $id:ident: $ty:ty, $($next:tt)+) -> {$($output:tt)*}) => {
-        offset!(@guard_with_size ($offset + core::mem::size_of::<$ty>(), $struct_size, $($next)+) -> {$($output)* ($offset - ($current_offset), $vis_field $id: $ty)});
+// With the `ufmt` feature, the `ufmt` option implements `ufmt::uDebug` in the same shape as the
+// Debug implementation.
+#[cfg(feature = "ufmt")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! offset_ufmt {
+    ($name:ident [$($lifetime:lifetime),*] [$($param:ident $(: $bound:path)?),*] {$(([$($kind:tt)*] [$($predicate:tt)?] $attrs:tt [$($offset:tt)*] $default:tt $vis_field:vis $id:ident: $ty:ty))*}) => {
+        impl<$($lifetime,)* $($param $(: $bound)?),*> $crate::__private::ufmt::uDebug for $name<$($lifetime,)* $($param),*> where $($param: $crate::__private::ufmt::uDebug),* {
+            fn fmt<W>(&self, f: &mut $crate::__private::ufmt::Formatter<'_, W>) -> core::result::Result<(), W::Error>
+            where
+                W: $crate::__private::ufmt::uWrite + ?Sized,
+            {
+                let mut debug = f.debug_struct(stringify!($name))?;
+                $($crate::offset_ufmt!(@field debug self [$($kind)*] [$($predicate)?] $id);)*
+                debug.finish()
+            }
+        }
     };
 
+    (@field $debug:ident $this:ident [reserved] $predicate:tt $id:ident) => {};
 
-    ($(#[$attr:meta])* $vis:vis struct $struct_name:ident {$($input:tt)*}) => {
-        offset!(@guard (0, $($input)*) -> {$(#[$attr])* $vis struct $struct_name});
-        $crate::offset_checker!($struct_name {$($input)*});
+    (@field $debug:ident $this:ident [flexible $elem:tt] $predicate:tt $id:ident) => {};
+
+    (@field $debug:ident $this:ident [$($kind:tt)*] [$($predicate:tt)?] $id:ident) => {
+        $(#[cfg $predicate])?
+        $debug.field(stringify!($id), &$crate::offset!(@read $this [$($kind)*] $id))?;
     };
+}
 
-    ($(#[$attr:meta])* $vis:vis struct $struct_name:ident[$struct_size:expr] {$($input:tt)*}) => {
-        offset!(@guard_with_size (0, $struct_size, $($input)*) -> {$(#[$attr])* $vis struct $struct_name});
-        $crate::offset_checker!($struct_name {$($input)*});
+#[cfg(not(feature = "ufmt"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! offset_ufmt {
+    ($($input:tt)*) => {
+        compile_error!("the `ufmt` option requires the `ufmt` feature of offsetter");
     };
 }
 
+// With the `serde` feature, the `serialize` and `deserialize` options implement the serde traits
+// over the fields.
+#[cfg(feature = "serde")]
+#[doc(hidden)]
 #[macro_export]
-/// Creates a struct with fields at specific offsets and a custom Debug implementation.
-///
-/// This macro works the same as the `offset!` macro but also implements the Debug trait
-/// in a way that hides padding fields. This gives you cleaner debug output that shows
-/// only the actual data fields, not the padding.
-///
-/// # Optional Total Size
-///
-/// You can optionally specify the total size of the struct by adding a size value in
-/// square brackets after the struct name. This will ensure the struct has exactly that
-/// size by adding padding at the end.
-///
-/// # Examples
-///
-/// Basic usage:
-///
-/// ```rust
-/// offset_debug!(
-///     pub struct Example {
-///         0x0 pub field1: u32,
-///         0x4 pub field2: u16,
-///         0x8 pub field3: u64
-///     }
-/// );
-///
-/// // Debug output will be something like:
-/// // Example { field1: 1, field2: 2, field3: 3 }
-/// // (no padding fields shown)
-/// ```
-///
-/// With explicit total size:
-///
-/// ```rust
-/// offset_debug!(
-///     pub struct KernelStructure[0x100] {
-///         0x00 pub header: u32,
-///         0x08 pub pointer: *mut u8,
-///         0x10 pub flags: u32
-///         // The struct will be padded to exactly 0x100 bytes
-///         // Debug output will not show the padding
-///     }
-/// );
-/// ```
-///
-/// Real-world example for Windows kernel structures:
-///
-/// ```rust
-/// offset_debug!(
-///     pub struct DRIVER_OBJECT[0x150] {
-///         0x0  pub type_: u16,
-///         0x2  pub size: u16,
-///         0x8  pub device_object: *mut DEVICE_OBJECT,
-///         0x10 pub flags: u32,
-///         0x18 pub driver_start: usize,
-///         // More fields...
-///     }
-/// );
-/// ```
-macro_rules! offset_debug {
+macro_rules! offset_serde {
+    (@serialize $name:ident [$($lifetime:lifetime),*] [$($param:ident $(: $bound:path)?),*] {$(([$($kind:tt)*] [$($predicate:tt)?] $attrs:tt [$($offset:tt)*] $default:tt $vis_field:vis $id:ident: $ty:ty))*}) => {
+        impl<$($lifetime,)* $($param $(: $bound)?),*> $crate::__private::serde::Serialize for $name<$($lifetime,)* $($param),*> where $($param: $crate::__private::serde::Serialize),* {
+            fn serialize<S: $crate::__private::serde::Serializer>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error> {
+                use $crate::__private::serde::ser::SerializeStruct;
+
+                let mut state = serializer.serialize_struct(stringify!($name), 0 $(+ $crate::offset_serde!(@count [$($kind)*] [$($predicate)?]))*)?;
+                $($crate::offset_serde!(@serialize_field state self [$($kind)*] [$($predicate)?] $id);)*
+                state.end()
+            }
+        }
+    };
 
-    (@guard_with_size ($current_offset:expr, $struct_size:expr) -> {$(#[$attr:meta])* $vis:vis struct $name:ident $(($amount:expr, $vis_field:vis $id:ident: $ty:ty))*}) => {
+    (@count [reserved] $predicate:tt) => {
+        0
+    };
+
+    (@count [flexible $elem:tt] $predicate:tt) => {
+        0
+    };
+
+    (@count $kind:tt [$($predicate:tt)?]) => {
+        $(if !cfg! $predicate { 0 } else)? { 1 }
+    };
+
+    (@serialize_field $state:ident $this:ident [reserved] $predicate:tt $id:ident) => {};
+
+    (@serialize_field $state:ident $this:ident [flexible $elem:tt] $predicate:tt $id:ident) => {};
+
+    (@serialize_field $state:ident $this:ident [$($kind:tt)*] [$($predicate:tt)?] $id:ident) => {
+        $(#[cfg $predicate])?
+        $state.serialize_field(stringify!($id), &$crate::offset!(@read $this [$($kind)*] $id))?;
+    };
+
+    // The fields are deserialized into a plain struct first, and then written through `new` and
+    // the setters, which zeroes everything else. The derive of the plain struct refers to serde by
+    // the path in its `crate` attribute, which is resolved where it's used, so offsetter's serde
+    // is imported next to it.
+    (@deserialize $name:ident [$($lifetime:lifetime),*] [$($param:ident $(: $bound:path)?),*] $fields:tt) => {
+        const _: () = {
+            use $crate::__private::serde as __serde;
+
+            $crate::offset_serde!(@shadow $name [$($lifetime),*] [$($param $(: $bound)?),*] [] $fields);
+
+            $crate::paste::paste! {
+                impl<'de, $($lifetime,)* $($param $(: $bound)?),*> __serde::Deserialize<'de> for $name<$($lifetime,)* $($param),*> where $($param: __serde::Deserialize<'de>),* {
+                    fn deserialize<D: __serde::Deserializer<'de>>(deserializer: D) -> core::result::Result<Self, D::Error> {
+                        let fields = <[<__ $name Fields>]<$($lifetime,)* $($param),*> as __serde::Deserialize<'de>>::deserialize(deserializer)?;
+                        Ok($crate::offset_serde!(@build fields value [] [] $fields))
+                    }
+                }
+            }
+        };
+    };
+
+    (@shadow $name:ident [$($lifetime:lifetime),*] [$($param:ident $(: $bound:path)?),*] [$($field:tt)*] {}) => {
         $crate::paste::paste! {
-            #[repr(C, packed)]
-            $(#[$attr])* $vis struct $name {
-                $([<_pad $id>]: [u8;$amount], $vis_field $id: $ty,)*
-                _remaining_padding: [u8; $struct_size - $current_offset]
+            #[doc(hidden)]
+            #[allow(non_camel_case_types)]
+            #[derive(__serde::Deserialize)]
+            #[serde(crate = "__serde")]
+            struct [<__ $name Fields>]<$($lifetime,)* $($param $(: $bound)?),*> {
+                $($field)*
+                #[serde(skip)]
+                _marker: core::marker::PhantomData<($(&$lifetime (),)* $($param,)*)>,
             }
         }
+    };
 
-        impl core::fmt::Debug for $name {
-            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-                f.debug_struct(stringify!($name))
-                 $(.field(stringify!($id), unsafe { &core::ptr::read_unaligned(core::ptr::addr_of!(self.$id))}))*
-                 .finish()
+    (@shadow $name:ident $lifetimes:tt $params:tt $shadow:tt {([reserved] $($entry:tt)*) $($fields:tt)*}) => {
+        $crate::offset_serde!(@shadow $name $lifetimes $params $shadow {$($fields)*});
+    };
+
+    (@shadow $name:ident $lifetimes:tt $params:tt $shadow:tt {([flexible $elem:tt] $($entry:tt)*) $($fields:tt)*}) => {
+        $crate::offset_serde!(@shadow $name $lifetimes $params $shadow {$($fields)*});
+    };
+
+    (@shadow $name:ident $lifetimes:tt $params:tt [$($field:tt)*] {([stride $elem:tt $count:tt $stride:tt] [$($predicate:tt)?] $attrs:tt $offset:tt $default:tt $vis_field:vis $id:ident: $ty:ty) $($fields:tt)*}) => {
+        $crate::offset_serde!(@shadow $name $lifetimes $params [$($field)* $(#[cfg $predicate])? $id: [$elem; $count],] {$($fields)*});
+    };
+
+    (@shadow $name:ident $lifetimes:tt $params:tt [$($field:tt)*] {($kind:tt [$($predicate:tt)?] $attrs:tt $offset:tt $default:tt $vis_field:vis $id:ident: $ty:ty) $($fields:tt)*}) => {
+        $crate::offset_serde!(@shadow $name $lifetimes $params [$($field)* $(#[cfg $predicate])? $id: $ty,] {$($fields)*});
+    };
+
+    (@build $fields:ident $value:ident [$($argument:tt)*] [$($set:tt)*] {}) => {{
+        #[allow(unused_mut)]
+        let mut $value = Self::__new($($argument)*);
+        $($set)*
+        $value
+    }};
+
+    (@build $fields:ident $value:ident [$($argument:tt)*] $sets:tt {([field] [$($predicate:tt)?] $attrs:tt $offset:tt $default:tt $vis_field:vis $id:ident: $ty:ty) $($rest:tt)*}) => {
+        $crate::offset_serde!(@build $fields $value [$($argument)* $(#[cfg $predicate])? $fields.$id,] $sets {$($rest)*})
+    };
+
+    (@build $fields:ident $value:ident [$($argument:tt)*] $sets:tt {([atomic] [$($predicate:tt)?] $attrs:tt $offset:tt $default:tt $vis_field:vis $id:ident: $ty:ty) $($rest:tt)*}) => {
+        $crate::offset_serde!(@build $fields $value [$($argument)* $(#[cfg $predicate])? $fields.$id,] $sets {$($rest)*})
+    };
+
+    (@build $fields:ident $value:ident $arguments:tt $sets:tt {([reserved] $($entry:tt)*) $($rest:tt)*}) => {
+        $crate::offset_serde!(@build $fields $value $arguments $sets {$($rest)*})
+    };
+
+    (@build $fields:ident $value:ident $arguments:tt $sets:tt {([flexible $elem:tt] $($entry:tt)*) $($rest:tt)*}) => {
+        $crate::offset_serde!(@build $fields $value $arguments $sets {$($rest)*})
+    };
+
+    (@build $fields:ident $value:ident $arguments:tt [$($set:tt)*] {([stride $elem:tt $count:tt $stride:tt] [$($predicate:tt)?] $attrs:tt $offset:tt $default:tt $vis_field:vis $id:ident: $ty:ty) $($rest:tt)*}) => {
+        $crate::offset_serde!(@build $fields $value $arguments [$($set)*
+            $(#[cfg $predicate])?
+            for (index, element) in $fields.$id.into_iter().enumerate() {
+                $crate::paste::paste!($value.[<set_ $id>](index, element));
+            }
+        ] {$($rest)*})
+    };
+
+    (@build $fields:ident $value:ident $arguments:tt [$($set:tt)*] {($kind:tt [$($predicate:tt)?] $attrs:tt $offset:tt $default:tt $vis_field:vis $id:ident: $ty:ty) $($rest:tt)*}) => {
+        $crate::offset_serde!(@build $fields $value $arguments [$($set)*
+            $(#[cfg $predicate])?
+            $crate::paste::paste!($value.[<set_ $id>]($fields.$id));
+        ] {$($rest)*})
+    };
+}
+
+#[cfg(not(feature = "serde"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! offset_serde {
+    (@$option:ident $($input:tt)*) => {
+        compile_error!(concat!("the `", stringify!($option), "` option requires the `serde` feature of offsetter"));
+    };
+}
+
+// With the `rkyv` feature, the `rkyv` option archives the struct as its bytes, which are portable
+// unlike the fields themselves. Reading the struct back from an archive is as unsafe as reading it
+// from any other bytes, so there's no `Deserialize` implementation.
+#[cfg(feature = "rkyv")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! offset_rkyv {
+    ($name:ident [] []) => {
+        impl $crate::__private::rkyv::Archive for $name {
+            type Archived = [u8; $name::SIZE];
+            type Resolver = ();
+
+            fn resolve(&self, _resolver: Self::Resolver, out: $crate::__private::rkyv::Place<Self::Archived>) {
+                out.write(unsafe { *(self as *const Self).cast::<[u8; $name::SIZE]>() });
+            }
+        }
+
+        impl<S: $crate::__private::rkyv::rancor::Fallible + ?Sized> $crate::__private::rkyv::Serialize<S> for $name {
+            fn serialize(&self, _serializer: &mut S) -> core::result::Result<Self::Resolver, S::Error> {
+                Ok(())
             }
         }
     };
 
+    ($name:ident $lifetimes:tt $params:tt) => {
+        compile_error!(concat!("the `rkyv` option doesn't support `", stringify!($name), "`, since it has generic parameters"));
+    };
+}
 
-    (@guard ($current_offset:expr) -> {$(#[$attr:meta])* $vis:vis struct $name:ident $(($amount:expr, $vis_field:vis $id:ident: $ty:ty))*}) => {
-        $crate::paste::paste! {
-            #[repr(C, packed)]
-            $(#[$attr])* $vis struct $name { $([<_pad $id>]: [u8;$amount], $vis_field $id: $ty),* }
+#[cfg(not(feature = "rkyv"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! offset_rkyv {
+    ($($input:tt)*) => {
+        compile_error!("the `rkyv` option requires the `rkyv` feature of offsetter");
+    };
+}
+
+// With the `arbitrary` feature, the `arbitrary` option fills the fields with fuzz data through
+// `new` and the setters, so the padding stays zero.
+#[cfg(feature = "arbitrary")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! offset_arbitrary {
+    ($name:ident [$($lifetime:lifetime),*] [$($param:ident $(: $bound:path)?),*] $fields:tt) => {
+        impl<'arbitrary, $($lifetime,)* $($param $(: $bound)?),*> $crate::__private::arbitrary::Arbitrary<'arbitrary> for $name<$($lifetime,)* $($param),*> where $($param: $crate::__private::arbitrary::Arbitrary<'arbitrary>),* {
+            fn arbitrary(unstructured: &mut $crate::__private::arbitrary::Unstructured<'arbitrary>) -> $crate::__private::arbitrary::Result<Self> {
+                Ok($crate::offset_arbitrary!(@build unstructured value [] [] $fields))
+            }
         }
+    };
 
-        impl core::fmt::Debug for $name {
-            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-                f.debug_struct(stringify!($name))
-                 $(.field(stringify!($id), unsafe { &core::ptr::read_unaligned(core::ptr::addr_of!(self.$id))}))*
-                 .finish()
+    (@build $unstructured:ident $value:ident [$($argument:tt)*] [$($set:tt)*] {}) => {{
+        #[allow(unused_mut)]
+        let mut $value = Self::__new($($argument)*);
+        $($set)*
+        $value
+    }};
+
+    (@build $unstructured:ident $value:ident [$($argument:tt)*] $sets:tt {([field] [$($predicate:tt)?] $attrs:tt $offset:tt $default:tt $vis_field:vis $id:ident: $ty:ty) $($rest:tt)*}) => {
+        $crate::offset_arbitrary!(@build $unstructured $value [$($argument)* $(#[cfg $predicate])? $unstructured.arbitrary()?,] $sets {$($rest)*})
+    };
+
+    (@build $unstructured:ident $value:ident [$($argument:tt)*] $sets:tt {([atomic] [$($predicate:tt)?] $attrs:tt $offset:tt $default:tt $vis_field:vis $id:ident: $ty:ty) $($rest:tt)*}) => {
+        $crate::offset_arbitrary!(@build $unstructured $value [$($argument)* $(#[cfg $predicate])? $unstructured.arbitrary()?,] $sets {$($rest)*})
+    };
+
+    (@build $unstructured:ident $value:ident $arguments:tt $sets:tt {([reserved] $($entry:tt)*) $($rest:tt)*}) => {
+        $crate::offset_arbitrary!(@build $unstructured $value $arguments $sets {$($rest)*})
+    };
+
+    (@build $unstructured:ident $value:ident $arguments:tt $sets:tt {([flexible $elem:tt] $($entry:tt)*) $($rest:tt)*}) => {
+        $crate::offset_arbitrary!(@build $unstructured $value $arguments $sets {$($rest)*})
+    };
+
+    (@build $unstructured:ident $value:ident $arguments:tt [$($set:tt)*] {([stride $elem:tt $count:tt $stride:tt] [$($predicate:tt)?] $attrs:tt $offset:tt $default:tt $vis_field:vis $id:ident: $ty:ty) $($rest:tt)*}) => {
+        $crate::offset_arbitrary!(@build $unstructured $value $arguments [$($set)*
+            $(#[cfg $predicate])?
+            for index in 0..$count {
+                $crate::paste::paste!($value.[<set_ $id>](index, $unstructured.arbitrary()?));
+            }
+        ] {$($rest)*})
+    };
+
+    (@build $unstructured:ident $value:ident $arguments:tt [$($set:tt)*] {($kind:tt [$($predicate:tt)?] $attrs:tt $offset:tt $default:tt $vis_field:vis $id:ident: $ty:ty) $($rest:tt)*}) => {
+        $crate::offset_arbitrary!(@build $unstructured $value $arguments [$($set)*
+            $(#[cfg $predicate])?
+            $crate::paste::paste!($value.[<set_ $id>]($unstructured.arbitrary()?));
+        ] {$($rest)*})
+    };
+}
+
+#[cfg(not(feature = "arbitrary"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! offset_arbitrary {
+    ($($input:tt)*) => {
+        compile_error!("the `arbitrary` option requires the `arbitrary` feature of offsetter");
+    };
+}
+
+// With the `kani` feature, the `kani` option generates proof harnesses that are only compiled by
+// `cargo kani`, which sets `cfg(kani)` and provides the `kani` crate.
+#[cfg(feature = "kani")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! offset_kani {
+    ($name:ident [] [] $fields:tt) => {
+        $crate::paste::paste! {
+            #[cfg(kani)]
+            #[kani::proof]
+            fn [<$name:snake _layout_proof>]() {
+                for &(_, declared, actual) in $name::__LAYOUT_OFFSETS {
+                    assert!(declared == actual);
+                }
+
+                let spans: &[(usize, usize)] = $crate::offset_kani!(@spans $name [] $fields);
+                let mut index = 1;
+                while index < spans.len() {
+                    assert!(spans[index - 1].1 <= spans[index].0);
+                    index += 1;
+                }
+            }
+
+            #[cfg(kani)]
+            #[kani::proof]
+            fn [<$name:snake _bytes_proof>]() {
+                let bytes: [u8; $name::SIZE] = kani::any();
+                let value = unsafe { core::ptr::read_unaligned(bytes.as_ptr().cast::<$name>()) };
+                assert!(unsafe { *(&value as *const $name).cast::<[u8; $name::SIZE]>() } == bytes);
             }
         }
     };
 
+    ($name:ident $lifetimes:tt $params:tt $fields:tt) => {
+        compile_error!("the `kani` option doesn't support structs with lifetimes or type parameters");
+    };
+
+    // The start and end of every field stored in the struct, in declaration order. Overlapping
+    // fields and bitfields alias other fields, and flexible arrays are empty.
+    (@spans $name:ident [$($span:tt)*] {}) => {
+        &[$($span)*]
+    };
+
+    (@spans $name:ident $spans:tt {([overlap] $($entry:tt)*) $($rest:tt)*}) => {
+        $crate::offset_kani!(@spans $name $spans {$($rest)*})
+    };
+
+    (@spans $name:ident $spans:tt {([bits $low:tt $high:tt] $($entry:tt)*) $($rest:tt)*}) => {
+        $crate::offset_kani!(@spans $name $spans {$($rest)*})
+    };
+
+    (@spans $name:ident $spans:tt {([flexible $elem:tt] $($entry:tt)*) $($rest:tt)*}) => {
+        $crate::offset_kani!(@spans $name $spans {$($rest)*})
+    };
+
+    (@spans $name:ident [$($span:tt)*] {([stride $elem:tt $count:tt $stride:tt] [$($predicate:tt)?] $attrs:tt [$($offset:tt)*] $default:tt $vis_field:vis $id:ident: $ty:ty) $($rest:tt)*}) => {
+        $crate::offset_kani!(@spans $name [$($span)* $(#[cfg $predicate])? (
+            $($offset)*,
+            ($($offset)*) + ($count - 1) * ($stride) + core::mem::size_of::<$elem>(),
+        ),] {$($rest)*})
+    };
+
+    (@spans $name:ident [$($span:tt)*] {($kind:tt [$($predicate:tt)?] $attrs:tt $offset:tt $default:tt $vis_field:vis $id:ident: $ty:ty) $($rest:tt)*}) => {
+        $crate::offset_kani!(@spans $name [$($span)* $(#[cfg $predicate])? (
+            core::mem::offset_of!($name, $id),
+            core::mem::offset_of!($name, $id) + core::mem::size_of::<$ty>(),
+        ),] {$($rest)*})
+    };
+}
+
+#[cfg(not(feature = "kani"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! offset_kani {
+    ($($input:tt)*) => {
+        compile_error!("the `kani` option requires the `kani` feature of offsetter");
+    };
+}
 
-    (@guard ($current_offset:expr, $offset:literal $vis_field:vis $id:ident: $ty:ty $(,)?) -> {$($output:tt)*}) => {
-        offset_debug!(@guard ($offset + core::mem::size_of::<$ty>()) -> {$($output)* ($offset - ($current_offset), $vis_field $id: $ty)});
+// With the `bytemuck` feature, the `pod` option implements `Zeroable` and `Pod`. The padding is
+// made of plain bytes, so that's sound as long as every declared type is `Pod` itself, which is
+// asserted by a function that is never called.
+#[cfg(feature = "bytemuck")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! offset_bytemuck {
+    ($name:ident [$($lifetime:lifetime),*] [$($param:ident $(: $bound:path)?),*] {$(([$($kind:tt)*] [$($predicate:tt)?] $attrs:tt [$($offset:tt)*] $default:tt $vis_field:vis $id:ident: $ty:ty))*}) => {
+        unsafe impl<$($lifetime,)* $($param: $crate::__private::bytemuck::Zeroable $(+ $bound)?),*> $crate::__private::bytemuck::Zeroable for $name<$($lifetime,)* $($param),*> {}
+
+        unsafe impl<$($lifetime,)* $($param: $crate::__private::bytemuck::Pod $(+ $bound)?),*> $crate::__private::bytemuck::Pod for $name<$($lifetime,)* $($param),*> {}
+
+        const _: () = {
+            fn assert_pod<T: $crate::__private::bytemuck::Pod>() {}
+
+            #[allow(dead_code)]
+            fn assert_fields<$($lifetime,)* $($param: $crate::__private::bytemuck::Pod $(+ $bound)?),*>() {
+                $($(#[cfg $predicate])? assert_pod::<$crate::offset_bytemuck!(@stored [$($kind)*] $ty)>();)*
+            }
+        };
+    };
+
+    (@stored [atomic] $ty:ty) => {
+        <$ty as $crate::Atomic>::Atomic
     };
 
-    (@guard ($current_offset:expr, $offset:literal $vis_field:vis $id:ident: $ty:ty, $($next:tt)+) -> {$($output:tt)*}) => {
-        offset_debug!(@guard ($offset + core::mem::size_of::<$ty>(), $($next)+) -> {$($output)* ($offset - ($current_offset), $vis_field $id: $ty)});
+    (@stored $kind:tt $ty:ty) => {
+        $ty
     };
+}
 
+#[cfg(not(feature = "bytemuck"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! offset_bytemuck {
+    ($($input:tt)*) => {
+        compile_error!("the `pod` option requires the `bytemuck` feature of offsetter");
+    };
+}
 
-    (@guard_with_size ($current_offset:expr, $struct_size:expr, $offset:literal $vis_field:vis $id:ident: $ty:ty $(,)?) -> {$($output:tt)*}) => {
-        offset_debug!(@guard_with_size ($offset + core::mem::size_of::<$ty>(), $struct_size) -> {$($output)* ($offset - ($current_offset), $vis_field $id: $ty)});
+// With the `zerocopy` feature, the `zerocopy` option adds the zerocopy derives to the struct
+// before it's emitted. The derives are only valid if every field type qualifies, which they
+// check themselves. They take the path of zerocopy as a string, which can't start with `$crate`,
+// so offsetter is named as a dependency of the crate using the option.
+#[cfg(feature = "zerocopy")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! offset_zerocopy {
+    ([zerocopy $($option:ident)*] $options:tt {[$($attr:tt)*] $($header:tt)*} $($input:tt)*) => {
+        $crate::offset!(@struct $options {[$($attr)* #[derive(
+            $crate::__private::zerocopy::FromBytes,
+            $crate::__private::zerocopy::IntoBytes,
+            $crate::__private::zerocopy::Unaligned,
+            $crate::__private::zerocopy::KnownLayout,
+            $crate::__private::zerocopy::Immutable
+        )] #[zerocopy(crate = "offsetter::__private::zerocopy")]] $($header)*} $($input)*);
     };
 
-    (@guard_with_size ($current_offset:expr, $struct_size:expr, $offset:literal $vis_field:vis $id:ident: $ty:ty, $($next:tt)+) -> {$($output:tt)*}) => {
-        offset_debug!(@guard_with_size ($offset + core::mem::size_of::<$ty>(), $struct_size, $($next)+) -> {$($output)* ($offset - ($current_offset), $vis_field $id: $ty)});
+    ([$other:ident $($option:ident)*] $($input:tt)*) => {
+        $crate::offset_zerocopy!([$($option)*] $($input)*);
     };
 
+    ([] $options:tt $($input:tt)*) => {
+        $crate::offset!(@struct $options $($input)*);
+    };
+}
 
-    ($(#[$attr:meta])* $vis:vis struct $struct_name:ident {$($input:tt)*}) => {
-        offset_debug!(@guard (0, $($input)*) -> {$(#[$attr])* $vis struct $struct_name});
-        $crate::offset_checker!($struct_name {$($input)*});
+#[cfg(not(feature = "zerocopy"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! offset_zerocopy {
+    ([zerocopy $($option:ident)*] $($input:tt)*) => {
+        compile_error!("the `zerocopy` option requires the `zerocopy` feature of offsetter");
     };
 
+    ([$other:ident $($option:ident)*] $($input:tt)*) => {
+        $crate::offset_zerocopy!([$($option)*] $($input)*);
+    };
 
-    ($(#[$attr:meta])* $vis:vis struct $struct_name:ident[$struct_size:expr] {$($input:tt)*}) => {
-        offset_debug!(@guard_with_size (0, $struct_size, $($input)*) -> {$(#[$attr])* $vis struct $struct_name});
-        $crate::offset_checker!($struct_name {$($input)*});
+    ([] $options:tt $($input:tt)*) => {
+        $crate::offset!(@struct $options $($input)*);
     };
 }
 
 #[cfg(feature = "checked")]
 #[macro_export]
 macro_rules! offset_checker {
-    ($struct_name:ident {$($offset:literal $vis_field:vis $id:ident: $ty:ty),* $(,)?}) => {
-        $(const _: () = assert!(core::mem::offset_of!($struct_name, $id) == $offset);)*
+    ($struct_name:ident [] {$(([$($kind:tt)*] $predicate:tt $attrs:tt [$($offset:tt)*] $default:tt $vis_field:vis $id:ident: $ty:ty))*}) => {
+        $($crate::offset_checker!(@$($kind)* $struct_name $predicate [$($offset)*] $id);)*
+    };
+
+    // A generic struct can't be named in a constant without concrete arguments, so it isn't checked.
+    ($struct_name:ident [$($param:ident),+] $fields:tt) => {};
+
+    (@field $struct_name:ident [$($predicate:tt)?] [$($offset:tt)*] $id:ident) => {
+        $(#[cfg $predicate])?
+        const _: () = {
+            let actual = core::mem::offset_of!($struct_name, $id);
+            let expected = $($offset)*;
+            if actual != expected {
+                let message = $crate::CheckMessage::EMPTY
+                    .str(concat!("`", stringify!($struct_name), "::", stringify!($id), "` is at offset "))
+                    .hex(actual)
+                    .str(" instead of ")
+                    .hex(expected);
+                panic!("{}", message.as_str());
+            }
+        };
+    };
+
+    // Fields reaching past the total size are reported by `@fits` in every build.
+    (@total_size $struct_name:ident [] [$struct_size:expr] ($current_offset:expr)) => {
+        const _: () = {
+            let size = $struct_size;
+            let actual = core::mem::size_of::<$struct_name>();
+            if actual != size {
+                let message = $crate::CheckMessage::EMPTY
+                    .str(concat!("`", stringify!($struct_name), "` is "))
+                    .hex(actual)
+                    .str(" bytes large instead of ")
+                    .hex(size);
+                panic!("{}", message.as_str());
+            }
+        };
+    };
+
+    (@total_size $struct_name:ident $params:tt $struct_size:tt $current_offset:tt) => {};
+
+    // Every field has to start after the end of the fields declared before it, except for the
+    // fields that are allowed to alias. Otherwise the length of the padding in front of it
+    // overflows, which doesn't say what it overlaps.
+    (@overlaps {$options:tt $attrs:tt $vis:vis $kind:ident $name:ident $size:tt $lifetimes:tt []} {$(([$($field_kind:tt)*] [$($predicate:tt)?] $field_attrs:tt [$($offset:tt)*] $default:tt $vis_field:vis $id:ident: $ty:ty))*}) => {
+        const _: () = {
+            const COUNT: usize = <[&str]>::len(&[$(stringify!($id)),*]);
+            let mut names: [&str; COUNT] = [""; COUNT];
+            let mut offsets: [usize; COUNT] = [0; COUNT];
+            let mut ends: [usize; COUNT] = [0; COUNT];
+            let mut count = 0;
+            $(
+                $(#[cfg $predicate])?
+                {
+                    let offset = $($offset)*;
+                    $crate::offset_checker!(@overlap_check [$($field_kind)*] $name $id offset names offsets ends count);
+                    names[count] = stringify!($id);
+                    offsets[count] = offset;
+                    ends[count] = offset + $crate::offset!(@extent {$options $attrs $vis $kind $name $size $lifetimes []} [$($field_kind)*] $id: $ty);
+                    count += 1;
+                }
+            )*
+            let _ = (names, offsets, ends, count);
+        };
+    };
+
+    (@overlaps $header:tt $fields:tt) => {};
+
+    (@overlap_check [overlap] $($input:tt)*) => {};
+
+    (@overlap_check [bits $low:tt $high:tt] $($input:tt)*) => {};
+
+    // The earlier field is named first, since it's usually the one whose type grew.
+    (@overlap_check $field_kind:tt $name:ident $id:ident $offset:ident $names:ident $offsets:ident $ends:ident $count:ident) => {
+        let mut index = 0;
+        while index < $count {
+            if $ends[index] > $offset {
+                let message = $crate::CheckMessage::EMPTY
+                    .str(concat!("`", stringify!($name), "::"))
+                    .str($names[index])
+                    .str("` at ")
+                    .hex($offsets[index])
+                    .str(" is ")
+                    .hex($ends[index] - $offsets[index])
+                    .str(concat!(" bytes large and doesn't fit before `", stringify!($name), "::", stringify!($id), "` at "))
+                    .hex($offset);
+                panic!("{}", message.as_str());
+            }
+            index += 1;
+        }
+    };
+
+
+    // The storage of a bitfield is covered by padding, so there is no field to check the offset of.
+    (@bits $low:tt $high:tt $struct_name:ident $predicate:tt [$($offset:tt)*] $id:ident) => {};
+
+    (@stride $elem:tt $count:tt $stride:tt $struct_name:ident $predicate:tt [$($offset:tt)*] $id:ident) => {};
+
+    (@flexible $elem:tt $struct_name:ident $predicate:tt [$($offset:tt)*] $id:ident) => {};
+
+    (@size {$options:tt $attrs:tt $vis:vis $kind:ident $name:ident $size:tt [$($lifetime:lifetime),*] []} [$($predicate:tt)?] $id:ident: $ty:ty, $expected:expr) => {
+        $(#[cfg $predicate])?
+        const _: () = {
+            const fn size<$($lifetime),*>() -> usize {
+                core::mem::size_of::<$ty>()
+            }
+
+            assert!(
+                size() == $expected,
+                concat!("`", stringify!($name), "::", stringify!($id), "` isn't `", stringify!($expected), "` bytes large")
+            );
+        };
+    };
+
+    (@size {$options:tt $attrs:tt $vis:vis $kind:ident $name:ident $size:tt $lifetimes:tt [$($params:tt)+]} $($input:tt)*) => {};
+
+    // Overlapping fields are allowed to alias anything, so there is nothing to check.
+    (@overlap $($input:tt)*) => {};
+
+    (@atomic $struct_name:ident $predicate:tt [$($offset:tt)*] $id:ident) => {
+        $crate::offset_checker!(@field $struct_name $predicate [$($offset)*] $id);
+    };
+
+    (@reserved $struct_name:ident $predicate:tt [$($offset:tt)*] $id:ident) => {
+        $crate::offset_checker!(@field $struct_name $predicate [$($offset)*] $id);
+    };
+
+    // Only generic structs aren't covered by `@overlaps`.
+    (@reserved_start {$options:tt $attrs:tt $vis:vis $kind:ident $name:ident $size:tt $lifetimes:tt []} $($input:tt)*) => {};
+
+    (@reserved_start {$options:tt $attrs:tt $vis:vis $kind:ident $name:ident $size:tt $lifetimes:tt $params:tt} [$($predicate:tt)?] ($current_offset:expr) [$($offset:tt)*] $id:ident) => {
+        $(#[cfg $predicate])?
+        const _: () = assert!(
+            $current_offset <= ($($offset)*),
+            concat!("reserved region `", stringify!($name), "::", stringify!($id), "` overlaps the fields before it")
+        );
+    };
+
+    (@reserved {$options:tt $attrs:tt $vis:vis $kind:ident $name:ident $size:tt $lifetimes:tt []} [$($predicate:tt)?] [$($offset:tt)*] $id:ident: $ty:ty, [$($next_offset:tt)*] $next_id:ident) => {
+        $(#[cfg $predicate])?
+        const _: () = assert!(
+            ($($offset)*) + $crate::offset!(@size_of {$options $attrs $vis $kind $name $size $lifetimes []} $id: $ty) <= ($($next_offset)*),
+            concat!("reserved region `", stringify!($name), "::", stringify!($id), "` overlaps `", stringify!($next_id), "`")
+        );
     };
+
+    (@reserved {$options:tt $attrs:tt $vis:vis $kind:ident $name:ident $size:tt $lifetimes:tt [$($params:tt)+]} $($input:tt)*) => {};
+
+    (@embed {$options:tt $attrs:tt $vis:vis $kind:ident $name:ident $size:tt $lifetimes:tt []} [$($predicate:tt)?] [$($offset:tt)*] $id:ident: $ty:ty, [$($next_offset:tt)*] $next_id:ident) => {
+        $(#[cfg $predicate])?
+        const _: () = assert!(
+            ($($offset)*) + $crate::offset!(@size_of {$options $attrs $vis $kind $name $size $lifetimes []} $id: $ty) == ($($next_offset)*),
+            concat!("embedded `", stringify!($name), "::", stringify!($id), "` doesn't end at the offset of `", stringify!($next_id), "`")
+        );
+    };
+
+    (@embed {$options:tt $attrs:tt $vis:vis $kind:ident $name:ident $size:tt $lifetimes:tt [$($params:tt)+]} $($input:tt)*) => {};
 }
 
 #[cfg(not(feature = "checked"))]
 #[macro_export]
 macro_rules! offset_checker {
-    ($struct_name:ident {$($offset:literal $vis_field:vis $id:ident: $ty:ty),* $(,)?}) => {};
+    ($($input:tt)*) => {};
 }